@@ -0,0 +1,667 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use attps_rust_sdk::{
+    AgentHeader, AgentManifest, AgentSettings, AttpsClient, BatchVerifier, Gateway, GatewayConfig, GatewayRoute,
+    Onboarding, OwnershipTransfer, Plan, ReviewPolicy, ReviewQueue, SecretKey, VerifyOutcome, VerifyRequest,
+};
+use ethers::types::{Address, U256};
+use clap::{Args, Subcommand};
+use serde_json::{json, Value};
+
+use crate::output::Output;
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// AgentProxy methods.
+    #[command(subcommand)]
+    Proxy(ProxyCommand),
+    /// AgentManager methods.
+    #[command(subcommand)]
+    Manager(ManagerCommand),
+    /// AgentFactory methods.
+    #[command(subcommand)]
+    Factory(FactoryCommand),
+    /// Compare an agent manifest with the chain and apply the difference.
+    #[command(subcommand)]
+    Manifest(ManifestCommand),
+    /// Create or register an agent, accept it and confirm, resuming from a journal.
+    Onboard {
+        /// An agent already created through the AgentFactory; a new one is created otherwise.
+        #[arg(long)]
+        agent: Option<String>,
+        /// Journal file that makes an interrupted onboarding resumable.
+        #[arg(long)]
+        journal: Option<String>,
+        #[command(flatten)]
+        settings: AgentSettingsArgs,
+    },
+    /// Review registering agents against a policy and accept or reject them in batches.
+    Review {
+        #[command(subcommand)]
+        command: ReviewCommand,
+        #[command(flatten)]
+        policy: ReviewPolicyArgs,
+    },
+    /// Two-step ownership transfer of the AgentProxy and AgentManager together.
+    #[command(subcommand)]
+    Ownership(OwnershipCommand),
+    /// Serve the SDK over a REST/JSON API until interrupted.
+    Gateway(GatewayArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct GatewayArgs {
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: String,
+    /// Accepted API keys, comma-separated.
+    #[arg(long = "api-key", env = "ATTPS_GATEWAY_API_KEYS", value_delimiter = ',', hide_env_values = true)]
+    api_keys: Vec<SecretKey>,
+    #[arg(long)]
+    allow_unauthenticated: bool,
+    /// Turn a route on, e.g. `proxy.verify`. Write routes are off by default.
+    #[arg(long)]
+    enable: Vec<String>,
+    /// Turn a route off, e.g. `manager.agent_configs`.
+    #[arg(long)]
+    disable: Vec<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ManifestCommand {
+    /// Show what `apply` would change.
+    Plan {
+        /// YAML or TOML manifest.
+        manifest: PathBuf,
+        /// Save the plan as JSON for a later `apply --plan`.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Send the transactions for a manifest, or for a plan saved with `plan --out`.
+    Apply {
+        #[arg(required_unless_present = "plan")]
+        manifest: Option<PathBuf>,
+        #[arg(long, conflicts_with = "manifest")]
+        plan: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ReviewCommand {
+    /// List registering agents with their settings and policy violations.
+    List,
+    /// Accept agents that pass the policy.
+    Accept { agents: Vec<String> },
+    /// Reject agents with `remove_agent`.
+    Reject { agents: Vec<String> },
+    /// Accept every agent that passes the policy.
+    Process {
+        /// Also reject the agents that fail it.
+        #[arg(long)]
+        reject_failing: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct ReviewPolicyArgs {
+    #[arg(long, global = true)]
+    min_threshold: Option<u8>,
+    /// Allowed signer; repeat for each. Any signer is allowed when omitted.
+    #[arg(long = "allowed-signer", global = true)]
+    allowed_signers: Vec<String>,
+    /// Accepted settings version; repeat for each. Any version is accepted when omitted.
+    #[arg(long = "accepted-version", global = true)]
+    versions: Vec<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum OwnershipCommand {
+    /// Show the owner and pending owner of each contract.
+    Status,
+    /// Start the transfer of every contract to a new owner.
+    Transfer { new_owner: String },
+    /// Accept every transfer pending to the configured wallet.
+    Accept,
+    /// Wait until every contract has been transferred to the new owner.
+    Wait {
+        new_owner: String,
+        /// Seconds to wait.
+        #[arg(long, default_value_t = 600)]
+        timeout: u64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProxyCommand {
+    GetAgentFactory,
+    GetAgentManager,
+    GetOwner,
+    GetTypeAndVersion,
+    AcceptOwnership,
+    SetAgentFactory { factory: String },
+    SetAgentManager { manager: String },
+    TransferOwnership { new_owner: String },
+    CreateAndRegisterAgent {
+        #[command(flatten)]
+        settings: AgentSettingsArgs,
+    },
+    /// Verifies one report. The bundle is a JSON object with the fields of a batch request.
+    Verify {
+        #[arg(long)]
+        bundle: PathBuf,
+        /// Simulate with `eth_call` instead of sending a transaction.
+        #[arg(long)]
+        dry_run: bool,
+        /// Block to simulate at, with `--dry-run`.
+        #[arg(long, requires = "dry_run")]
+        block: Option<u64>,
+    },
+    /// Verifies a JSON array of requests, each with a unique `id`.
+    VerifyBatch {
+        #[arg(long)]
+        bundle: PathBuf,
+        #[arg(long, default_value_t = 8)]
+        max_in_flight: usize,
+        /// Journal file that makes an interrupted batch resumable.
+        #[arg(long)]
+        journal: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ManagerCommand {
+    AgentProxy,
+    GetOwner,
+    GetTypeAndVersion,
+    AgentVersion,
+    AllowedAgent { agent: String },
+    AllowedSigner { agent: String, settings_digest: String, signer: String },
+    GetAgentConfig { agent: String, settings_digest: String },
+    GetAgentConfigs { agent: String },
+    GetAgentConfigsCount { agent: String },
+    GetAgentConfigsInRange { agent: String, start: u64, end: u64 },
+    GetAllAllowedAgents,
+    GetAllRegisteringAgents,
+    GetAllowedAgentsCount,
+    GetAllowedAgentsInRange { start: u64, end: u64 },
+    GetRegisteringAgentsCount,
+    GetRegisteringAgentsInRange { start: u64, end: u64 },
+    IsValidMessageId { message_id: String },
+    IsValidSourceAgentId { source_agent_id: String },
+    SignerThreshold { agent: String, settings_digest: String },
+    ValidateDataConversion { agent: String, data: String },
+    AcceptAgent { agent: String },
+    AcceptAgentSettingProposal { agent: String },
+    AcceptOwnership,
+    ChangeAgentSettingProposal {
+        agent: String,
+        #[command(flatten)]
+        settings: AgentSettingsArgs,
+    },
+    RegisterAgent {
+        agent: String,
+        #[command(flatten)]
+        settings: AgentSettingsArgs,
+    },
+    RemoveAgent { agent: String },
+    SetAgentProxy { proxy: String },
+    TransferOwnership { new_owner: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum FactoryCommand {
+    GetAgentManager,
+    GetAgentProxy,
+    GetAgentsCount,
+    GetAgentsInRange { start: u64, end: u64 },
+    GetAllAgents,
+    HasAgent { agent: String },
+    TypeAndVersion,
+}
+
+#[derive(Debug, Args)]
+pub struct AgentSettingsArgs {
+    /// Signer address; repeat for each signer.
+    #[arg(long = "signer", required = true)]
+    signers: Vec<String>,
+    #[arg(long)]
+    threshold: u8,
+    #[arg(long, default_value = "0x0000000000000000000000000000000000000000")]
+    converter: String,
+    #[arg(long, default_value = "1.0")]
+    settings_version: String,
+    #[arg(long)]
+    message_id: String,
+    #[arg(long)]
+    source_agent_id: String,
+    #[arg(long)]
+    source_agent_name: String,
+    #[arg(long)]
+    target_agent_id: String,
+    /// Unix timestamp; defaults to now.
+    #[arg(long)]
+    timestamp: Option<u64>,
+    #[arg(long, default_value_t = 0)]
+    message_type: u8,
+    #[arg(long, default_value_t = 1)]
+    priority: u8,
+    #[arg(long, default_value_t = 3600)]
+    ttl: u64,
+}
+
+impl AgentSettingsArgs {
+    fn signers(&self) -> Vec<&str> {
+        self.signers.iter().map(String::as_str).collect()
+    }
+
+    fn settings(&self) -> Result<AgentSettings, String> {
+        let signers = self
+            .signers
+            .iter()
+            .map(|signer| signer.parse::<Address>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Invalid address format in signers: {}", e))?;
+        Ok(AgentSettings {
+            signers,
+            threshold: self.threshold,
+            converter_address: self.converter.parse().map_err(|e| format!("Invalid converter address: {}", e))?,
+            agent_header: AgentHeader {
+                version: self.settings_version.clone(),
+                message_id: self.message_id.clone(),
+                source_agent_id: self.source_agent_id.clone(),
+                source_agent_name: self.source_agent_name.clone(),
+                target_agent_id: self.target_agent_id.clone(),
+                timestamp: U256::from(self.timestamp()),
+                message_type: self.message_type,
+                priority: self.priority,
+                ttl: U256::from(self.ttl),
+            },
+        })
+    }
+
+    fn timestamp(&self) -> u64 {
+        self.timestamp.unwrap_or_else(|| {
+            SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or_default()
+        })
+    }
+}
+
+impl Command {
+    /// Whether the command needs the configured wallet to sign transactions.
+    pub fn sends_transactions(&self) -> bool {
+        match self {
+            Command::Proxy(command) => matches!(
+                command,
+                ProxyCommand::AcceptOwnership
+                    | ProxyCommand::SetAgentFactory { .. }
+                    | ProxyCommand::SetAgentManager { .. }
+                    | ProxyCommand::TransferOwnership { .. }
+                    | ProxyCommand::CreateAndRegisterAgent { .. }
+                    | ProxyCommand::Verify { dry_run: false, .. }
+                    | ProxyCommand::VerifyBatch { .. }
+            ),
+            Command::Manager(command) => matches!(
+                command,
+                ManagerCommand::AcceptAgent { .. }
+                    | ManagerCommand::AcceptAgentSettingProposal { .. }
+                    | ManagerCommand::AcceptOwnership
+                    | ManagerCommand::ChangeAgentSettingProposal { .. }
+                    | ManagerCommand::RegisterAgent { .. }
+                    | ManagerCommand::RemoveAgent { .. }
+                    | ManagerCommand::SetAgentProxy { .. }
+                    | ManagerCommand::TransferOwnership { .. }
+            ),
+            Command::Factory(_) => false,
+            Command::Manifest(command) => matches!(command, ManifestCommand::Apply { .. }),
+            Command::Onboard { .. } => true,
+            Command::Review { command, .. } => !matches!(command, ReviewCommand::List),
+            Command::Ownership(command) => {
+                matches!(command, OwnershipCommand::Transfer { .. } | OwnershipCommand::Accept)
+            }
+            Command::Gateway(args) => args.config().is_ok_and(|config| {
+                GatewayRoute::ALL.iter().any(|route| route.writes() && config.is_enabled(*route))
+            }),
+        }
+    }
+
+    pub async fn execute(&self, client: &AttpsClient) -> Result<Output, String> {
+        match self {
+            Command::Proxy(command) => command.execute(client).await,
+            Command::Manager(command) => command.execute(client).await.map(Output::ok),
+            Command::Factory(command) => command.execute(client).await.map(Output::ok),
+            Command::Manifest(command) => command.execute(client).await,
+            Command::Onboard { agent, journal, settings } => {
+                // a resumed run has to send the same settings as the first one
+                if journal.is_some() && settings.timestamp.is_none() {
+                    return Err("--timestamp is required with --journal".to_string());
+                }
+                let agent = agent
+                    .as_deref()
+                    .map(|agent| agent.parse::<Address>().map_err(|e| format!("Invalid address format: {}", e)))
+                    .transpose()?;
+                let report = Onboarding::new(client, journal.as_deref()).onboard_agent(&settings.settings()?, agent).await?;
+                serde_json::to_value(report).map(Output::ok).map_err(|e| e.to_string())
+            }
+            Command::Review { command, policy } => {
+                let queue = ReviewQueue::new(client.manager(), policy.policy()?);
+                let result = match command {
+                    ReviewCommand::List => serde_json::to_value(queue.applicants().await?),
+                    ReviewCommand::Accept { agents } => serde_json::to_value(queue.accept(&parse_addresses(agents)?).await?),
+                    ReviewCommand::Reject { agents } => serde_json::to_value(queue.reject(&parse_addresses(agents)?).await?),
+                    ReviewCommand::Process { reject_failing } => serde_json::to_value(queue.process(*reject_failing).await?),
+                };
+                let result = result.map_err(|e| e.to_string())?;
+                // any per-agent error fails the command, after printing every report
+                let errors = result.as_array().map_or(0, |reports| reports.iter().filter(|report| !report["error"].is_null()).count());
+                Ok(match errors {
+                    0 => Output::ok(result),
+                    errors => Output::failed(result, format!("{} agents failed", errors)),
+                })
+            }
+            Command::Ownership(command) => command.execute(client).await.map(Output::ok),
+            Command::Gateway(_) => Err("The gateway is started with GatewayArgs::serve".to_string()),
+        }
+    }
+}
+
+impl GatewayArgs {
+    fn config(&self) -> Result<GatewayConfig, String> {
+        let mut config = GatewayConfig {
+            api_keys: self.api_keys.clone(),
+            allow_unauthenticated: self.allow_unauthenticated,
+            ..Default::default()
+        };
+        for (names, enabled) in [(&self.enable, true), (&self.disable, false)] {
+            for name in names {
+                GatewayRoute::from_name(name)?;
+                config.routes.insert(name.clone(), enabled);
+            }
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub async fn serve(&self, client: AttpsClient) -> Result<Output, String> {
+        let gateway = Gateway::new(client, self.config()?)?;
+        let listener = tokio::net::TcpListener::bind(&self.listen)
+            .await
+            .map_err(|e| format!("Failed to listen on {}: {}", self.listen, e))?;
+        eprintln!("Gateway listening on {}", self.listen);
+        gateway.serve(listener).await?;
+        Ok(Output::ok(Value::Null))
+    }
+}
+
+impl ProxyCommand {
+    async fn execute(&self, client: &AttpsClient) -> Result<Output, String> {
+        let proxy = client.proxy();
+        let result = match self {
+            ProxyCommand::GetAgentFactory => proxy.get_agent_factory().await?,
+            ProxyCommand::GetAgentManager => proxy.get_agent_manager().await?,
+            ProxyCommand::GetOwner => proxy.get_owner().await?,
+            ProxyCommand::GetTypeAndVersion => proxy.get_type_and_version().await?,
+            ProxyCommand::AcceptOwnership => proxy.accept_ownership().await?,
+            ProxyCommand::SetAgentFactory { factory } => proxy.set_agent_factory(factory).await?,
+            ProxyCommand::SetAgentManager { manager } => proxy.set_agent_manager(manager).await?,
+            ProxyCommand::TransferOwnership { new_owner } => proxy.transfer_ownership(new_owner).await?,
+            ProxyCommand::CreateAndRegisterAgent { settings } => {
+                proxy
+                    .create_and_register_agent(
+                        settings.signers(),
+                        settings.threshold,
+                        &settings.converter,
+                        &settings.settings_version,
+                        &settings.message_id,
+                        &settings.source_agent_id,
+                        &settings.source_agent_name,
+                        &settings.target_agent_id,
+                        settings.timestamp(),
+                        settings.message_type,
+                        settings.priority,
+                        settings.ttl,
+                    )
+                    .await?
+            }
+            ProxyCommand::Verify { bundle, dry_run, block } => {
+                let request = read_verify_request(bundle)?;
+                if *dry_run {
+                    let result = proxy
+                        .verify_call(
+                            &request.agent,
+                            &request.settings_digest,
+                            &request.data,
+                            &request.data_hash,
+                            &request.signature_proof,
+                            &request.zk_proof,
+                            &request.merkle_proof,
+                            &request.content_type,
+                            &request.encoding,
+                            &request.compression,
+                            *block,
+                        )
+                        .await?;
+                    let output = json!({ "passed": result.passed, "revert_reason": result.revert_reason });
+                    return Ok(match result.revert_reason {
+                        None => Output::ok(output),
+                        Some(reason) => Output::failed(output, format!("Verification reverted: {}", reason)),
+                    });
+                }
+                proxy
+                    .verify(
+                        &request.agent,
+                        &request.settings_digest,
+                        &request.data,
+                        &request.data_hash,
+                        &request.signature_proof,
+                        &request.zk_proof,
+                        &request.merkle_proof,
+                        &request.content_type,
+                        &request.encoding,
+                        &request.compression,
+                    )
+                    .await?
+            }
+            ProxyCommand::VerifyBatch { bundle, max_in_flight, journal } => {
+                let requests: Vec<VerifyRequest> = serde_json::from_value(read_json(bundle)?)
+                    .map_err(|e| format!("Invalid bundle {}: {}", bundle.display(), e))?;
+                let reports = BatchVerifier::new(proxy, *max_in_flight, journal.as_deref())
+                    .submit(&requests)
+                    .await?;
+                let failed = reports
+                    .iter()
+                    .filter(|report| !matches!(report.outcome, VerifyOutcome::Confirmed { .. }))
+                    .count();
+                let output = serde_json::to_value(&reports).map_err(|e| e.to_string())?;
+                return Ok(match failed {
+                    0 => Output::ok(output),
+                    failed => Output::failed(output, format!("{} of {} verifications failed", failed, reports.len())),
+                });
+            }
+        };
+        Ok(Output::ok(result))
+    }
+}
+
+impl ManagerCommand {
+    async fn execute(&self, client: &AttpsClient) -> Result<Value, String> {
+        let manager = client.manager();
+        Ok(match self {
+            ManagerCommand::AgentProxy => manager.agent_proxy().await?.into(),
+            ManagerCommand::GetOwner => manager.get_owner().await?.into(),
+            ManagerCommand::GetTypeAndVersion => manager.get_type_and_version().await?.into(),
+            ManagerCommand::AgentVersion => manager.agent_version().await?.into(),
+            ManagerCommand::AllowedAgent { agent } => manager.allowed_agent(agent).await?.into(),
+            ManagerCommand::AllowedSigner { agent, settings_digest, signer } => {
+                manager.allowed_signer(agent, settings_digest, signer).await?.into()
+            }
+            ManagerCommand::GetAgentConfig { agent, settings_digest } => {
+                manager.get_agent_config(agent, settings_digest).await?.into()
+            }
+            ManagerCommand::GetAgentConfigs { agent } => manager.get_agent_configs(agent).await?.into(),
+            ManagerCommand::GetAgentConfigsCount { agent } => manager.get_agent_configs_count(agent).await?.into(),
+            ManagerCommand::GetAgentConfigsInRange { agent, start, end } => {
+                manager.get_agent_configs_in_range(agent, *start, *end).await?.into()
+            }
+            ManagerCommand::GetAllAllowedAgents => manager.get_all_allowed_agents().await?.into(),
+            ManagerCommand::GetAllRegisteringAgents => manager.get_all_registering_agents().await?.into(),
+            ManagerCommand::GetAllowedAgentsCount => manager.get_allowed_agents_count().await?.into(),
+            ManagerCommand::GetAllowedAgentsInRange { start, end } => {
+                manager.get_allowed_agents_in_range(*start, *end).await?.into()
+            }
+            ManagerCommand::GetRegisteringAgentsCount => manager.get_registering_agents_count().await?.into(),
+            ManagerCommand::GetRegisteringAgentsInRange { start, end } => {
+                manager.get_registering_agents_in_range(*start, *end).await?.into()
+            }
+            ManagerCommand::IsValidMessageId { message_id } => manager.is_valid_message_id(message_id).await?.into(),
+            ManagerCommand::IsValidSourceAgentId { source_agent_id } => {
+                manager.is_valid_source_agent_id(source_agent_id).await?.into()
+            }
+            ManagerCommand::SignerThreshold { agent, settings_digest } => {
+                manager.signer_threshold(agent, settings_digest).await?.into()
+            }
+            ManagerCommand::ValidateDataConversion { agent, data } => {
+                manager.validate_data_conversion(agent, data).await?.into()
+            }
+            ManagerCommand::AcceptAgent { agent } => manager.accept_agent(agent).await?.into(),
+            ManagerCommand::AcceptAgentSettingProposal { agent } => {
+                manager.accept_agent_setting_proposal(agent).await?.into()
+            }
+            ManagerCommand::AcceptOwnership => manager.accept_ownership().await?.into(),
+            ManagerCommand::ChangeAgentSettingProposal { agent, settings } => manager
+                .change_agent_setting_proposal(
+                    agent,
+                    settings.signers(),
+                    settings.threshold,
+                    &settings.converter,
+                    &settings.settings_version,
+                    &settings.message_id,
+                    &settings.source_agent_id,
+                    &settings.source_agent_name,
+                    &settings.target_agent_id,
+                    settings.timestamp(),
+                    settings.message_type,
+                    settings.priority,
+                    settings.ttl,
+                )
+                .await?
+                .into(),
+            ManagerCommand::RegisterAgent { agent, settings } => manager
+                .register_agent(
+                    agent,
+                    settings.signers(),
+                    settings.threshold,
+                    &settings.converter,
+                    &settings.settings_version,
+                    &settings.message_id,
+                    &settings.source_agent_id,
+                    &settings.source_agent_name,
+                    &settings.target_agent_id,
+                    settings.timestamp(),
+                    settings.message_type,
+                    settings.priority,
+                    settings.ttl,
+                )
+                .await?
+                .into(),
+            ManagerCommand::RemoveAgent { agent } => manager.remove_agent(agent).await?.into(),
+            ManagerCommand::SetAgentProxy { proxy } => manager.set_agent_proxy(proxy).await?.into(),
+            ManagerCommand::TransferOwnership { new_owner } => manager.transfer_ownership(new_owner).await?.into(),
+        })
+    }
+}
+
+impl FactoryCommand {
+    async fn execute(&self, client: &AttpsClient) -> Result<Value, String> {
+        let factory = client.factory();
+        Ok(match self {
+            FactoryCommand::GetAgentManager => factory.get_agent_manager().await?.into(),
+            FactoryCommand::GetAgentProxy => factory.get_agent_proxy().await?.into(),
+            FactoryCommand::GetAgentsCount => factory.get_agents_count().await?.into(),
+            FactoryCommand::GetAgentsInRange { start, end } => factory.get_agents_in_range(*start, *end).await?.into(),
+            FactoryCommand::GetAllAgents => factory.get_all_agents().await?.into(),
+            FactoryCommand::HasAgent { agent } => factory.has_agent(agent).await?.into(),
+            FactoryCommand::TypeAndVersion => factory.type_and_version().await?.into(),
+        })
+    }
+}
+
+impl ManifestCommand {
+    async fn execute(&self, client: &AttpsClient) -> Result<Output, String> {
+        match self {
+            ManifestCommand::Plan { manifest, out } => {
+                let plan = AgentManifest::from_file(manifest)?.plan(client.manager()).await?;
+                let json = serde_json::to_value(&plan).map_err(|e| e.to_string())?;
+                if let Some(out) = out {
+                    let contents = serde_json::to_string_pretty(&plan).map_err(|e| e.to_string())?;
+                    std::fs::write(out, contents)
+                        .map_err(|e| format!("Failed to write plan {}: {}", out.display(), e))?;
+                }
+                Ok(Output::ok(json).with_text(plan.to_string()))
+            }
+            ManifestCommand::Apply { manifest, plan } => {
+                let plan: Plan = match (manifest, plan) {
+                    (_, Some(path)) => serde_json::from_value(read_json(path)?)
+                        .map_err(|e| format!("Invalid plan {}: {}", path.display(), e))?,
+                    (Some(manifest), None) => AgentManifest::from_file(manifest)?.plan(client.manager()).await?,
+                    (None, None) => return Err("Either a manifest or --plan is required".to_string()),
+                };
+                let applied = plan.apply(client.manager()).await?;
+                let text = format!("{}\nApplied {} changes", plan, applied.len());
+                Ok(Output::ok(serde_json::to_value(&applied).map_err(|e| e.to_string())?).with_text(text))
+            }
+        }
+    }
+}
+
+impl ReviewPolicyArgs {
+    fn policy(&self) -> Result<ReviewPolicy, String> {
+        Ok(ReviewPolicy {
+            min_threshold: self.min_threshold,
+            allowed_signers: match self.allowed_signers.is_empty() {
+                true => None,
+                false => Some(parse_addresses(&self.allowed_signers)?),
+            },
+            versions: match self.versions.is_empty() {
+                true => None,
+                false => Some(self.versions.clone()),
+            },
+        })
+    }
+}
+
+impl OwnershipCommand {
+    async fn execute(&self, client: &AttpsClient) -> Result<Value, String> {
+        let ownership = OwnershipTransfer::new(client);
+        let result = match self {
+            OwnershipCommand::Status => serde_json::to_value(ownership.status().await?),
+            OwnershipCommand::Transfer { new_owner } => serde_json::to_value(ownership.transfer_all(new_owner).await?),
+            OwnershipCommand::Accept => serde_json::to_value(ownership.accept_all().await?),
+            OwnershipCommand::Wait { new_owner, timeout } => {
+                serde_json::to_value(ownership.wait_for_transfer(new_owner, Duration::from_secs(*timeout)).await?)
+            }
+        };
+        result.map_err(|e| e.to_string())
+    }
+}
+
+fn parse_addresses(addresses: &[String]) -> Result<Vec<Address>, String> {
+    addresses
+        .iter()
+        .map(|address| address.parse::<Address>().map_err(|e| format!("Invalid address format: {}", e)))
+        .collect()
+}
+
+fn read_json(path: &Path) -> Result<Value, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("Invalid JSON in {}: {}", path.display(), e))
+}
+
+fn read_verify_request(path: &Path) -> Result<VerifyRequest, String> {
+    let mut bundle = read_json(path)?;
+    // a single bundle needs no id
+    if let Some(fields) = bundle.as_object_mut() {
+        fields.entry("id").or_insert_with(|| json!(""));
+    }
+    serde_json::from_value(bundle).map_err(|e| format!("Invalid bundle {}: {}", path.display(), e))
+}
@@ -0,0 +1,123 @@
+mod commands;
+mod output;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use attps_rust_sdk::{AttpsClient, AttpsConfig, ConnectOptions, SecretKey, VersionPolicy, WalletSource};
+use clap::Parser;
+use ethers::core::rand::thread_rng;
+use ethers::signers::LocalWallet;
+
+use crate::commands::Command;
+use crate::output::{Format, Output};
+
+/// Command-line access to the ATTPS AgentProxy, AgentManager and AgentFactory contracts.
+#[derive(Debug, Parser)]
+#[command(name = "attps", version)]
+struct Cli {
+    /// Config file (.toml or .json) with profiles; without it the environment is used.
+    #[arg(long, global = true, env = "ATTPS_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Profile to use, e.g. `sepolia` or one from the config file.
+    #[arg(long, global = true, env = "ATTPS_PROFILE")]
+    profile: Option<String>,
+
+    #[arg(long, short, global = true, value_enum, default_value_t = Format::Human)]
+    output: Format,
+
+    /// Do not read the contract versions at connect (an unsupported version only warns).
+    #[arg(long, global = true)]
+    skip_version_check: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+fn load_config(cli: &Cli) -> Result<AttpsConfig, String> {
+    match (&cli.config, &cli.profile) {
+        (Some(path), profile) => AttpsConfig::from_file(path, profile.as_deref()),
+        (None, Some(profile)) => AttpsConfig::from_lookup(|key| match key {
+            "ATTPS_PROFILE" => Some(profile.clone()),
+            _ => std::env::var(key).ok(),
+        }),
+        (None, None) => AttpsConfig::from_env(),
+    }
+}
+
+async fn run(cli: &Cli) -> Result<Output, String> {
+    let mut config = load_config(cli)?;
+
+    // read-only commands need a signer to build the nodes, but never use its key
+    if config.wallet.is_none() && !cli.command.sends_transactions() {
+        let wallet = LocalWallet::new(&mut thread_rng());
+        let key = SecretKey::new(hex::encode(wallet.signer().to_bytes()));
+        config.wallet = Some(WalletSource::PrivateKey(key));
+    }
+
+    let mut options = ConnectOptions::builtin();
+    if cli.skip_version_check {
+        options.version_policy = VersionPolicy::Skip;
+    }
+    let client = AttpsClient::from_config_with_options(&config, &options).await?;
+
+    if let Command::Gateway(args) = &cli.command {
+        return args.serve(client).await;
+    }
+    cli.command.execute(&client).await
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenvy::dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .init();
+
+    let cli = Cli::parse();
+    match run(&cli).await {
+        Ok(output) => {
+            output.print(cli.output);
+            if output.succeeded() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Err(e) => {
+            Output::error(e).print(cli.output);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_arguments() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from(["attps", "-o", "json", "manager", "get-agent-configs", "0x0000000000000000000000000000000000000001"]).unwrap();
+        assert_eq!(cli.output, Format::Json);
+        assert!(!cli.command.sends_transactions());
+
+        let cli = Cli::try_parse_from([
+            "attps", "manager", "register-agent", "0x0000000000000000000000000000000000000001",
+            "--signer", "0x0000000000000000000000000000000000000002", "--threshold", "1",
+            "--message-id", "m", "--source-agent-id", "s", "--source-agent-name", "Source", "--target-agent-id", "t",
+        ]).unwrap();
+        assert!(cli.command.sends_transactions());
+
+        assert!(Cli::try_parse_from(["attps", "proxy", "verify"]).is_err());
+
+        let cli = Cli::try_parse_from(["attps", "gateway", "--api-key", "a,b", "--enable", "proxy.verify"]).unwrap();
+        assert!(cli.command.sends_transactions());
+        let cli = Cli::try_parse_from(["attps", "gateway", "--api-key", "a"]).unwrap();
+        assert!(!cli.command.sends_transactions());
+    }
+}
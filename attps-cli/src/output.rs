@@ -0,0 +1,68 @@
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Human,
+    Json,
+}
+
+/// What a command prints. A command can produce a result and still fail, e.g. a
+/// verification that reverts; the process then exits with a non-zero code.
+#[derive(Debug)]
+pub struct Output {
+    result: Value,
+    /// Printed instead of `result` in human-readable output.
+    text: Option<String>,
+    error: Option<String>,
+}
+
+impl Output {
+    pub fn ok(result: impl Into<Value>) -> Self {
+        Self { result: result.into(), text: None, error: None }
+    }
+
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    pub fn failed(result: impl Into<Value>, error: impl Into<String>) -> Self {
+        Self { result: result.into(), text: None, error: Some(error.into()) }
+    }
+
+    pub fn error(error: impl Into<String>) -> Self {
+        Self { result: Value::Null, text: None, error: Some(error.into()) }
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+
+    pub fn print(&self, format: Format) {
+        match format {
+            Format::Json => {
+                let mut output = json!({ "ok": self.succeeded() });
+                if !self.result.is_null() {
+                    output["result"] = self.result.clone();
+                }
+                if let Some(error) = &self.error {
+                    output["error"] = json!(error);
+                }
+                println!("{}", output);
+            }
+            Format::Human => {
+                match (&self.text, &self.result) {
+                    (Some(text), _) => println!("{}", text),
+                    (None, Value::Null) => {}
+                    (None, Value::String(result)) => println!("{}", result),
+                    (None, Value::Bool(_) | Value::Number(_)) => println!("{}", self.result),
+                    (None, result) => println!("{}", serde_json::to_string_pretty(result).unwrap_or_default()),
+                }
+                if let Some(error) = &self.error {
+                    eprintln!("Error: {}", error);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,598 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use ethers::signers::LocalWallet;
+use ethers::types::Address;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::core::consts::{
+    LOCAL_CHAIN_ID, LOCAL_PROVIDER, SEPOLIA_CHAIN_ID, SEPOLIA_PROVIDER,
+};
+use crate::core::deployments::{Deployment, DeploymentRegistry};
+use crate::core::transport::{split_provider_urls, AttpsTransport, EndpointLimits};
+use crate::core::ApiResult;
+use crate::utils::secret::{expose_secrets, SecretKey};
+use crate::utils::signer::AttpsSigner;
+use crate::utils::wallet::{PasswordSource, WalletSource};
+
+pub const ENV_PROFILE: &str = "ATTPS_PROFILE";
+pub const ENV_AGENTPROXY_PROVIDER: &str = "AGENTPROXY_PROVIDER";
+pub const ENV_AGENTPROXY_CONTRACT: &str = "AGENTPROXY_CONTRACT";
+pub const ENV_AGENTPROXY_READ_QUORUM: &str = "AGENTPROXY_READ_QUORUM";
+pub const ENV_AGENTPROXY_RATE_LIMIT: &str = "AGENTPROXY_RATE_LIMIT";
+pub const ENV_AGENTPROXY_MAX_IN_FLIGHT: &str = "AGENTPROXY_MAX_IN_FLIGHT";
+pub const ENV_WALLET_PRIVATE_KEY: &str = "WALLET_PRIVATE_KEY";
+pub const ENV_WALLET_KEYSTORE: &str = "WALLET_KEYSTORE";
+pub const ENV_WALLET_KEYSTORE_PASSWORD: &str = "WALLET_KEYSTORE_PASSWORD";
+pub const ENV_WALLET_MNEMONIC: &str = "WALLET_MNEMONIC";
+pub const ENV_WALLET_DERIVATION_PATH: &str = "WALLET_DERIVATION_PATH";
+pub const ENV_WALLET_REMOTE_SIGNER_URL: &str = "WALLET_REMOTE_SIGNER_URL";
+pub const ENV_WALLET_REMOTE_SIGNER_ADDRESS: &str = "WALLET_REMOTE_SIGNER_ADDRESS";
+
+/// One network profile as written in a config file. Every field is optional so a
+/// file only has to spell out what differs from the built-in profile of the same name.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ProfileConfig {
+    /// One URL, or several separated by commas for failover.
+    pub provider_url: Option<String>,
+    pub read_quorum: Option<usize>,
+    /// Limits for every provider URL.
+    pub limits: Option<EndpointLimits>,
+    /// Limits for single provider URLs, on top of `limits`.
+    #[serde(default)]
+    pub endpoint_limits: HashMap<String, EndpointLimits>,
+    pub agent_proxy_contract: Option<String>,
+    pub wallet_private_key: Option<SecretKey>,
+    pub wallet_keystore: Option<String>,
+    pub wallet_keystore_password_env: Option<String>,
+    pub wallet_keystore_password_file: Option<String>,
+    pub wallet_mnemonic: Option<SecretKey>,
+    pub wallet_derivation_path: Option<String>,
+    pub wallet_remote_signer_url: Option<String>,
+    pub wallet_remote_signer_address: Option<String>,
+    pub chain_id: Option<u64>,
+}
+
+impl ProfileConfig {
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "sepolia" => Some(Self {
+                provider_url: Some(SEPOLIA_PROVIDER.to_string()),
+                chain_id: Some(SEPOLIA_CHAIN_ID),
+                ..Default::default()
+            }),
+            "local" => Some(Self {
+                provider_url: Some(LOCAL_PROVIDER.to_string()),
+                chain_id: Some(LOCAL_CHAIN_ID),
+                ..Default::default()
+            }),
+            _ => None,
+        }
+    }
+
+    fn has_wallet(&self) -> bool {
+        self.wallet_private_key.is_some()
+            || self.wallet_keystore.is_some()
+            || self.wallet_mnemonic.is_some()
+            || self.wallet_remote_signer_url.is_some()
+    }
+
+    fn merge(mut self, other: ProfileConfig) -> Self {
+        // a wallet set at a higher level replaces the lower one instead of mixing with it
+        if other.has_wallet() {
+            self.wallet_private_key = None;
+            self.wallet_keystore = None;
+            self.wallet_keystore_password_env = None;
+            self.wallet_keystore_password_file = None;
+            self.wallet_mnemonic = None;
+            self.wallet_derivation_path = None;
+            self.wallet_remote_signer_url = None;
+            self.wallet_remote_signer_address = None;
+        }
+
+        let limits = match (other.limits, self.limits) {
+            (Some(other), Some(base)) => Some(other.or(base)),
+            (other, base) => other.or(base),
+        };
+        let mut endpoint_limits = self.endpoint_limits;
+        endpoint_limits.extend(other.endpoint_limits);
+
+        Self {
+            provider_url: other.provider_url.or(self.provider_url),
+            read_quorum: other.read_quorum.or(self.read_quorum),
+            limits,
+            endpoint_limits,
+            agent_proxy_contract: other.agent_proxy_contract.or(self.agent_proxy_contract),
+            wallet_private_key: other.wallet_private_key.or(self.wallet_private_key),
+            wallet_keystore: other.wallet_keystore.or(self.wallet_keystore),
+            wallet_keystore_password_env: other.wallet_keystore_password_env.or(self.wallet_keystore_password_env),
+            wallet_keystore_password_file: other.wallet_keystore_password_file.or(self.wallet_keystore_password_file),
+            wallet_mnemonic: other.wallet_mnemonic.or(self.wallet_mnemonic),
+            wallet_derivation_path: other.wallet_derivation_path.or(self.wallet_derivation_path),
+            wallet_remote_signer_url: other.wallet_remote_signer_url.or(self.wallet_remote_signer_url),
+            wallet_remote_signer_address: other.wallet_remote_signer_address.or(self.wallet_remote_signer_address),
+            chain_id: other.chain_id.or(self.chain_id),
+        }
+    }
+
+    fn from_env_lookup(lookup: &impl Fn(&str) -> Option<String>) -> ApiResult<Self> {
+        let read_quorum = lookup(ENV_AGENTPROXY_READ_QUORUM)
+            .map(|quorum| {
+                quorum
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid {} \"{}\"", ENV_AGENTPROXY_READ_QUORUM, quorum))
+            })
+            .transpose()?;
+        let requests_per_second = lookup(ENV_AGENTPROXY_RATE_LIMIT)
+            .map(|rate| {
+                rate.trim()
+                    .parse::<f64>()
+                    .map_err(|_| format!("Invalid {} \"{}\"", ENV_AGENTPROXY_RATE_LIMIT, rate))
+            })
+            .transpose()?;
+        let max_in_flight = lookup(ENV_AGENTPROXY_MAX_IN_FLIGHT)
+            .map(|max| {
+                max.trim()
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid {} \"{}\"", ENV_AGENTPROXY_MAX_IN_FLIGHT, max))
+            })
+            .transpose()?;
+        let limits = (requests_per_second.is_some() || max_in_flight.is_some()).then_some(EndpointLimits {
+            requests_per_second,
+            burst: None,
+            max_in_flight,
+        });
+
+        Ok(Self {
+            provider_url: lookup(ENV_AGENTPROXY_PROVIDER),
+            read_quorum,
+            limits,
+            endpoint_limits: HashMap::new(),
+            agent_proxy_contract: lookup(ENV_AGENTPROXY_CONTRACT),
+            wallet_private_key: lookup(ENV_WALLET_PRIVATE_KEY).map(SecretKey::from),
+            wallet_keystore: lookup(ENV_WALLET_KEYSTORE),
+            wallet_keystore_password_env: lookup(ENV_WALLET_KEYSTORE_PASSWORD)
+                .map(|_| ENV_WALLET_KEYSTORE_PASSWORD.to_string()),
+            wallet_keystore_password_file: None,
+            wallet_mnemonic: lookup(ENV_WALLET_MNEMONIC).map(SecretKey::from),
+            wallet_derivation_path: lookup(ENV_WALLET_DERIVATION_PATH),
+            wallet_remote_signer_url: lookup(ENV_WALLET_REMOTE_SIGNER_URL),
+            wallet_remote_signer_address: lookup(ENV_WALLET_REMOTE_SIGNER_ADDRESS),
+            chain_id: None,
+        })
+    }
+
+    fn wallet_source(&self) -> ApiResult<Option<WalletSource>> {
+        let configured = [
+            self.wallet_private_key.is_some(),
+            self.wallet_keystore.is_some(),
+            self.wallet_mnemonic.is_some(),
+            self.wallet_remote_signer_url.is_some(),
+        ];
+        if configured.iter().filter(|&&set| set).count() > 1 {
+            return Err(
+                "Only one of wallet_private_key, wallet_keystore, wallet_mnemonic and wallet_remote_signer_url can be set"
+                    .to_string(),
+            );
+        }
+
+        if let Some(private_key) = &self.wallet_private_key {
+            return Ok(Some(WalletSource::PrivateKey(private_key.clone())));
+        }
+        if let Some(path) = &self.wallet_keystore {
+            let password = match (&self.wallet_keystore_password_env, &self.wallet_keystore_password_file) {
+                (Some(name), _) => PasswordSource::Env(name.clone()),
+                (None, Some(path)) => PasswordSource::File(path.into()),
+                (None, None) => PasswordSource::Prompt,
+            };
+            return Ok(Some(WalletSource::Keystore { path: path.into(), password }));
+        }
+        if let Some(phrase) = &self.wallet_mnemonic {
+            return Ok(Some(WalletSource::Mnemonic {
+                phrase: Zeroizing::new(phrase.expose_secret().to_string()),
+                derivation_path: self.wallet_derivation_path.clone(),
+            }));
+        }
+        if let Some(url) = &self.wallet_remote_signer_url {
+            let address = self
+                .wallet_remote_signer_address
+                .clone()
+                .ok_or_else(|| "Missing wallet_remote_signer_address".to_string())?;
+            return Ok(Some(WalletSource::Remote { url: url.clone(), address }));
+        }
+
+        Ok(None)
+    }
+}
+
+impl fmt::Debug for ProfileConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProfileConfig")
+            .field("provider_url", &self.provider_url)
+            .field("agent_proxy_contract", &self.agent_proxy_contract)
+            .field("wallet_private_key", &self.wallet_private_key)
+            .field("wallet_keystore", &self.wallet_keystore)
+            .field("wallet_keystore_password_env", &self.wallet_keystore_password_env)
+            .field("wallet_keystore_password_file", &self.wallet_keystore_password_file)
+            .field("wallet_mnemonic", &self.wallet_mnemonic)
+            .field("wallet_derivation_path", &self.wallet_derivation_path)
+            .field("wallet_remote_signer_url", &self.wallet_remote_signer_url)
+            .field("wallet_remote_signer_address", &self.wallet_remote_signer_address)
+            .field("chain_id", &self.chain_id)
+            .field("read_quorum", &self.read_quorum)
+            .field("limits", &self.limits)
+            .field("endpoint_limits", &self.endpoint_limits)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfigFile {
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: HashMap<String, ProfileConfig>,
+    #[serde(default)]
+    pub deployments: Vec<Deployment>,
+}
+
+impl ConfigFile {
+    /// Writes the file as `.json` or `.toml` by extension, keys included. Any other
+    /// serialization of a config redacts them.
+    pub fn save(&self, path: impl AsRef<Path>) -> ApiResult<()> {
+        let path = path.as_ref();
+        let contents = expose_secrets(|| match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::to_string_pretty(self).map_err(|e| e.to_string()),
+            _ => toml::to_string(self).map_err(|e| e.to_string()),
+        })
+        .map_err(|e| format!("Failed to write config file {}: {}", path.display(), e))?;
+
+        std::fs::write(path, contents)
+            .map_err(|e| format!("Failed to write config file {}: {}", path.display(), e))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AttpsConfig {
+    pub profile: Option<String>,
+    pub provider_url: String,
+    pub agent_proxy_contract: Address,
+    pub wallet: Option<WalletSource>,
+    pub chain_id: Option<u64>,
+    pub read_quorum: Option<usize>,
+    pub limits: Option<EndpointLimits>,
+    pub endpoint_limits: HashMap<String, EndpointLimits>,
+    /// The registry entry for `chain_id`, when it describes this proxy.
+    pub deployment: Option<Deployment>,
+}
+
+impl AttpsConfig {
+    pub fn new(provider_url: &str, agent_proxy_contract: &str, wallet_private_key: Option<&SecretKey>) -> ApiResult<Self> {
+        Self::from_profile_config(None, ProfileConfig {
+            provider_url: Some(provider_url.to_string()),
+            agent_proxy_contract: Some(agent_proxy_contract.to_string()),
+            wallet_private_key: wallet_private_key.cloned(),
+            ..Default::default()
+        })
+    }
+
+    pub fn profile(name: &str) -> ApiResult<Self> {
+        let profile = ProfileConfig::builtin(name).ok_or_else(|| format!("Unknown profile \"{}\"", name))?;
+        Self::from_profile_config(Some(name), profile)
+    }
+
+    /// Reads `AGENTPROXY_PROVIDER`, `AGENTPROXY_CONTRACT` and one of `WALLET_PRIVATE_KEY`,
+    /// `WALLET_KEYSTORE` (with `WALLET_KEYSTORE_PASSWORD`, or a prompt), `WALLET_MNEMONIC`
+    /// (with `WALLET_DERIVATION_PATH`) or `WALLET_REMOTE_SIGNER_URL` (with
+    /// `WALLET_REMOTE_SIGNER_ADDRESS`), on top of the built-in profile named by
+    /// `ATTPS_PROFILE` when it is set.
+    pub fn from_env() -> ApiResult<Self> {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> ApiResult<Self> {
+        let profile_name = lookup(ENV_PROFILE);
+        let base = match &profile_name {
+            Some(name) => ProfileConfig::builtin(name).ok_or_else(|| {
+                format!("Unknown profile \"{}\" in {}", name, ENV_PROFILE)
+            })?,
+            None => ProfileConfig::default(),
+        };
+
+        let from_env = ProfileConfig::from_env_lookup(&lookup)?;
+
+        Self::from_profile_config(profile_name.as_deref(), base.merge(from_env))
+    }
+
+    /// Loads a profile from a `.toml` or `.json` file. Without a profile name the file's
+    /// `default_profile` is used. Environment variables override the file values.
+    pub fn from_file(path: impl AsRef<Path>, profile: Option<&str>) -> ApiResult<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+
+        let file: ConfigFile = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&contents)
+                .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?,
+            _ => toml::from_str(&contents)
+                .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?,
+        };
+
+        Self::from_config_file(file, profile, |key| std::env::var(key).ok())
+    }
+
+    pub fn from_config_file(
+        file: ConfigFile,
+        profile: Option<&str>,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> ApiResult<Self> {
+        let name = profile
+            .map(str::to_string)
+            .or_else(|| lookup(ENV_PROFILE))
+            .or(file.default_profile.clone())
+            .ok_or_else(|| "No profile selected and the config file has no default_profile".to_string())?;
+
+        let builtin = ProfileConfig::builtin(&name);
+        let from_file = file.profiles.get(&name).cloned();
+        if builtin.is_none() && from_file.is_none() {
+            return Err(format!("Unknown profile \"{}\"", name));
+        }
+
+        let from_env = ProfileConfig::from_env_lookup(&lookup)?;
+
+        let merged = builtin
+            .unwrap_or_default()
+            .merge(from_file.unwrap_or_default())
+            .merge(from_env);
+
+        let mut registry = DeploymentRegistry::builtin();
+        registry.extend(file.deployments);
+
+        Self::from_profile_config_with_registry(Some(&name), merged, &registry)
+    }
+
+    pub fn from_profile_config(name: Option<&str>, profile: ProfileConfig) -> ApiResult<Self> {
+        Self::from_profile_config_with_registry(name, profile, &DeploymentRegistry::builtin())
+    }
+
+    /// Like `from_profile_config`, but a missing `agent_proxy_contract` is taken from the
+    /// registry entry for the profile's chain ID.
+    pub fn from_profile_config_with_registry(
+        name: Option<&str>,
+        profile: ProfileConfig,
+        registry: &DeploymentRegistry,
+    ) -> ApiResult<Self> {
+        let context = match name {
+            Some(name) => format!(" for profile \"{}\"", name),
+            None => String::new(),
+        };
+
+        // key material is never part of the error message
+        let wallet = profile.wallet_source().map_err(|e| format!("{}{}", e, context))?;
+        if let Some(wallet) = &wallet {
+            wallet.validate().map_err(|e| format!("Invalid wallet{}: {}", context, e))?;
+        }
+
+        let provider_url = profile
+            .provider_url
+            .ok_or_else(|| format!("Missing provider_url{} (or set {})", context, ENV_AGENTPROXY_PROVIDER))?;
+        let urls: Vec<&str> = split_provider_urls(&provider_url).collect();
+        if urls.is_empty() {
+            return Err(format!("Missing provider_url{} (or set {})", context, ENV_AGENTPROXY_PROVIDER));
+        }
+        for provider_url in &urls {
+            let url = Url::parse(provider_url)
+                .map_err(|e| format!("Invalid provider_url \"{}\"{}: {}", provider_url, context, e))?;
+            if !matches!(url.scheme(), "http" | "https" | "ws" | "wss") {
+                return Err(format!(
+                    "Invalid provider_url \"{}\"{}: unsupported scheme \"{}\"",
+                    provider_url,
+                    context,
+                    url.scheme()
+                ));
+            }
+        }
+        if let Some(quorum) = profile.read_quorum {
+            if quorum == 0 || quorum > urls.len() {
+                return Err(format!(
+                    "Invalid read_quorum {}{}: needs between 1 and {} provider URLs",
+                    quorum,
+                    context,
+                    urls.len()
+                ));
+            }
+        }
+
+        if let Some(limits) = &profile.limits {
+            limits.validate().map_err(|e| format!("{}{}", e, context))?;
+        }
+        for (url, limits) in &profile.endpoint_limits {
+            if !urls.contains(&url.trim()) {
+                return Err(format!("Invalid endpoint_limits{}: \"{}\" is not a provider_url", context, url));
+            }
+            limits.validate().map_err(|e| format!("{} for \"{}\"{}", e, url, context))?;
+        }
+
+        let known = profile.chain_id.and_then(|chain_id| registry.get(chain_id));
+        let agent_proxy_contract = match profile.agent_proxy_contract {
+            Some(agent_proxy_contract) => agent_proxy_contract
+                .parse::<Address>()
+                .map_err(|e| format!("Invalid agent_proxy_contract \"{}\"{}: {}", agent_proxy_contract, context, e))?,
+            None => known.map(|deployment| deployment.agent_proxy).ok_or_else(|| {
+                format!("Missing agent_proxy_contract{} (or set {})", context, ENV_AGENTPROXY_CONTRACT)
+            })?,
+        };
+        // an overridden proxy makes the registry's manager, factory and start block meaningless
+        let deployment = known
+            .filter(|deployment| deployment.agent_proxy == agent_proxy_contract)
+            .cloned();
+
+        Ok(Self {
+            profile: name.map(str::to_string),
+            provider_url,
+            agent_proxy_contract,
+            wallet,
+            chain_id: profile.chain_id,
+            read_quorum: profile.read_quorum,
+            limits: profile.limits,
+            endpoint_limits: profile.endpoint_limits,
+            deployment,
+        })
+    }
+
+    pub fn transport(&self) -> ApiResult<AttpsTransport> {
+        let mut transport = AttpsTransport::from_urls(&self.provider_url)?;
+        let base = self.limits.unwrap_or_default();
+        if self.limits.is_some() {
+            transport = transport.with_limits(base)?;
+        }
+        for (url, limits) in &self.endpoint_limits {
+            transport = transport.with_endpoint_limits(url, limits.or(base))?;
+        }
+        match self.read_quorum {
+            Some(quorum) => transport.with_read_quorum(quorum),
+            None => Ok(transport),
+        }
+    }
+
+    pub fn load_wallet(&self) -> ApiResult<LocalWallet> {
+        self.wallet_source()?.load()
+    }
+
+    pub async fn load_signer(&self) -> ApiResult<AttpsSigner> {
+        self.wallet_source()?.load_signer().await
+    }
+
+    fn wallet_source(&self) -> ApiResult<&WalletSource> {
+        self.wallet.as_ref().ok_or_else(|| format!(
+            "Missing wallet (set {}, {}, {} or {})",
+            ENV_WALLET_PRIVATE_KEY, ENV_WALLET_KEYSTORE, ENV_WALLET_MNEMONIC, ENV_WALLET_REMOTE_SIGNER_URL
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::consts::SEPOLIA_AGENTPROXY_CONTRACT;
+
+    const TEST_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    #[test]
+    fn test_profiles_and_overrides() {
+        let file: ConfigFile = toml::from_str(r#"
+            default_profile = "local"
+
+            [profiles.staging]
+            provider_url = "https://rpc.example.org"
+            agent_proxy_contract = "0x590CDE19Ee0FbA69A72D37d1B8C3474f327C8Eec"
+            chain_id = 11155111
+
+            [[deployments]]
+            name = "local"
+            chain_id = 31337
+            agent_proxy = "0x5FbDB2315678afecb367f032d93F642f64180aa3"
+            deployment_block = 1
+        "#).unwrap();
+
+        let local = AttpsConfig::from_config_file(file.clone(), None, |_| None).unwrap();
+        assert_eq!(local.profile.as_deref(), Some("local"));
+        assert_eq!(local.provider_url, LOCAL_PROVIDER);
+        assert_eq!(local.chain_id, Some(LOCAL_CHAIN_ID));
+        assert_eq!(local.agent_proxy_contract, "0x5FbDB2315678afecb367f032d93F642f64180aa3".parse::<Address>().unwrap());
+        assert_eq!(local.deployment.unwrap().deployment_block, Some(1));
+
+        let staging = AttpsConfig::from_config_file(file.clone(), Some("staging"), |key| {
+            (key == ENV_WALLET_PRIVATE_KEY).then(|| TEST_KEY.to_string())
+        }).unwrap();
+        assert_eq!(staging.provider_url, "https://rpc.example.org");
+        assert_eq!(staging.load_wallet().unwrap(), TEST_KEY.parse::<LocalWallet>().unwrap());
+        assert!(!format!("{:?}", staging).contains(TEST_KEY));
+
+        assert_eq!(staging.deployment.unwrap().name, "sepolia");
+
+        let sepolia = AttpsConfig::from_lookup(|key| (key == ENV_PROFILE).then(|| "sepolia".to_string())).unwrap();
+        assert_eq!(sepolia.agent_proxy_contract, SEPOLIA_AGENTPROXY_CONTRACT.parse::<Address>().unwrap());
+        let overridden = AttpsConfig::from_lookup(|key| match key {
+            ENV_PROFILE => Some("sepolia".to_string()),
+            ENV_AGENTPROXY_CONTRACT => Some("0x5FbDB2315678afecb367f032d93F642f64180aa3".to_string()),
+            _ => None,
+        }).unwrap();
+        assert!(sepolia.deployment.is_some() && overridden.deployment.is_none());
+        assert!(sepolia.load_wallet().is_err());
+
+        let mnemonic = AttpsConfig::from_lookup(|key| match key {
+            ENV_PROFILE => Some("sepolia".to_string()),
+            ENV_WALLET_MNEMONIC => Some("test test test test test test test test test test test junk".to_string()),
+            _ => None,
+        }).unwrap();
+        assert!(matches!(mnemonic.wallet, Some(WalletSource::Mnemonic { .. })));
+        assert!(!format!("{:?}", mnemonic).contains("junk"));
+        assert_eq!(mnemonic.load_wallet().unwrap(), TEST_KEY.parse::<LocalWallet>().unwrap());
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let err = AttpsConfig::profile("local").unwrap_err();
+        assert_eq!(err, "Missing agent_proxy_contract for profile \"local\" (or set AGENTPROXY_CONTRACT)");
+
+        let err = AttpsConfig::new("ftp://example.org", "0x590CDE19Ee0FbA69A72D37d1B8C3474f327C8Eec", None).unwrap_err();
+        assert!(err.contains("unsupported scheme \"ftp\""));
+
+        let err = AttpsConfig::new(LOCAL_PROVIDER, "0x1234", None).unwrap_err();
+        assert!(err.starts_with("Invalid agent_proxy_contract \"0x1234\""));
+
+        let err = AttpsConfig::new(LOCAL_PROVIDER, "0x590CDE19Ee0FbA69A72D37d1B8C3474f327C8Eec", Some(&SecretKey::from("0xnotakey"))).unwrap_err();
+        assert_eq!(err, "Invalid wallet: Invalid private key");
+
+        let err = AttpsConfig::from_lookup(|key| match key {
+            ENV_PROFILE => Some("sepolia".to_string()),
+            ENV_WALLET_PRIVATE_KEY => Some(TEST_KEY.to_string()),
+            ENV_WALLET_KEYSTORE => Some("key.json".to_string()),
+            _ => None,
+        }).unwrap_err();
+        assert!(err.starts_with("Only one of"));
+
+        let json: ConfigFile = serde_json::from_str(r#"{"profiles": {"dev": {"provider_url": "http://localhost:8545"}}}"#).unwrap();
+        let err = AttpsConfig::from_config_file(json, Some("dev"), |_| None).unwrap_err();
+        assert!(err.starts_with("Missing agent_proxy_contract for profile \"dev\""));
+
+        let quorum = |value: &'static str| {
+            AttpsConfig::from_lookup(move |key| match key {
+                ENV_PROFILE => Some("sepolia".to_string()),
+                ENV_AGENTPROXY_PROVIDER => Some("https://rpc-a.example.org, https://rpc-b.example.org".to_string()),
+                ENV_AGENTPROXY_READ_QUORUM => Some(value.to_string()),
+                _ => None,
+            })
+        };
+        assert_eq!(quorum("2").unwrap().transport().unwrap().read_quorum(), Some(2));
+        assert_eq!(
+            quorum("3").unwrap_err(),
+            "Invalid read_quorum 3 for profile \"sepolia\": needs between 1 and 2 provider URLs"
+        );
+        assert_eq!(quorum("two").unwrap_err(), "Invalid AGENTPROXY_READ_QUORUM \"two\"");
+
+        let file: ConfigFile = toml::from_str(r#"
+            [profiles.sepolia]
+            provider_url = "https://rpc-a.example.org,https://rpc-b.example.org"
+
+            [profiles.sepolia.limits]
+            requests_per_second = 10
+            max_in_flight = 4
+
+            [profiles.sepolia.endpoint_limits."https://rpc-c.example.org"]
+            requests_per_second = 2
+        "#).unwrap();
+        let err = AttpsConfig::from_config_file(file, Some("sepolia"), |_| None).unwrap_err();
+        assert_eq!(err, "Invalid endpoint_limits for profile \"sepolia\": \"https://rpc-c.example.org\" is not a provider_url");
+        let err = AttpsConfig::from_lookup(|key| match key {
+            ENV_PROFILE => Some("sepolia".to_string()),
+            ENV_AGENTPROXY_RATE_LIMIT => Some("0".to_string()),
+            _ => None,
+        }).unwrap_err();
+        assert_eq!(err, "Invalid requests_per_second 0: must be greater than 0 for profile \"sepolia\"");
+    }
+}
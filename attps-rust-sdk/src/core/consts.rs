@@ -1,10 +1,6 @@
-use once_cell::sync::Lazy;
+pub const SEPOLIA_CHAIN_ID: u64 = 11155111;
+pub const SEPOLIA_PROVIDER: &str = "https://1rpc.io/sepolia";
+pub const SEPOLIA_AGENTPROXY_CONTRACT: &str = "0x590CDE19Ee0FbA69A72D37d1B8C3474f327C8Eec";
 
-pub static AGENTPROXY_CONTRACT: Lazy<String> =
-    Lazy::new(|| std::env::var("AGENTPROXY_CONTRACT").expect("env not found AGENTPROXY_CONTRACT"));
-
-pub static AGENTPROXY_PROVIDER: Lazy<String> =
-    Lazy::new(|| std::env::var("AGENTPROXY_PROVIDER").expect("env not found AGENTPROXY_PROVIDER"));
-
-pub static WALLET_PRIVATE_KEY: Lazy<String> =
-    Lazy::new(|| std::env::var("WALLET_PRIVATE_KEY").expect("env not found WALLET_PRIVATE_KEY"));
+pub const LOCAL_CHAIN_ID: u64 = 31337;
+pub const LOCAL_PROVIDER: &str = "http://127.0.0.1:8545";
@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+
+use crate::core::consts::{SEPOLIA_AGENTPROXY_CONTRACT, SEPOLIA_CHAIN_ID};
+use crate::core::ApiResult;
+
+/// The contracts of one ATTPS deployment. Manager and factory addresses are optional
+/// because they can always be read from the proxy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deployment {
+    pub name: String,
+    pub chain_id: u64,
+    pub agent_proxy: Address,
+    #[serde(default)]
+    pub agent_manager: Option<Address>,
+    #[serde(default)]
+    pub agent_factory: Option<Address>,
+    /// First block worth scanning for events; `AttpsClient` looks it up when `None`.
+    #[serde(default)]
+    pub deployment_block: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeploymentsFile {
+    #[serde(default)]
+    pub deployments: Vec<Deployment>,
+}
+
+/// Known deployments keyed by chain ID. Entries inserted later replace earlier ones,
+/// so user files can override the built-in addresses.
+#[derive(Debug, Clone, Default)]
+pub struct DeploymentRegistry {
+    deployments: BTreeMap<u64, Deployment>,
+}
+
+impl DeploymentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.insert(Deployment {
+            name: "sepolia".to_string(),
+            chain_id: SEPOLIA_CHAIN_ID,
+            agent_proxy: SEPOLIA_AGENTPROXY_CONTRACT.parse().expect("valid built-in address"),
+            // not recorded yet: the addresses are read from the proxy at connect
+            agent_manager: None,
+            agent_factory: None,
+            deployment_block: None,
+        });
+        registry
+    }
+
+    /// The built-in registry with the deployments from a `.toml` or `.json` file on top.
+    pub fn builtin_with_file(path: impl AsRef<Path>) -> ApiResult<Self> {
+        let mut registry = Self::builtin();
+        registry.extend_from_file(path)?;
+        Ok(registry)
+    }
+
+    pub fn insert(&mut self, deployment: Deployment) -> Option<Deployment> {
+        self.deployments.insert(deployment.chain_id, deployment)
+    }
+
+    pub fn extend(&mut self, deployments: impl IntoIterator<Item = Deployment>) {
+        for deployment in deployments {
+            self.insert(deployment);
+        }
+    }
+
+    pub fn extend_from_file(&mut self, path: impl AsRef<Path>) -> ApiResult<()> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read deployments file {}: {}", path.display(), e))?;
+
+        let file: DeploymentsFile = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&contents)
+                .map_err(|e| format!("Invalid deployments file {}: {}", path.display(), e))?,
+            _ => toml::from_str(&contents)
+                .map_err(|e| format!("Invalid deployments file {}: {}", path.display(), e))?,
+        };
+
+        self.extend(file.deployments);
+        Ok(())
+    }
+
+    pub fn get(&self, chain_id: u64) -> Option<&Deployment> {
+        self.deployments.get(&chain_id)
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&Deployment> {
+        self.deployments
+            .values()
+            .find(|deployment| deployment.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Looks a deployment up by chain name or decimal chain ID.
+    pub fn resolve(&self, chain: &str) -> ApiResult<&Deployment> {
+        let found = match chain.parse::<u64>() {
+            Ok(chain_id) => self.get(chain_id),
+            Err(_) => self.by_name(chain),
+        };
+        found.ok_or_else(|| format!("No known deployment for chain \"{}\"", chain))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Deployment> {
+        self.deployments.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_overrides() {
+        let mut registry = DeploymentRegistry::builtin();
+        assert_eq!(registry.resolve("Sepolia").unwrap().chain_id, SEPOLIA_CHAIN_ID);
+        assert_eq!(registry.resolve("11155111").unwrap().name, "sepolia");
+        assert!(registry.resolve("mainnet").unwrap_err().contains("No known deployment"));
+
+        let file: DeploymentsFile = toml::from_str(r#"
+            [[deployments]]
+            name = "sepolia"
+            chain_id = 11155111
+            agent_proxy = "0x0000000000000000000000000000000000000001"
+            deployment_block = 7000000
+
+            [[deployments]]
+            name = "local"
+            chain_id = 31337
+            agent_proxy = "0x0000000000000000000000000000000000000002"
+        "#).unwrap();
+        registry.extend(file.deployments);
+
+        let sepolia = registry.get(SEPOLIA_CHAIN_ID).unwrap();
+        assert_eq!(sepolia.agent_proxy, Address::from_low_u64_be(1));
+        assert_eq!(sepolia.deployment_block, Some(7000000));
+        assert_eq!(registry.resolve("local").unwrap().agent_proxy, Address::from_low_u64_be(2));
+        assert_eq!(registry.iter().count(), 2);
+    }
+}
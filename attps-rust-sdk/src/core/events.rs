@@ -0,0 +1,356 @@
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use ethers::abi::RawLog;
+use ethers::contract::{EthLogDecode, LogMeta};
+use ethers::prelude::*;
+use futures::Stream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::core::transport::AttpsTransport;
+use crate::core::{ApiResult, SignerClient};
+
+const BACKFILL_CHUNK: u64 = 2_000;
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// A live stream of logs over WebSocket that survives disconnects.
+///
+/// After a dropped connection it reconnects with exponential backoff (rotating through
+/// the given URLs), subscribes again, and fetches the blocks it missed with
+/// `eth_getLogs` before resuming the subscription. Logs are delivered once, in
+/// `(block, log index)` order. Removed logs from reorgs are passed through, and the
+/// replacement logs of the removed blocks are delivered after them.
+pub struct LogStream {
+    receiver: mpsc::Receiver<Log>,
+    task: JoinHandle<()>,
+}
+
+impl LogStream {
+    /// Starts streaming logs matching `filter`. With `from_block`, historical logs from
+    /// that block are delivered first.
+    pub fn spawn(ws_urls: Vec<String>, filter: Filter, from_block: Option<u64>) -> ApiResult<Self> {
+        if ws_urls.is_empty() {
+            return Err("Event streams need a ws:// or wss:// provider URL".to_string());
+        }
+        let (sender, receiver) = mpsc::channel(256);
+        let task = tokio::spawn(run(ws_urls, filter, from_block, sender));
+        Ok(Self { receiver, task })
+    }
+}
+
+impl Stream for LogStream {
+    type Item = Log;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for LogStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct Cursor {
+    // last delivered (block, log index)
+    last: Option<(U64, U256)>,
+    // first block not yet known to be fully delivered
+    next_block: Option<u64>,
+}
+
+impl Cursor {
+    fn accept(&mut self, log: &Log) -> bool {
+        if log.removed == Some(true) {
+            // the removed block is replaced as a whole, so rewind to just before it
+            if let Some(block) = log.block_number.map(|block| block.as_u64()) {
+                if self.last.is_some_and(|last| last.0.as_u64() >= block) {
+                    self.last = block.checked_sub(1).map(|previous| (previous.into(), U256::MAX));
+                }
+                self.next_block = self.next_block.map(|next| next.min(block));
+            }
+            return true;
+        }
+        let key = (log.block_number.unwrap_or_default(), log.log_index.unwrap_or_default());
+        if self.last.is_some_and(|last| key <= last) {
+            return false;
+        }
+        self.last = Some(key);
+        let block = key.0.as_u64();
+        self.next_block = Some(self.next_block.map_or(block, |next| next.max(block)));
+        true
+    }
+}
+
+async fn run(ws_urls: Vec<String>, filter: Filter, from_block: Option<u64>, sender: mpsc::Sender<Log>) {
+    let mut cursor = Cursor { last: None, next_block: from_block };
+    let mut attempt: u32 = 0;
+    let mut index = 0;
+
+    loop {
+        let url = &ws_urls[index % ws_urls.len()];
+        match follow(url, &filter, &mut cursor, &sender).await {
+            Follow::Closed => return,
+            Follow::Failed { subscribed, error } => {
+                if subscribed {
+                    attempt = 0;
+                }
+                tracing::warn!(url = url.as_str(), error = error.as_str(), "Log stream disconnected");
+            }
+        }
+
+        let delay = Duration::from_millis(500)
+            .saturating_mul(1 << attempt.min(6))
+            .min(MAX_RECONNECT_DELAY);
+        attempt += 1;
+        index += 1;
+        tokio::time::sleep(delay).await;
+    }
+}
+
+enum Follow {
+    /// The consumer dropped the stream.
+    Closed,
+    Failed { subscribed: bool, error: String },
+}
+
+async fn follow(url: &str, filter: &Filter, cursor: &mut Cursor, sender: &mpsc::Sender<Log>) -> Follow {
+    let failed = |subscribed: bool, error: String| Follow::Failed { subscribed, error };
+
+    let ws = match Ws::connect_with_reconnects(url, 0).await {
+        Ok(ws) => ws,
+        Err(e) => return failed(false, e.to_string()),
+    };
+    let provider = Provider::new(ws);
+
+    // subscribe before reading the head, so nothing falls between backfill and subscription
+    let mut subscription = match provider.subscribe_logs(filter).await {
+        Ok(subscription) => subscription,
+        Err(e) => return failed(false, e.to_string()),
+    };
+    let head = match provider.get_block_number().await {
+        Ok(head) => head.as_u64(),
+        Err(e) => return failed(true, e.to_string()),
+    };
+
+    match cursor.next_block {
+        Some(next_block) => {
+            let mut start = next_block;
+            while start <= head {
+                let end = (start + BACKFILL_CHUNK - 1).min(head);
+                let logs = match provider.get_logs(&filter.clone().from_block(start).to_block(end)).await {
+                    Ok(logs) => logs,
+                    Err(e) => return failed(true, e.to_string()),
+                };
+                for log in logs {
+                    if cursor.accept(&log) && sender.send(log).await.is_err() {
+                        return Follow::Closed;
+                    }
+                }
+                start = end + 1;
+            }
+        }
+        None => cursor.next_block = Some(head + 1),
+    }
+
+    while let Some(log) = subscription.next().await {
+        if cursor.accept(&log) && sender.send(log).await.is_err() {
+            return Follow::Closed;
+        }
+    }
+    failed(true, "subscription closed".to_string())
+}
+
+/// `eth_getLogs` for `filter` from `from_block` to the head, `BACKFILL_CHUNK` blocks
+/// per request so providers that limit the range accept it.
+pub(crate) async fn get_logs_in_chunks<M: Middleware>(client: &M, filter: &Filter, from_block: u64) -> ApiResult<Vec<Log>> {
+    let head = client.get_block_number().await
+        .map_err(|e| format!("Failed to get block number: {}", e))?
+        .as_u64();
+    let mut logs = Vec::new();
+    let mut start = from_block;
+    while start <= head {
+        let end = (start + BACKFILL_CHUNK - 1).min(head);
+        logs.extend(client.get_logs(&filter.clone().from_block(start).to_block(end)).await
+            .map_err(|e| format!("Failed to get logs for blocks {}-{}: {}", start, end, e))?);
+        start = end + 1;
+    }
+    Ok(logs)
+}
+
+/// Decoded contract events from a `LogStream`, with their log metadata.
+pub struct EventStream<E> {
+    logs: LogStream,
+    _event: PhantomData<E>,
+}
+
+impl<E: EthLogDecode> EventStream<E> {
+    pub fn new(client: &SignerClient, address: Address, from_block: Option<u64>) -> ApiResult<Self> {
+        let transport: &AttpsTransport = client.provider().as_ref();
+        let logs = LogStream::spawn(transport.ws_urls(), Filter::new().address(address), from_block)?;
+        Ok(Self { logs, _event: PhantomData })
+    }
+}
+
+impl<E: EthLogDecode + Unpin> Stream for EventStream<E> {
+    type Item = ApiResult<(E, LogMeta)>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.logs).poll_next(cx).map(|log| {
+            log.map(|log| {
+                let meta = LogMeta::from(&log);
+                E::decode_log(&RawLog::from(log))
+                    .map(|event| (event, meta))
+                    .map_err(|e| format!("Failed to decode event: {}", e))
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use futures::{SinkExt, StreamExt};
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message;
+
+    fn log_at(block: u64) -> Value {
+        serde_json::to_value(Log {
+            address: Address::from_low_u64_be(1),
+            block_number: Some(block.into()),
+            block_hash: Some(H256::from_low_u64_be(block)),
+            transaction_hash: Some(H256::from_low_u64_be(100 + block)),
+            transaction_index: Some(0u64.into()),
+            log_index: Some(0u64.into()),
+            removed: Some(false),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    // The first connection sees block 1 and then drops; blocks 2 and 3 are mined
+    // during the outage and only block 3 is pushed after the reconnect.
+    async fn flaky_node() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let connection = connections.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    let head: u64 = if connection == 0 { 1 } else { 3 };
+                    while let Some(Ok(Message::Text(text))) = ws.next().await {
+                        let request: Value = serde_json::from_str(&text).unwrap();
+                        let result = match request["method"].as_str().unwrap() {
+                            "eth_subscribe" => json!("0xabc"),
+                            "eth_blockNumber" => json!(format!("{:#x}", head)),
+                            "eth_getLogs" => {
+                                let bound = |key: &str| {
+                                    u64::from_str_radix(request["params"][0][key].as_str().unwrap().trim_start_matches("0x"), 16).unwrap()
+                                };
+                                json!((bound("fromBlock")..=bound("toBlock")).map(log_at).collect::<Vec<_>>())
+                            }
+                            _ => Value::Null,
+                        };
+                        let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": result});
+                        ws.send(Message::Text(response.to_string())).await.unwrap();
+
+                        let push = |block: u64| {
+                            json!({
+                                "jsonrpc": "2.0",
+                                "method": "eth_subscription",
+                                "params": {"subscription": "0xabc", "result": log_at(block)},
+                            })
+                            .to_string()
+                        };
+                        match (connection, request["method"].as_str().unwrap()) {
+                            (0, "eth_blockNumber") => {
+                                ws.send(Message::Text(push(1))).await.unwrap();
+                                ws.close(None).await.ok();
+                                return;
+                            }
+                            (_, "eth_getLogs") => {
+                                ws.send(Message::Text(push(3))).await.unwrap();
+                            }
+                            _ => {}
+                        }
+                    }
+                });
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_cursor_redelivers_after_reorg() {
+        let log = |block: u64, index: u64, removed: bool| Log {
+            block_number: Some(block.into()),
+            log_index: Some(index.into()),
+            removed: Some(removed),
+            ..Default::default()
+        };
+        let mut cursor = Cursor { last: None, next_block: None };
+        assert!(cursor.accept(&log(4, 0, false)));
+        assert!(cursor.accept(&log(5, 1, false)));
+        assert!(cursor.accept(&log(6, 2, false)));
+        assert!(!cursor.accept(&log(5, 1, false)));
+
+        // blocks 5 and 6 are reorged out; their replacements reuse the same positions
+        assert!(cursor.accept(&log(6, 2, true)));
+        assert!(cursor.accept(&log(5, 1, true)));
+        assert!(!cursor.accept(&log(4, 0, false)));
+        assert!(cursor.accept(&log(5, 0, false)));
+        assert!(cursor.accept(&log(6, 2, false)));
+        assert_eq!(cursor.next_block, Some(6));
+    }
+
+    #[tokio::test]
+    async fn test_get_logs_in_chunks() {
+        use crate::utils::mock_server::{MockRequest, MockResponse, MockServer};
+
+        // one log at the start of every requested range; wider ranges are refused
+        let server = MockServer::start(Arc::new(|request: MockRequest| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let bound = |key: &str| u64::from_str_radix(body["params"][0][key].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+            let response = match body["method"].as_str() {
+                Some("eth_blockNumber") => json!({ "jsonrpc": "2.0", "id": body["id"], "result": "0x1388" }),
+                Some("eth_getLogs") if bound("toBlock") - bound("fromBlock") >= BACKFILL_CHUNK => {
+                    json!({ "jsonrpc": "2.0", "id": body["id"], "error": { "code": -32005, "message": "range too large" } })
+                }
+                Some("eth_getLogs") => json!({ "jsonrpc": "2.0", "id": body["id"], "result": [log_at(bound("fromBlock"))] }),
+                _ => json!({ "jsonrpc": "2.0", "id": body["id"], "result": null }),
+            };
+            MockResponse::new(200, "application/json", response.to_string())
+        }))
+        .await
+        .unwrap();
+
+        let provider = Provider::new(AttpsTransport::from_urls(&server.url()).unwrap());
+        let logs = get_logs_in_chunks(&provider, &Filter::new(), 1_000).await.unwrap();
+        let blocks: Vec<u64> = logs.iter().map(|log| log.block_number.unwrap().as_u64()).collect();
+        assert_eq!(blocks, vec![1_000, 3_000, 5_000]);
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_backfills_missed_blocks() {
+        let url = flaky_node().await;
+        let mut stream = LogStream::spawn(vec![url], Filter::new(), None).unwrap();
+
+        let mut blocks = Vec::new();
+        while blocks.len() < 3 {
+            let log = tokio::time::timeout(Duration::from_secs(10), stream.next()).await.unwrap().unwrap();
+            blocks.push(log.block_number.unwrap().as_u64());
+        }
+        assert_eq!(blocks, vec![1, 2, 3]);
+        assert!(tokio::time::timeout(Duration::from_millis(200), stream.next()).await.is_err());
+    }
+}
@@ -0,0 +1,153 @@
+//! Prometheus metrics, recorded when the `metrics` feature is enabled. Without the
+//! feature the recording functions compile to nothing.
+
+#[cfg(feature = "metrics")]
+pub use enabled::registry;
+#[cfg(feature = "metrics")]
+pub(crate) use enabled::*;
+#[cfg(not(feature = "metrics"))]
+pub(crate) use disabled::*;
+
+#[cfg(feature = "metrics")]
+mod enabled {
+    use std::sync::OnceLock;
+    use std::time::Duration;
+
+    use ethers::types::TransactionReceipt;
+    use prometheus::{exponential_buckets, histogram_opts, opts, CounterVec, HistogramVec, IntCounterVec, Registry};
+
+    struct Metrics {
+        registry: Registry,
+        rpc_duration: HistogramVec,
+        rpc_errors: IntCounterVec,
+        retries: IntCounterVec,
+        transactions: IntCounterVec,
+        gas_used: IntCounterVec,
+        fees: CounterVec,
+        verify: IntCounterVec,
+        proof_duration: HistogramVec,
+    }
+
+    fn metrics() -> &'static Metrics {
+        static METRICS: OnceLock<Metrics> = OnceLock::new();
+        METRICS.get_or_init(|| {
+            let registry = Registry::new_custom(Some("attps".to_string()), None).unwrap();
+            let metrics = Metrics {
+                rpc_duration: HistogramVec::new(
+                    histogram_opts!("rpc_duration_seconds", "JSON-RPC request latency, including failover and retries")
+                        .buckets(exponential_buckets(0.005, 2.0, 12).unwrap()),
+                    &["method"],
+                ).unwrap(),
+                rpc_errors: IntCounterVec::new(opts!("rpc_errors_total", "JSON-RPC requests that failed on every endpoint"), &["method"]).unwrap(),
+                retries: IntCounterVec::new(opts!("retries_total", "Contract calls retried after a failure"), &["contract"]).unwrap(),
+                transactions: IntCounterVec::new(opts!("transactions_total", "Mined transactions"), &["contract", "method", "status"]).unwrap(),
+                gas_used: IntCounterVec::new(opts!("gas_used_total", "Gas used by mined transactions"), &["contract", "method"]).unwrap(),
+                fees: CounterVec::new(opts!("fees_wei_total", "Fees paid by mined transactions, in wei"), &["contract", "method"]).unwrap(),
+                verify: IntCounterVec::new(opts!("verify_total", "Verifications by mode (call or send) and outcome"), &["mode", "outcome"]).unwrap(),
+                proof_duration: HistogramVec::new(
+                    histogram_opts!("proof_generation_seconds", "Signature proof generation time")
+                        .buckets(exponential_buckets(0.001, 2.0, 12).unwrap()),
+                    &["outcome"],
+                ).unwrap(),
+                registry,
+            };
+            metrics.registry.register(Box::new(metrics.rpc_duration.clone())).unwrap();
+            metrics.registry.register(Box::new(metrics.rpc_errors.clone())).unwrap();
+            metrics.registry.register(Box::new(metrics.retries.clone())).unwrap();
+            metrics.registry.register(Box::new(metrics.transactions.clone())).unwrap();
+            metrics.registry.register(Box::new(metrics.gas_used.clone())).unwrap();
+            metrics.registry.register(Box::new(metrics.fees.clone())).unwrap();
+            metrics.registry.register(Box::new(metrics.verify.clone())).unwrap();
+            metrics.registry.register(Box::new(metrics.proof_duration.clone())).unwrap();
+            metrics
+        })
+    }
+
+    /// The registry with all SDK metrics, named `attps_*`. Gather it in a scrape handler
+    /// with `prometheus::TextEncoder`.
+    pub fn registry() -> &'static Registry {
+        &metrics().registry
+    }
+
+    pub(crate) fn observe_rpc(method: &str, elapsed: Duration, ok: bool) {
+        let metrics = metrics();
+        metrics.rpc_duration.with_label_values(&[method]).observe(elapsed.as_secs_f64());
+        if !ok {
+            metrics.rpc_errors.with_label_values(&[method]).inc();
+        }
+    }
+
+    pub(crate) fn count_retry(contract: &str) {
+        metrics().retries.with_label_values(&[contract]).inc();
+    }
+
+    pub(crate) fn observe_receipt(contract: &str, method: &str, receipt: &TransactionReceipt) {
+        let metrics = metrics();
+        let status = if receipt.status == Some(1.into()) { "success" } else { "reverted" };
+        metrics.transactions.with_label_values(&[contract, method, status]).inc();
+        if let Some(gas_used) = receipt.gas_used {
+            metrics.gas_used.with_label_values(&[contract, method]).inc_by(gas_used.low_u64());
+            if let Some(price) = receipt.effective_gas_price {
+                let fee = gas_used.saturating_mul(price);
+                metrics.fees.with_label_values(&[contract, method]).inc_by(fee.to_string().parse().unwrap_or_default());
+            }
+        }
+    }
+
+    pub(crate) fn count_verify(mode: &str, outcome: &str) {
+        metrics().verify.with_label_values(&[mode, outcome]).inc();
+    }
+
+    pub(crate) fn observe_proof(elapsed: Duration, ok: bool) {
+        let outcome = if ok { "success" } else { "error" };
+        metrics().proof_duration.with_label_values(&[outcome]).observe(elapsed.as_secs_f64());
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+mod disabled {
+    use std::time::Duration;
+
+    use ethers::types::TransactionReceipt;
+
+    pub(crate) fn observe_rpc(_method: &str, _elapsed: Duration, _ok: bool) {}
+
+    pub(crate) fn count_retry(_contract: &str) {}
+
+    pub(crate) fn observe_receipt(_contract: &str, _method: &str, _receipt: &TransactionReceipt) {}
+
+    pub(crate) fn count_verify(_mode: &str, _outcome: &str) {}
+
+    pub(crate) fn observe_proof(_elapsed: Duration, _ok: bool) {}
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use ethers::types::TransactionReceipt;
+    use prometheus::{Encoder, TextEncoder};
+
+    #[test]
+    fn test_metrics_are_exported() {
+        observe_rpc("test_method", Duration::from_millis(20), false);
+        count_verify("test", "reverted");
+        let receipt = TransactionReceipt {
+            status: Some(1.into()),
+            gas_used: Some(21_000.into()),
+            effective_gas_price: Some(2_000_000_000u64.into()),
+            ..Default::default()
+        };
+        observe_receipt("TestContract", "test_method", &receipt);
+
+        let mut text = Vec::new();
+        TextEncoder::new().encode(&registry().gather(), &mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("attps_rpc_errors_total{method=\"test_method\"} 1"));
+        assert!(text.contains("attps_verify_total{mode=\"test\",outcome=\"reverted\"} 1"));
+        assert!(text.contains("attps_gas_used_total{contract=\"TestContract\",method=\"test_method\"} 21000"));
+        assert!(text.contains("attps_fees_wei_total{contract=\"TestContract\",method=\"test_method\"} 42000000000000"));
+        assert!(text.contains("attps_transactions_total{contract=\"TestContract\",method=\"test_method\",status=\"success\"} 1"));
+    }
+}
@@ -1,3 +1,17 @@
+pub mod config;
 pub mod consts;
+pub mod deployments;
+pub mod events;
+pub mod metrics;
+pub mod transport;
+pub mod versions;
 
-pub type ApiResult<T> = Result<T, String>;
\ No newline at end of file
+use ethers::middleware::SignerMiddleware;
+use ethers::providers::Provider;
+
+use crate::core::transport::AttpsTransport;
+use crate::utils::signer::AttpsSigner;
+
+pub type ApiResult<T> = Result<T, String>;
+
+pub type SignerClient = SignerMiddleware<Provider<AttpsTransport>, AttpsSigner>;
\ No newline at end of file
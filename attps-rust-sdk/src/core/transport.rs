@@ -0,0 +1,708 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use async_trait::async_trait;
+use ethers::abi::Detokenize;
+use ethers::prelude::*;
+use ethers::providers::{JsonRpcClient, JsonRpcError, ProviderError, RpcError, Ws, WsClientError};
+use futures::future::join_all;
+use reqwest::header::RETRY_AFTER;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::Semaphore;
+use tracing::Instrument;
+
+use crate::core::metrics;
+use crate::core::{ApiResult, SignerClient};
+
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+// used when a 429 response has no usable Retry-After header
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+const RATE_LIMIT_RETRIES: usize = 3;
+
+#[derive(Debug)]
+pub enum TransportError {
+    /// The endpoint answered with a JSON-RPC error; another endpoint would say the same.
+    Rpc(JsonRpcError),
+    Serde(serde_json::Error),
+    /// Every endpoint failed at the transport level.
+    AllFailed(Vec<String>),
+    Quorum(String),
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportError::Rpc(e) => write!(f, "{}", e),
+            TransportError::Serde(e) => write!(f, "Invalid RPC response: {}", e),
+            TransportError::AllFailed(errors) => write!(f, "All RPC endpoints failed: {}", errors.join("; ")),
+            TransportError::Quorum(e) => f.write_str(e),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+impl RpcError for TransportError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            TransportError::Rpc(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            TransportError::Serde(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<TransportError> for ProviderError {
+    fn from(src: TransportError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(src))
+    }
+}
+
+/// Client-side limits for one endpoint. Unset fields are unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct EndpointLimits {
+    /// Sustained request rate of the token bucket.
+    pub requests_per_second: Option<f64>,
+    /// Bucket size, i.e. how many requests may go out at once after an idle period.
+    /// Defaults to one second's worth of requests.
+    pub burst: Option<u32>,
+    /// Requests allowed in flight at the same time.
+    pub max_in_flight: Option<usize>,
+}
+
+impl EndpointLimits {
+    /// Fields set in `self` win over `base`.
+    pub fn or(self, base: EndpointLimits) -> Self {
+        Self {
+            requests_per_second: self.requests_per_second.or(base.requests_per_second),
+            burst: self.burst.or(base.burst),
+            max_in_flight: self.max_in_flight.or(base.max_in_flight),
+        }
+    }
+
+    pub fn validate(&self) -> ApiResult<()> {
+        if let Some(rate) = self.requests_per_second {
+            if !(rate.is_finite() && rate > 0.0) {
+                return Err(format!("Invalid requests_per_second {}: must be greater than 0", rate));
+            }
+        }
+        if self.burst == Some(0) {
+            return Err("Invalid burst 0: must be at least 1".to_string());
+        }
+        if self.max_in_flight == Some(0) {
+            return Err("Invalid max_in_flight 0: must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Tokens may go negative: each caller reserves its token and waits its turn, so
+/// queued requests leave at the configured rate instead of racing.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, burst: Option<u32>) -> Self {
+        let capacity = burst.map(f64::from).unwrap_or(rate.ceil()).max(1.0);
+        Self { rate, capacity, tokens: capacity, updated: Instant::now() }
+    }
+
+    /// Reserves a token and returns how long to wait before using it.
+    fn reserve(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity) - 1.0;
+        self.updated = now;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Health {
+    failures: u32,
+    unhealthy_until: Option<Instant>,
+    /// Set from 429 responses; the endpoint gets no requests until then.
+    throttled_until: Option<Instant>,
+    last_error: Option<String>,
+}
+
+#[derive(Debug)]
+enum EndpointClient {
+    Http { client: reqwest::Client, next_id: AtomicU64 },
+    /// Connected on first use and again after the connection drops.
+    Ws(tokio::sync::Mutex<Option<Ws>>),
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    url: String,
+    client: EndpointClient,
+    health: Mutex<Health>,
+    limiter: Mutex<Option<TokenBucket>>,
+    in_flight: Mutex<Option<Arc<Semaphore>>>,
+}
+
+enum EndpointError {
+    Rpc(JsonRpcError),
+    Transport(String),
+    RateLimited { retry_after: Duration, error: String },
+}
+
+impl Endpoint {
+    fn set_limits(&self, limits: EndpointLimits) {
+        *self.limiter.lock().unwrap() = limits
+            .requests_per_second
+            .map(|rate| TokenBucket::new(rate, limits.burst));
+        *self.in_flight.lock().unwrap() = limits.max_in_flight.map(|max| Arc::new(Semaphore::new(max)));
+    }
+
+    async fn request(&self, method: &str, params: &Value) -> Result<Value, EndpointError> {
+        let wait = self
+            .limiter
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map(|bucket| bucket.reserve(Instant::now()))
+            .unwrap_or_default();
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+
+        let in_flight = self.in_flight.lock().unwrap().clone();
+        let _permit = match &in_flight {
+            Some(semaphore) => Some(semaphore.acquire().await.expect("semaphore is never closed")),
+            None => None,
+        };
+
+        match &self.client {
+            EndpointClient::Http { client, next_id } => {
+                let id = next_id.fetch_add(1, Ordering::Relaxed);
+                self.http_request(client, id, method, params).await
+            }
+            EndpointClient::Ws(connection) => {
+                let mut connection = connection.lock().await;
+                if connection.is_none() {
+                    // reconnects are driven by the endpoint's health backoff, not by ethers
+                    let ws = Ws::connect_with_reconnects(self.url.as_str(), 0)
+                        .await
+                        .map_err(|e| EndpointError::Transport(e.to_string()))?;
+                    *connection = Some(ws);
+                }
+                let ws = connection.clone().expect("connected above");
+                drop(connection);
+
+                match JsonRpcClient::request::<_, Value>(&ws, method, params).await {
+                    Ok(value) => Ok(value),
+                    Err(WsClientError::JsonRpcError(e)) => Err(EndpointError::Rpc(e)),
+                    Err(e) => {
+                        if let EndpointClient::Ws(connection) = &self.client {
+                            connection.lock().await.take();
+                        }
+                        Err(EndpointError::Transport(e.to_string()))
+                    }
+                }
+            }
+        }
+    }
+
+    async fn http_request(
+        &self,
+        client: &reqwest::Client,
+        id: u64,
+        method: &str,
+        params: &Value,
+    ) -> Result<Value, EndpointError> {
+        let body = serde_json::json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        let response = client
+            .post(&self.url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| EndpointError::Transport(e.to_string()))?;
+
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        // 503 with Retry-After is how some providers throttle, too
+        if status == StatusCode::TOO_MANY_REQUESTS || (!status.is_success() && retry_after.is_some()) {
+            return Err(EndpointError::RateLimited {
+                retry_after: retry_after.unwrap_or(DEFAULT_RETRY_AFTER),
+                error: format!("HTTP {}", status),
+            });
+        }
+
+        let text = response.text().await.map_err(|e| EndpointError::Transport(e.to_string()))?;
+        if !status.is_success() {
+            return Err(EndpointError::Transport(format!("HTTP {}: {}", status, text.trim())));
+        }
+
+        let mut response: Value = serde_json::from_str(&text)
+            .map_err(|e| EndpointError::Transport(format!("Invalid RPC response: {}", e)))?;
+        if let Some(error) = response.get("error") {
+            let error: JsonRpcError = serde_json::from_value(error.clone())
+                .map_err(|e| EndpointError::Transport(format!("Invalid RPC error: {}", e)))?;
+            if error.code == 429 {
+                return Err(EndpointError::RateLimited { retry_after: DEFAULT_RETRY_AFTER, error: error.to_string() });
+            }
+            return Err(EndpointError::Rpc(error));
+        }
+        match response.get_mut("result") {
+            Some(result) => Ok(result.take()),
+            None => Err(EndpointError::Transport("Invalid RPC response: missing result".to_string())),
+        }
+    }
+
+    fn throttled_until(&self, now: Instant) -> Option<Instant> {
+        self.health.lock().unwrap().throttled_until.filter(|until| *until > now)
+    }
+
+    fn is_healthy(&self, now: Instant) -> bool {
+        let health = self.health.lock().unwrap();
+        health.unhealthy_until.is_none_or(|until| now >= until)
+    }
+
+    fn record_success(&self) {
+        *self.health.lock().unwrap() = Health::default();
+    }
+
+    fn record_failure(&self, error: &str) {
+        let mut health = self.health.lock().unwrap();
+        health.failures += 1;
+        let backoff = Duration::from_secs(1 << health.failures.min(6)).min(MAX_BACKOFF);
+        health.unhealthy_until = Some(Instant::now() + backoff);
+        health.last_error = Some(error.to_string());
+    }
+
+    fn record_throttle(&self, retry_after: Duration, error: &str) {
+        let mut health = self.health.lock().unwrap();
+        let until = Instant::now() + retry_after.min(MAX_RETRY_AFTER);
+        health.throttled_until = Some(until);
+        health.unhealthy_until = Some(health.unhealthy_until.map_or(until, |unhealthy| unhealthy.max(until)));
+        health.last_error = Some(format!("rate limited: {}", error));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointStatus {
+    pub url: String,
+    pub healthy: bool,
+    /// Waiting out a 429 response's Retry-After.
+    pub throttled: bool,
+    pub failures: u32,
+    pub last_error: Option<String>,
+}
+
+/// A JSON-RPC transport over several endpoints of the same chain.
+///
+/// Requests go to the first healthy endpoint in configured order and fail over to the
+/// next one on connection, HTTP or malformed-response errors. A failed endpoint is
+/// skipped for an exponentially growing backoff, and used again once every healthy
+/// endpoint has failed too. JSON-RPC error responses (reverts, nonce errors) are
+/// returned as-is. Clones share health state and limits.
+///
+/// Each endpoint can be limited with `EndpointLimits`. An endpoint that answers with
+/// HTTP 429 (or an error carrying `Retry-After`) gets no requests until the
+/// `Retry-After` delay has passed; when every endpoint is throttled, the request waits
+/// for the first one to become available again.
+#[derive(Debug, Clone)]
+pub struct AttpsTransport {
+    endpoints: Arc<Vec<Endpoint>>,
+    read_quorum: Option<usize>,
+}
+
+impl AttpsTransport {
+    pub fn new(urls: &[&str]) -> ApiResult<Self> {
+        if urls.is_empty() {
+            return Err("At least one provider URL is required".to_string());
+        }
+        let endpoints = urls
+            .iter()
+            .map(|url| {
+                let client = if is_ws_url(url) {
+                    EndpointClient::Ws(tokio::sync::Mutex::new(None))
+                } else {
+                    reqwest::Url::parse(url).map_err(|_| format!("Invalid provider URL \"{}\"", url))?;
+                    EndpointClient::Http { client: reqwest::Client::new(), next_id: AtomicU64::new(1) }
+                };
+                Ok(Endpoint {
+                    url: url.to_string(),
+                    client,
+                    health: Mutex::new(Health::default()),
+                    limiter: Mutex::new(None),
+                    in_flight: Mutex::new(None),
+                })
+            })
+            .collect::<ApiResult<Vec<_>>>()?;
+        Ok(Self { endpoints: Arc::new(endpoints), read_quorum: None })
+    }
+
+    /// Parses a comma-separated list of provider URLs, as accepted by `AGENTPROXY_PROVIDER`.
+    pub fn from_urls(provider_urls: &str) -> ApiResult<Self> {
+        let urls: Vec<&str> = split_provider_urls(provider_urls).collect();
+        Self::new(&urls)
+    }
+
+    /// Requires `quorum` endpoints to return the same result for critical reads such as
+    /// `AgentManagerNode::allowed_signer`.
+    pub fn with_read_quorum(mut self, quorum: usize) -> ApiResult<Self> {
+        if quorum == 0 || quorum > self.endpoints.len() {
+            return Err(format!(
+                "Read quorum {} needs between 1 and {} provider URLs",
+                quorum,
+                self.endpoints.len()
+            ));
+        }
+        self.read_quorum = Some(quorum);
+        Ok(self)
+    }
+
+    pub fn read_quorum(&self) -> Option<usize> {
+        self.read_quorum
+    }
+
+    /// Applies `limits` to every endpoint.
+    pub fn with_limits(self, limits: EndpointLimits) -> ApiResult<Self> {
+        limits.validate()?;
+        for endpoint in self.endpoints.iter() {
+            endpoint.set_limits(limits);
+        }
+        Ok(self)
+    }
+
+    /// Applies `limits` to the endpoint with the given URL, replacing earlier limits.
+    pub fn with_endpoint_limits(self, url: &str, limits: EndpointLimits) -> ApiResult<Self> {
+        limits.validate()?;
+        let endpoint = self
+            .endpoints
+            .iter()
+            .find(|endpoint| endpoint.url == url.trim())
+            .ok_or_else(|| format!("Unknown provider URL \"{}\"", url))?;
+        endpoint.set_limits(limits);
+        Ok(self)
+    }
+
+    /// The `ws://` and `wss://` endpoints, which event streams subscribe through.
+    pub fn ws_urls(&self) -> Vec<String> {
+        self.endpoints
+            .iter()
+            .filter(|endpoint| matches!(endpoint.client, EndpointClient::Ws(_)))
+            .map(|endpoint| endpoint.url.clone())
+            .collect()
+    }
+
+    pub fn status(&self) -> Vec<EndpointStatus> {
+        let now = Instant::now();
+        self.endpoints
+            .iter()
+            .map(|endpoint| {
+                let healthy = endpoint.is_healthy(now);
+                let health = endpoint.health.lock().unwrap();
+                EndpointStatus {
+                    url: endpoint.url.clone(),
+                    healthy,
+                    throttled: health.throttled_until.is_some_and(|until| until > now),
+                    failures: health.failures,
+                    last_error: health.last_error.clone(),
+                }
+            })
+            .collect()
+    }
+
+    // healthy endpoints first, each group in configured order
+    fn ordered(&self) -> Vec<&Endpoint> {
+        let now = Instant::now();
+        let (mut healthy, unhealthy): (Vec<&Endpoint>, Vec<&Endpoint>) =
+            self.endpoints.iter().partition(|endpoint| endpoint.is_healthy(now));
+        healthy.extend(unhealthy);
+        healthy
+    }
+
+    async fn request_endpoint(&self, endpoint: &Endpoint, method: &str, params: &Value) -> Result<Value, TransportError> {
+        if let Some(until) = endpoint.throttled_until(Instant::now()) {
+            let remaining = until.saturating_duration_since(Instant::now());
+            return Err(TransportError::AllFailed(vec![format!(
+                "{}: rate limited for another {}ms",
+                endpoint.url,
+                remaining.as_millis()
+            )]));
+        }
+        match endpoint.request(method, params).await {
+            Ok(value) => {
+                endpoint.record_success();
+                Ok(value)
+            }
+            Err(EndpointError::Rpc(e)) => {
+                // the endpoint is up, the request itself was rejected
+                endpoint.record_success();
+                Err(TransportError::Rpc(e))
+            }
+            Err(EndpointError::Transport(e)) => {
+                endpoint.record_failure(&e);
+                Err(TransportError::AllFailed(vec![format!("{}: {}", endpoint.url, e)]))
+            }
+            Err(EndpointError::RateLimited { retry_after, error }) => {
+                endpoint.record_throttle(retry_after, &error);
+                Err(TransportError::AllFailed(vec![format!("{}: rate limited ({})", endpoint.url, error)]))
+            }
+        }
+    }
+
+    /// When every endpoint is throttled, the earliest time one of them is available.
+    fn all_throttled_until(&self) -> Option<Instant> {
+        let now = Instant::now();
+        self.endpoints
+            .iter()
+            .map(|endpoint| endpoint.throttled_until(now))
+            .collect::<Option<Vec<_>>>()
+            .and_then(|until| until.into_iter().min())
+    }
+
+    /// Sends the request to every endpoint at once and returns the result at least
+    /// `quorum` of them agree on.
+    pub async fn quorum_request<T, R>(&self, method: &str, params: T, quorum: usize) -> Result<R, TransportError>
+    where
+        T: Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(params).map_err(TransportError::Serde)?;
+        let results = join_all(
+            self.endpoints
+                .iter()
+                .map(|endpoint| self.request_endpoint(endpoint, method, &params)),
+        ).await;
+
+        let mut groups: Vec<(Value, usize)> = Vec::new();
+        let mut errors = Vec::new();
+        for result in results {
+            match result {
+                Ok(value) => match groups.iter_mut().find(|(seen, _)| *seen == value) {
+                    Some((_, count)) => *count += 1,
+                    None => groups.push((value, 1)),
+                },
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+
+        match groups.into_iter().max_by_key(|(_, count)| *count) {
+            Some((value, count)) if count >= quorum => serde_json::from_value(value).map_err(TransportError::Serde),
+            best => Err(TransportError::Quorum(format!(
+                "RPC quorum of {} not reached for {}: best agreement {} of {} endpoints{}",
+                quorum,
+                method,
+                best.map(|(_, count)| count).unwrap_or(0),
+                self.endpoints.len(),
+                if errors.is_empty() { String::new() } else { format!(" ({})", errors.join("; ")) }
+            ))),
+        }
+    }
+
+    /// The lowest head across endpoints, so quorum reads compare the same state.
+    async fn lowest_block_number(&self) -> Result<U64, TransportError> {
+        let params = Value::Array(Vec::new());
+        let heads = join_all(
+            self.endpoints
+                .iter()
+                .map(|endpoint| self.request_endpoint(endpoint, "eth_blockNumber", &params)),
+        ).await;
+        heads
+            .into_iter()
+            .filter_map(|head| head.ok().and_then(|head| serde_json::from_value::<U64>(head).ok()))
+            .min()
+            .ok_or_else(|| TransportError::Quorum("No RPC endpoint returned a block number".to_string()))
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for AttpsTransport {
+    type Error = TransportError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: fmt::Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(params).map_err(TransportError::Serde)?;
+        let started = Instant::now();
+        let result = self.request_value(method, &params).instrument(tracing::trace_span!("rpc", method)).await;
+        metrics::observe_rpc(method, started.elapsed(), result.is_ok());
+        serde_json::from_value(result?).map_err(TransportError::Serde)
+    }
+}
+
+impl AttpsTransport {
+    async fn request_value(&self, method: &str, params: &Value) -> Result<Value, TransportError> {
+        let mut retries = 0;
+        loop {
+            let mut errors = Vec::new();
+            for endpoint in self.ordered() {
+                match self.request_endpoint(endpoint, method, params).await {
+                    Ok(value) => return Ok(value),
+                    Err(TransportError::AllFailed(mut failed)) => errors.append(&mut failed),
+                    Err(e) => return Err(e),
+                }
+            }
+
+            match self.all_throttled_until() {
+                Some(until) if retries < RATE_LIMIT_RETRIES => {
+                    retries += 1;
+                    tokio::time::sleep_until(until.into()).await;
+                }
+                _ => return Err(TransportError::AllFailed(errors)),
+            }
+        }
+    }
+}
+
+pub fn is_ws_url(url: &str) -> bool {
+    url.starts_with("ws://") || url.starts_with("wss://")
+}
+
+/// Parses a `Retry-After` value, either delay-seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+pub fn split_provider_urls(provider_urls: &str) -> impl Iterator<Item = &str> {
+    provider_urls.split(',').map(str::trim).filter(|url| !url.is_empty())
+}
+
+/// Runs a read-only contract call, through the read quorum when one is configured.
+pub(crate) async fn critical_call<D: Detokenize>(
+    client: &SignerClient,
+    call: ContractCall<SignerClient, D>,
+) -> ApiResult<D> {
+    let transport: &AttpsTransport = client.provider().as_ref();
+    let Some(quorum) = transport.read_quorum() else {
+        return call.call().await.map_err(|e| format!("{:?}", e));
+    };
+
+    let mut tx = call.tx.clone();
+    tx.set_from(client.address());
+    let block = match call.block {
+        Some(block) => block,
+        None => transport.lowest_block_number().await.map_err(|e| e.to_string())?.into(),
+    };
+
+    let output: Bytes = transport
+        .quorum_request("eth_call", (tx, block), quorum)
+        .await
+        .map_err(|e| e.to_string())?;
+    let tokens = call
+        .function
+        .decode_output(&output)
+        .map_err(|e| format!("Failed to decode {} output: {}", call.function.name, e))?;
+    D::from_tokens(tokens).map_err(|e| format!("Failed to decode {} output: {}", call.function.name, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::mock_server::{MockRequest, MockResponse, MockServer};
+
+    async fn rpc_endpoint(block: u64, status: u16) -> MockServer {
+        MockServer::start(Arc::new(move |request: MockRequest| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let response = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": body["id"],
+                "result": format!("{:#x}", block),
+            });
+            if status != 200 {
+                return MockResponse::new(status, "text/plain", "Service Unavailable");
+            }
+            MockResponse::new(status, "application/json", response.to_string())
+        }))
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_failover_and_quorum() {
+        let down = rpc_endpoint(0, 503).await;
+        let first = rpc_endpoint(100, 200).await;
+        let second = rpc_endpoint(100, 200).await;
+        let lagging = rpc_endpoint(99, 200).await;
+
+        let transport = AttpsTransport::from_urls(&format!("{}, {}", down.url(), first.url())).unwrap();
+        let block: U64 = JsonRpcClient::request(&transport, "eth_blockNumber", ()).await.unwrap();
+        assert_eq!(block, U64::from(100));
+        let status = transport.status();
+        assert!(!status[0].healthy && status[0].failures == 1 && status[1].healthy);
+
+        let urls = [down.url(), first.url(), second.url(), lagging.url()];
+        let urls: Vec<&str> = urls.iter().map(String::as_str).collect();
+        let transport = AttpsTransport::new(&urls).unwrap().with_read_quorum(2).unwrap();
+        let block: U64 = transport.quorum_request("eth_blockNumber", (), 2).await.unwrap();
+        assert_eq!(block, U64::from(100));
+        assert_eq!(transport.lowest_block_number().await.unwrap(), U64::from(99));
+
+        let err = transport.quorum_request::<_, U64>("eth_blockNumber", (), 3).await.unwrap_err();
+        assert!(err.to_string().starts_with("RPC quorum of 3 not reached for eth_blockNumber: best agreement 2 of 4"));
+        assert!(AttpsTransport::new(&urls).unwrap().with_read_quorum(5).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limits_and_retry_after() {
+        let requests = Arc::new(AtomicU64::new(0));
+        let counter = requests.clone();
+        let throttling = MockServer::start(Arc::new(move |request: MockRequest| {
+            if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                return MockResponse {
+                    headers: vec![("Retry-After", "1".to_string())],
+                    ..MockResponse::new(429, "text/plain", "Too Many Requests")
+                };
+            }
+            let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let response = serde_json::json!({"jsonrpc": "2.0", "id": body["id"], "result": "0x64"});
+            MockResponse::new(200, "application/json", response.to_string())
+        }))
+        .await
+        .unwrap();
+
+        let transport = AttpsTransport::new(&[&throttling.url()]).unwrap();
+        let started = Instant::now();
+        let block: U64 = JsonRpcClient::request(&transport, "eth_blockNumber", ()).await.unwrap();
+        assert_eq!(block, U64::from(100));
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // 10 requests per second with a burst of 2: the last of five waits 300 ms
+        let limits = EndpointLimits { requests_per_second: Some(10.0), burst: Some(2), max_in_flight: Some(1) };
+        let transport = AttpsTransport::new(&[&throttling.url()]).unwrap().with_limits(limits).unwrap();
+        let started = Instant::now();
+        let blocks = join_all((0..5).map(|_| JsonRpcClient::request::<_, U64>(&transport, "eth_blockNumber", ()))).await;
+        assert!(blocks.into_iter().all(|block| block.is_ok()));
+        assert!(started.elapsed() >= Duration::from_millis(300));
+
+        assert!(transport.clone().with_endpoint_limits("http://127.0.0.1:1", limits).is_err());
+        assert!(transport.with_limits(EndpointLimits { max_in_flight: Some(0), ..limits }).is_err());
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use ethers::abi::Abi;
+use serde::Serialize;
+
+use crate::core::ApiResult;
+use crate::svc::agent_factory_node::AGENTFACTORYCONTRACT_ABI;
+use crate::svc::agent_manager_node::AGENTMANAGERCONTRACT_ABI;
+use crate::svc::agent_proxy_node::AGENTPROXYCONTRACT_ABI;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum ContractKind {
+    AgentProxy,
+    AgentManager,
+    AgentFactory,
+}
+
+impl ContractKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContractKind::AgentProxy => "AgentProxy",
+            ContractKind::AgentManager => "AgentManager",
+            ContractKind::AgentFactory => "AgentFactory",
+        }
+    }
+
+    /// The ABI the SDK's typed bindings were generated from.
+    pub fn bundled_abi(&self) -> &'static Abi {
+        match self {
+            ContractKind::AgentProxy => &AGENTPROXYCONTRACT_ABI,
+            ContractKind::AgentManager => &AGENTMANAGERCONTRACT_ABI,
+            ContractKind::AgentFactory => &AGENTFACTORYCONTRACT_ABI,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self { major, minor, patch }
+    }
+
+    /// Parses `1.2.3`, `v1.2` or `1`; missing parts are zero.
+    pub fn parse(version: &str) -> ApiResult<Self> {
+        let trimmed = version.trim();
+        let trimmed = trimmed.strip_prefix(['v', 'V']).unwrap_or(trimmed);
+        let parts: Vec<&str> = trimmed.split('.').collect();
+        if parts.len() > 3 {
+            return Err(format!("Invalid version \"{}\"", version));
+        }
+        let mut numbers = [0u64; 3];
+        for (number, part) in numbers.iter_mut().zip(parts) {
+            *number = part.parse().map_err(|_| format!("Invalid version \"{}\"", version))?;
+        }
+        Ok(Self::new(numbers[0], numbers[1], numbers[2]))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A parsed `typeAndVersion()` result such as `AgentProxy 1.0.0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeAndVersion {
+    pub contract_type: String,
+    pub version: Version,
+}
+
+impl TypeAndVersion {
+    pub fn parse(type_and_version: &str) -> ApiResult<Self> {
+        let (contract_type, version) = type_and_version
+            .trim()
+            .rsplit_once(' ')
+            .ok_or_else(|| format!("Invalid typeAndVersion \"{}\"", type_and_version))?;
+        Ok(Self {
+            contract_type: contract_type.trim().to_string(),
+            version: Version::parse(version)
+                .map_err(|_| format!("Invalid typeAndVersion \"{}\"", type_and_version))?,
+        })
+    }
+}
+
+impl fmt::Display for TypeAndVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.contract_type, self.version)
+    }
+}
+
+/// The ABI of one contract over a range of `typeAndVersion` versions.
+#[derive(Debug, Clone)]
+pub struct AbiVariant {
+    pub contract: ContractKind,
+    pub min_version: Version,
+    /// Exclusive upper bound; `None` accepts every later version.
+    pub max_version: Option<Version>,
+    /// Accepted `agentVersion()` values for AgentManager variants; empty accepts any.
+    pub agent_versions: Vec<String>,
+    pub abi: Abi,
+}
+
+impl AbiVariant {
+    pub fn new(contract: ContractKind, min_version: Version, max_version: Option<Version>, abi: Abi) -> Self {
+        Self { contract, min_version, max_version, agent_versions: Vec::new(), abi }
+    }
+
+    pub fn from_json(
+        contract: ContractKind,
+        min_version: Version,
+        max_version: Option<Version>,
+        abi_json: &str,
+    ) -> ApiResult<Self> {
+        let abi: Abi = serde_json::from_str(abi_json)
+            .map_err(|e| format!("Invalid {} ABI: {}", contract.name(), e))?;
+        Ok(Self::new(contract, min_version, max_version, abi))
+    }
+
+    // the range is not yet checked against the deployed contracts' typeAndVersion, which is
+    // why the default policy only warns
+    pub fn bundled(contract: ContractKind) -> Self {
+        Self::new(contract, Version::new(1, 0, 0), Some(Version::new(2, 0, 0)), contract.bundled_abi().clone())
+    }
+
+    pub fn with_agent_versions(mut self, agent_versions: Vec<String>) -> Self {
+        self.agent_versions = agent_versions;
+        self
+    }
+
+    pub fn supports(&self, version: Version) -> bool {
+        version >= self.min_version && self.max_version.is_none_or(|max| version < max)
+    }
+
+    /// Functions the typed bindings call that this ABI does not declare with the same
+    /// inputs and outputs.
+    pub fn missing_binding_functions(&self) -> Vec<String> {
+        let declared: HashSet<String> = self.abi.functions().map(|function| function.signature()).collect();
+        let mut missing: Vec<String> = self
+            .contract
+            .bundled_abi()
+            .functions()
+            .map(|function| function.signature())
+            .filter(|signature| !declared.contains(signature))
+            .collect();
+        missing.sort();
+        missing
+    }
+
+    fn range(&self) -> String {
+        match self.max_version {
+            Some(max) => format!(">={} <{}", self.min_version, max),
+            None => format!(">={}", self.min_version),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VersionPolicy {
+    /// Fail to connect to an unsupported contract version.
+    Refuse,
+    /// Log the incompatibility, or a failure to read the versions, with `tracing::warn!`
+    /// and connect anyway.
+    #[default]
+    Warn,
+    /// Do not read the versions at all.
+    Skip,
+}
+
+/// ABI variants the SDK accepts, keyed by contract and version range. Variants
+/// registered later take precedence over earlier ones with an overlapping range.
+#[derive(Debug, Clone)]
+pub struct AbiRegistry {
+    variants: Vec<AbiVariant>,
+}
+
+impl Default for AbiRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl AbiRegistry {
+    pub fn builtin() -> Self {
+        Self {
+            variants: vec![
+                AbiVariant::bundled(ContractKind::AgentProxy),
+                AbiVariant::bundled(ContractKind::AgentManager),
+                AbiVariant::bundled(ContractKind::AgentFactory),
+            ],
+        }
+    }
+
+    pub fn register(&mut self, variant: AbiVariant) {
+        self.variants.push(variant);
+    }
+
+    pub fn variant(&self, contract: ContractKind, version: Version) -> Option<&AbiVariant> {
+        self.variants
+            .iter()
+            .rev()
+            .find(|variant| variant.contract == contract && variant.supports(version))
+    }
+
+    /// Checks a `typeAndVersion()` result (and, for the manager, `agentVersion()`)
+    /// against the registered variants and returns the one that applies.
+    pub fn check(
+        &self,
+        contract: ContractKind,
+        type_and_version: &str,
+        agent_version: Option<&str>,
+    ) -> ApiResult<&AbiVariant> {
+        let reported = TypeAndVersion::parse(type_and_version)
+            .map_err(|e| format!("Incompatible {}: {}", contract.name(), e))?;
+        if reported.contract_type != contract.name() {
+            return Err(format!(
+                "Incompatible {}: contract reports \"{}\"",
+                contract.name(),
+                type_and_version
+            ));
+        }
+
+        let variant = self.variant(contract, reported.version).ok_or_else(|| {
+            let supported: Vec<String> = self
+                .variants
+                .iter()
+                .filter(|variant| variant.contract == contract)
+                .map(|variant| variant.range())
+                .collect();
+            format!(
+                "Incompatible {}: version {} is not supported (supported: {})",
+                contract.name(),
+                reported.version,
+                supported.join(", ")
+            )
+        })?;
+
+        let missing = variant.missing_binding_functions();
+        if !missing.is_empty() {
+            return Err(format!(
+                "Incompatible {}: the ABI for version {} lacks functions the SDK calls: {}",
+                contract.name(),
+                reported.version,
+                missing.join(", ")
+            ));
+        }
+
+        if let Some(agent_version) = agent_version {
+            if !variant.agent_versions.is_empty() && !variant.agent_versions.iter().any(|v| v == agent_version) {
+                return Err(format!(
+                    "Incompatible {}: agentVersion \"{}\" is not supported (supported: {})",
+                    contract.name(),
+                    agent_version,
+                    variant.agent_versions.join(", ")
+                ));
+            }
+        }
+
+        Ok(variant)
+    }
+}
+
+/// The versions read from a deployment at connect time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractVersions {
+    pub agent_proxy: String,
+    pub agent_manager: String,
+    pub agent_factory: String,
+    pub agent_version: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_checks() {
+        assert_eq!(Version::parse("v1.2").unwrap(), Version::new(1, 2, 0));
+        assert!(Version::parse("1.2.3.4").is_err());
+        let parsed = TypeAndVersion::parse("AgentProxy 1.0.0").unwrap();
+        assert_eq!(parsed.to_string(), "AgentProxy 1.0.0");
+
+        let mut registry = AbiRegistry::builtin();
+        assert!(registry.check(ContractKind::AgentProxy, "AgentProxy 1.4.2", None).is_ok());
+
+        let err = registry.check(ContractKind::AgentProxy, "AgentManager 1.0.0", None).unwrap_err();
+        assert_eq!(err, "Incompatible AgentProxy: contract reports \"AgentManager 1.0.0\"");
+        let err = registry.check(ContractKind::AgentProxy, "AgentProxy 2.0.0", None).unwrap_err();
+        assert_eq!(err, "Incompatible AgentProxy: version 2.0.0 is not supported (supported: >=1.0.0 <2.0.0)");
+
+        // a 2.x proxy that dropped a function the bindings call
+        let mut abi = ContractKind::AgentProxy.bundled_abi().clone();
+        abi.functions.remove("verify");
+        registry.register(AbiVariant::new(ContractKind::AgentProxy, Version::new(2, 0, 0), None, abi));
+        let err = registry.check(ContractKind::AgentProxy, "AgentProxy 2.0.0", None).unwrap_err();
+        assert!(err.contains("lacks functions the SDK calls: verify("), "{}", err);
+
+        registry.register(
+            AbiVariant::bundled(ContractKind::AgentManager).with_agent_versions(vec!["v1".to_string()]),
+        );
+        assert!(registry.check(ContractKind::AgentManager, "AgentManager 1.0.0", Some("v1")).is_ok());
+        let err = registry.check(ContractKind::AgentManager, "AgentManager 1.0.0", Some("v2")).unwrap_err();
+        assert!(err.contains("agentVersion \"v2\" is not supported"));
+    }
+}
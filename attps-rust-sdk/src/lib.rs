@@ -1,13 +1,35 @@
 mod svc;
-pub use svc::agent_factory_node::AgentFactoryNode;
-pub use svc::agent_manager_node::{AgentManagerNode, extract_setting_digests};
-pub use svc::agent_proxy_node::{AgentProxyNode, Proofs, Metadata};
+pub use svc::agent_factory_node::{AgentFactoryNode, AgentCreatedFilter};
+pub use svc::agent_manager_node::{AgentManagerNode, AgentManagerContractEvents, AgentConfig, AgentHeader, AgentSettings, extract_setting_digests, settings_digest_version, SETTINGS_DIGEST_PREFIX_V1};
+pub use svc::agent_proxy_node::{AgentProxyNode, AgentProxyContractEvents, Proofs, Metadata, VerifyCallResult};
+pub use svc::client::{AttpsClient, ConnectOptions, check_versions};
+pub use svc::manifest::{AgentManifest, AppliedChange, ManifestAgent, ManifestHeader, Plan, PlanAction, PlannedChange};
+pub use svc::onboard::{OnboardReport, OnboardStep, Onboarding};
+pub use svc::ownership::{OwnershipStatus, OwnershipTransfer};
+pub use svc::proposals::{Proposal, ProposalState, ProposalTracker};
+pub use svc::review::{Applicant, ReviewAction, ReviewPolicy, ReviewQueue, ReviewReport};
+pub use svc::webhooks::{verify_webhook_signature, webhook_signature, DeadLetter, WebhookDispatcher, WebhookEndpoint, WebhookEvent, WebhookFormat};
+pub use svc::verify_batch::{BatchVerifier, VerifyOutcome, VerifyReport, VerifyRequest};
+#[cfg(feature = "gateway")]
+pub use svc::gateway::{Gateway, GatewayConfig, GatewayRoute};
+#[cfg(feature = "indexer")]
+pub use svc::indexer::{Indexer, SyncReport};
 
 mod core;
-pub use core::ApiResult;
-pub use core::consts::{AGENTPROXY_CONTRACT, AGENTPROXY_PROVIDER, WALLET_PRIVATE_KEY};
+pub use core::{ApiResult, SignerClient};
+pub use core::config::{AttpsConfig, ConfigFile, ProfileConfig};
+pub use core::deployments::{Deployment, DeploymentRegistry, DeploymentsFile};
+pub use core::events::{EventStream, LogStream};
+#[cfg(feature = "metrics")]
+pub use core::metrics::registry as metrics_registry;
+pub use core::transport::{AttpsTransport, EndpointLimits, EndpointStatus, TransportError};
+pub use core::versions::{AbiRegistry, AbiVariant, ContractKind, ContractVersions, TypeAndVersion, Version, VersionPolicy};
 
 
 mod utils;
-pub use utils::common::generate_signature_proof;
+pub use utils::common::{generate_signature_proof, generate_signature_proof_with_signers, encode_signature_proof, decode_revert_data};
+pub use utils::remote_signer::{MockRemoteSigner, RemoteSigner};
+pub use utils::secret::{expose_secrets, SecretKey};
+pub use utils::signer::{AttpsSigner, ProofSigner, SignerError};
+pub use utils::wallet::{PasswordSource, WalletSource};
 
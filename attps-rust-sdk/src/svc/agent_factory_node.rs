@@ -3,11 +3,15 @@ use std::sync::Arc;
 use tokio::time::{sleep, Duration};
 
 use ethers::prelude::*;
-use ethers::signers::Wallet;
-use ethers::core::k256::ecdsa::SigningKey;
+use tracing::Instrument;
 use ethers::types::Address;
 
-use crate::core::ApiResult;
+use crate::core::events::EventStream;
+use crate::core::metrics;
+use crate::core::transport::AttpsTransport;
+use crate::core::{ApiResult, SignerClient};
+use crate::utils::secret::SecretKey;
+use crate::utils::signer::AttpsSigner;
 
 abigen!(
     AgentFactoryContract,
@@ -15,21 +19,55 @@ abigen!(
 );
 
 pub struct AgentFactoryNode {
-    contract: AgentFactoryContract<SignerMiddleware<Provider<Http>, Wallet<SigningKey>>>,
+    contract: AgentFactoryContract<SignerClient>,
 }
 
 impl AgentFactoryNode {
-    pub async fn new(provider_url: &str, contract_addr: &str, private_key: &str) -> Self {
-        let provider = Provider::<Http>::try_from(provider_url).expect("Invalid provider URL");
-        let chain_id = provider.get_chainid().await.expect("Failed to get chain ID").as_u64();
-        let wallet: Wallet<SigningKey> = private_key.parse().expect("Invalid private key");
-        let wallet = wallet.with_chain_id(chain_id);
-        let client = SignerMiddleware::new(provider.clone(), wallet);
+    pub async fn new(provider_url: &str, contract_addr: &str, private_key: &SecretKey) -> Self {
+        Self::try_new(provider_url, contract_addr, private_key)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub async fn try_new(provider_url: &str, contract_addr: &str, private_key: &SecretKey) -> ApiResult<Self> {
+        Self::try_new_with_signer(provider_url, contract_addr, private_key.to_wallet()?).await
+    }
+
+    pub async fn try_new_with_signer(
+        provider_url: &str,
+        contract_addr: &str,
+        signer: impl Into<AttpsSigner>,
+    ) -> ApiResult<Self> {
+        let transport = AttpsTransport::from_urls(provider_url).map_err(|_| {
+            "Invalid provider URL".to_string()
+        })?;
+        Self::try_new_with_transport(transport, contract_addr, signer).await
+    }
+
+    pub async fn try_new_with_transport(
+        transport: AttpsTransport,
+        contract_addr: &str,
+        signer: impl Into<AttpsSigner>,
+    ) -> ApiResult<Self> {
+        let provider = Provider::new(transport);
+        let chain_id = provider.get_chainid().await.map_err(|e| {
+            format!("Failed to get chain ID: {:?}", e)
+        })?.as_u64();
+        let signer = signer.into().with_chain_id(chain_id);
+        let client = SignerMiddleware::new(provider.clone(), signer);
         let provider = Arc::new(client);
 
-        let address = contract_addr.parse::<Address>().unwrap();
+        let address = contract_addr.parse::<Address>().map_err(|_| {
+            "Invalid contract address".to_string()
+        })?;
         let contract = AgentFactoryContract::new(address, provider.clone());
-        Self { contract }
+        Ok(Self { contract })
+    }
+
+    /// Streams this contract's events over the transport's WebSocket endpoints,
+    /// starting at `from_block` when given and otherwise at the current head.
+    pub fn events(&self, from_block: Option<u64>) -> ApiResult<EventStream<AgentCreatedFilter>> {
+        EventStream::new(&self.contract.client(), self.contract.address(), from_block)
     }
 
     async fn retry_with_exponential_backoff<F, Fut, T>(&self, mut f: F) -> ApiResult<T>
@@ -42,9 +80,11 @@ impl AgentFactoryNode {
         let mut delay = Duration::from_millis(100);
 
         while attempts < max_attempts {
-            match f().await {
+            match f().instrument(tracing::debug_span!("attempt", attempt = attempts + 1)).await {
                 Ok(result) => return Ok(result),
-                Err(_) if attempts < max_attempts - 1 => {
+                Err(e) if attempts < max_attempts - 1 => {
+                    tracing::debug!(error = %e, "Retrying");
+                    metrics::count_retry("AgentFactory");
                     sleep(delay).await;
                     delay *= 2;
                 }
@@ -56,6 +96,7 @@ impl AgentFactoryNode {
         Err("Max retry attempts reached".to_string())
     }
 
+    #[tracing::instrument(name = "AgentFactory.get_agent_manager", skip_all, fields(contract = "AgentFactory", method = "get_agent_manager"))]
     pub async fn get_agent_manager(&self) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.agent_manager().call().await
@@ -64,6 +105,7 @@ impl AgentFactoryNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentFactory.get_agent_proxy", skip_all, fields(contract = "AgentFactory", method = "get_agent_proxy"))]
     pub async fn get_agent_proxy(&self) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.agent_proxy().call().await
@@ -72,6 +114,7 @@ impl AgentFactoryNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentFactory.get_agents_count", skip_all, fields(contract = "AgentFactory", method = "get_agents_count"))]
     pub async fn get_agents_count(&self) -> ApiResult<u64> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.get_agents_count().call().await
@@ -79,6 +122,7 @@ impl AgentFactoryNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentFactory.get_agents_in_range", skip_all, fields(contract = "AgentFactory", method = "get_agents_in_range"))]
     pub async fn get_agents_in_range(&self, agent_idx_start: u64, agent_idx_end: u64) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.get_agents_in_range(agent_idx_start, agent_idx_end).call().await
@@ -87,6 +131,7 @@ impl AgentFactoryNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentFactory.get_all_agents", skip_all, fields(contract = "AgentFactory", method = "get_all_agents"))]
     pub async fn get_all_agents(&self) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.get_all_agents().call().await
@@ -95,6 +140,16 @@ impl AgentFactoryNode {
         }).await
     }
 
+    /// Like `get_all_agents`, but returns the addresses.
+    #[tracing::instrument(name = "AgentFactory.agents", skip_all, fields(contract = "AgentFactory", method = "agents"))]
+    pub async fn agents(&self) -> ApiResult<Vec<Address>> {
+        self.retry_with_exponential_backoff(|| async {
+            self.contract.get_all_agents().call().await
+                .map_err(|e| format!("Failed to get all agents: {}", e))
+        }).await
+    }
+
+    #[tracing::instrument(name = "AgentFactory.has_agent", skip_all, fields(contract = "AgentFactory", method = "has_agent", agent = %agent))]
     pub async fn has_agent(&self, agent: &str) -> ApiResult<bool> {
         let agent_address = agent.parse::<Address>().map_err(|_| {
             "Invalid address format".to_string()
@@ -106,6 +161,7 @@ impl AgentFactoryNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentFactory.type_and_version", skip_all, fields(contract = "AgentFactory", method = "type_and_version"))]
     pub async fn type_and_version(&self) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.type_and_version().call().await
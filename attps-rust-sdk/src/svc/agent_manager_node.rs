@@ -4,10 +4,15 @@ use regex::Regex;
 use hex;
 
 use ethers::prelude::*;
-use ethers::core::k256::ecdsa::SigningKey;
+use tracing::Instrument;
 use ethers::types::{Address, H256};
 
-use crate::core::ApiResult;
+use crate::core::events::EventStream;
+use crate::core::metrics;
+use crate::core::transport::{critical_call, AttpsTransport};
+use crate::core::{ApiResult, SignerClient};
+use crate::utils::secret::SecretKey;
+use crate::utils::signer::AttpsSigner;
 
 abigen!(
     AgentManagerContract,
@@ -15,21 +20,59 @@ abigen!(
 );
 
 pub struct AgentManagerNode {
-    contract: AgentManagerContract<SignerMiddleware<Provider<Http>, Wallet<SigningKey>>>,
+    contract: AgentManagerContract<SignerClient>,
 }
 
 impl AgentManagerNode {
-    pub async fn new(provider_url: &str, contract_addr: &str, private_key: &str) -> Self {
-        let provider = Provider::<Http>::try_from(provider_url).expect("Invalid provider URL");
-        let chain_id = provider.get_chainid().await.expect("Failed to get chain ID").as_u64();
-        let wallet: Wallet<SigningKey> = private_key.parse().expect("Invalid private key");
-        let wallet = wallet.with_chain_id(chain_id);
-        let client = SignerMiddleware::new(provider.clone(), wallet);
+    pub async fn new(provider_url: &str, contract_addr: &str, private_key: &SecretKey) -> Self {
+        Self::try_new(provider_url, contract_addr, private_key)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub async fn try_new(provider_url: &str, contract_addr: &str, private_key: &SecretKey) -> ApiResult<Self> {
+        Self::try_new_with_signer(provider_url, contract_addr, private_key.to_wallet()?).await
+    }
+
+    pub async fn try_new_with_signer(
+        provider_url: &str,
+        contract_addr: &str,
+        signer: impl Into<AttpsSigner>,
+    ) -> ApiResult<Self> {
+        let transport = AttpsTransport::from_urls(provider_url).map_err(|_| {
+            "Invalid provider URL".to_string()
+        })?;
+        Self::try_new_with_transport(transport, contract_addr, signer).await
+    }
+
+    pub async fn try_new_with_transport(
+        transport: AttpsTransport,
+        contract_addr: &str,
+        signer: impl Into<AttpsSigner>,
+    ) -> ApiResult<Self> {
+        let provider = Provider::new(transport);
+        let chain_id = provider.get_chainid().await.map_err(|e| {
+            format!("Failed to get chain ID: {:?}", e)
+        })?.as_u64();
+        let signer = signer.into().with_chain_id(chain_id);
+        let client = SignerMiddleware::new(provider.clone(), signer);
         let provider = Arc::new(client);
 
-        let address = contract_addr.parse::<Address>().unwrap();
+        let address = contract_addr.parse::<Address>().map_err(|_| {
+            "Invalid contract address".to_string()
+        })?;
         let contract = AgentManagerContract::new(address, provider.clone());
-        Self { contract }
+        Ok(Self { contract })
+    }
+
+    /// Streams this contract's events over the transport's WebSocket endpoints,
+    /// starting at `from_block` when given and otherwise at the current head.
+    pub fn events(&self, from_block: Option<u64>) -> ApiResult<EventStream<AgentManagerContractEvents>> {
+        EventStream::new(&self.contract.client(), self.contract.address(), from_block)
+    }
+
+    pub(crate) fn contract(&self) -> &AgentManagerContract<SignerClient> {
+        &self.contract
     }
 
     async fn retry_with_exponential_backoff<F, Fut, T>(&self, mut f: F) -> ApiResult<T>
@@ -42,9 +85,11 @@ impl AgentManagerNode {
         let mut delay = Duration::from_millis(100);
 
         while attempts < max_attempts {
-            match f().await {
+            match f().instrument(tracing::debug_span!("attempt", attempt = attempts + 1)).await {
                 Ok(result) => return Ok(result),
-                Err(_) if attempts < max_attempts - 1 => {
+                Err(e) if attempts < max_attempts - 1 => {
+                    tracing::debug!(error = %e, "Retrying");
+                    metrics::count_retry("AgentManager");
                     sleep(delay).await;
                     delay *= 2;
                 }
@@ -56,6 +101,7 @@ impl AgentManagerNode {
         Err("Max retry attempts reached".to_string())
     }
 
+    #[tracing::instrument(name = "AgentManager.agent_proxy", skip_all, fields(contract = "AgentManager", method = "agent_proxy"))]
     pub async fn agent_proxy(&self) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.agent_proxy().call().await
@@ -64,6 +110,7 @@ impl AgentManagerNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.get_owner", skip_all, fields(contract = "AgentManager", method = "get_owner"))]
     pub async fn get_owner(&self) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.owner().call().await
@@ -72,6 +119,7 @@ impl AgentManagerNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.get_type_and_version", skip_all, fields(contract = "AgentManager", method = "get_type_and_version"))]
     pub async fn get_type_and_version(&self) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.type_and_version().call().await
@@ -79,6 +127,7 @@ impl AgentManagerNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.agent_version", skip_all, fields(contract = "AgentManager", method = "agent_version"))]
     pub async fn agent_version(&self) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.agent_version().call().await
@@ -86,17 +135,19 @@ impl AgentManagerNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.allowed_agent", skip_all, fields(contract = "AgentManager", method = "allowed_agent", agent = %agent_address))]
     pub async fn allowed_agent(&self, agent_address: &str) -> ApiResult<bool> {
         let address = agent_address.parse::<Address>().map_err(|_| {
             "Invalid address format".to_string()
         })?;
 
         self.retry_with_exponential_backoff(|| async {
-            self.contract.allowed_agent(address).call().await
-                .map_err(|_| "Failed to check if agent is allowed".to_string())
+            critical_call(&self.contract.client(), self.contract.allowed_agent(address)).await
+                .map_err(|e| format!("Failed to check if agent is allowed: {}", e))
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.allowed_signer", skip_all, fields(contract = "AgentManager", method = "allowed_signer", agent = %agent_address, settings_digest = %setting_digest))]
     pub async fn allowed_signer(&self, agent_address: &str, setting_digest: &str, signer_address: &str) -> ApiResult<bool> {
         let agent = agent_address.parse::<Address>().map_err(|_| {
             "Invalid agent address format".to_string()
@@ -111,11 +162,12 @@ impl AgentManagerNode {
         })?;
 
         self.retry_with_exponential_backoff(|| async {
-            self.contract.allowed_signer(agent, setting_digest.into(), signer).call().await
-                .map_err(|_| "Failed to check if signer is allowed".to_string())
+            critical_call(&self.contract.client(), self.contract.allowed_signer(agent, setting_digest.into(), signer)).await
+                .map_err(|e| format!("Failed to check if signer is allowed: {}", e))
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.get_agent_config", skip_all, fields(contract = "AgentManager", method = "get_agent_config", agent = %agent_address, settings_digest = %setting_digest))]
     pub async fn get_agent_config(&self, agent_address: &str, setting_digest: &str) -> ApiResult<String> {
         let agent = agent_address.parse::<Address>().map_err(|_| {
             "Invalid agent address format".to_string()
@@ -132,6 +184,7 @@ impl AgentManagerNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.get_agent_configs", skip_all, fields(contract = "AgentManager", method = "get_agent_configs", agent = %agent_address))]
     pub async fn get_agent_configs(&self, agent_address: &str) -> ApiResult<String> {
         let agent = agent_address.parse::<Address>().map_err(|_| {
             "Invalid agent address format".to_string()
@@ -144,6 +197,20 @@ impl AgentManagerNode {
         }).await
     }
 
+    /// Like `get_agent_configs`, but returns the decoded configs.
+    #[tracing::instrument(name = "AgentManager.agent_configs", skip_all, fields(contract = "AgentManager", method = "agent_configs", agent = %agent_address))]
+    pub async fn agent_configs(&self, agent_address: &str) -> ApiResult<Vec<AgentConfig>> {
+        let agent = agent_address.parse::<Address>().map_err(|_| {
+            "Invalid agent address format".to_string()
+        })?;
+
+        self.retry_with_exponential_backoff(|| async {
+            self.contract.get_agent_configs(agent).call().await
+                .map_err(|e| format!("Failed to get agent configs: {}", e))
+        }).await
+    }
+
+    #[tracing::instrument(name = "AgentManager.get_agent_configs_count", skip_all, fields(contract = "AgentManager", method = "get_agent_configs_count", agent = %agent_address))]
     pub async fn get_agent_configs_count(&self, agent_address: &str) -> ApiResult<u64> {
         let agent = agent_address.parse::<Address>().map_err(|_| {
             "Invalid agent address format".to_string()
@@ -155,6 +222,18 @@ impl AgentManagerNode {
         }).await
     }
 
+    /// The digest the manager gave the agent's latest config with exactly `settings`
+    /// (signer order included), or `None` if it has no such config.
+    pub async fn find_settings_digest(&self, agent_address: &str, settings: &AgentSettings) -> ApiResult<Option<H256>> {
+        let configs = self.agent_configs(agent_address).await?;
+        Ok(configs
+            .iter()
+            .filter(|config| config.settings == *settings)
+            .max_by_key(|config| config.config_block_number)
+            .map(|config| H256::from(config.config_digest)))
+    }
+
+    #[tracing::instrument(name = "AgentManager.get_agent_configs_in_range", skip_all, fields(contract = "AgentManager", method = "get_agent_configs_in_range", agent = %agent_address))]
     pub async fn get_agent_configs_in_range(&self, agent_address: &str, agent_config_idx_start: u64, agent_config_idx_end: u64) -> ApiResult<String> {
         let agent = agent_address.parse::<Address>().map_err(|_| {
             "Invalid agent address format".to_string()
@@ -167,6 +246,7 @@ impl AgentManagerNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.get_all_allowed_agents", skip_all, fields(contract = "AgentManager", method = "get_all_allowed_agents"))]
     pub async fn get_all_allowed_agents(&self) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.get_all_allowed_agents().call().await
@@ -175,6 +255,7 @@ impl AgentManagerNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.get_all_registering_agents", skip_all, fields(contract = "AgentManager", method = "get_all_registering_agents"))]
     pub async fn get_all_registering_agents(&self) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.get_all_registering_agents().call().await
@@ -183,6 +264,25 @@ impl AgentManagerNode {
         }).await
     }
 
+    /// Like `get_all_allowed_agents`, but returns the addresses.
+    #[tracing::instrument(name = "AgentManager.allowed_agents", skip_all, fields(contract = "AgentManager", method = "allowed_agents"))]
+    pub async fn allowed_agents(&self) -> ApiResult<Vec<Address>> {
+        self.retry_with_exponential_backoff(|| async {
+            self.contract.get_all_allowed_agents().call().await
+                .map_err(|e| format!("Failed to get all allowed agents: {}", e))
+        }).await
+    }
+
+    /// Like `get_all_registering_agents`, but returns the addresses.
+    #[tracing::instrument(name = "AgentManager.registering_agents", skip_all, fields(contract = "AgentManager", method = "registering_agents"))]
+    pub async fn registering_agents(&self) -> ApiResult<Vec<Address>> {
+        self.retry_with_exponential_backoff(|| async {
+            self.contract.get_all_registering_agents().call().await
+                .map_err(|e| format!("Failed to get all registering agents: {}", e))
+        }).await
+    }
+
+    #[tracing::instrument(name = "AgentManager.get_allowed_agents_count", skip_all, fields(contract = "AgentManager", method = "get_allowed_agents_count"))]
     pub async fn get_allowed_agents_count(&self) -> ApiResult<u64> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.get_allowed_agents_count().call().await
@@ -190,6 +290,7 @@ impl AgentManagerNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.get_allowed_agents_in_range", skip_all, fields(contract = "AgentManager", method = "get_allowed_agents_in_range"))]
     pub async fn get_allowed_agents_in_range(&self, allowed_agent_idx_start: u64, allowed_agent_idx_end: u64) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.get_allowed_agents_in_range(allowed_agent_idx_start, allowed_agent_idx_end).call().await
@@ -198,6 +299,7 @@ impl AgentManagerNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.get_registering_agents_count", skip_all, fields(contract = "AgentManager", method = "get_registering_agents_count"))]
     pub async fn get_registering_agents_count(&self) -> ApiResult<u64> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.get_registering_agents_count().call().await
@@ -205,6 +307,7 @@ impl AgentManagerNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.get_registering_agents_in_range", skip_all, fields(contract = "AgentManager", method = "get_registering_agents_in_range"))]
     pub async fn get_registering_agents_in_range(&self, registering_agent_idx_start: u64, registering_agent_idx_end: u64) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.get_registering_agents_in_range(registering_agent_idx_start, registering_agent_idx_end).call().await
@@ -213,6 +316,7 @@ impl AgentManagerNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.is_valid_message_id", skip_all, fields(contract = "AgentManager", method = "is_valid_message_id"))]
     pub async fn is_valid_message_id(&self, message_id: &str) -> ApiResult<bool> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.is_valid_message_id(message_id.to_string()).call().await
@@ -220,6 +324,7 @@ impl AgentManagerNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.is_valid_source_agent_id", skip_all, fields(contract = "AgentManager", method = "is_valid_source_agent_id"))]
     pub async fn is_valid_source_agent_id(&self, source_agent_id: &str) -> ApiResult<bool> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.is_valid_source_agent_id(source_agent_id.to_string()).call().await
@@ -227,6 +332,7 @@ impl AgentManagerNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.signer_threshold", skip_all, fields(contract = "AgentManager", method = "signer_threshold", agent = %agent_address, settings_digest = %setting_digest))]
     pub async fn signer_threshold(&self, agent_address: &str, setting_digest: &str) -> ApiResult<u8> {
         let agent = agent_address.parse::<Address>().map_err(|_| {
             "Invalid agent address format".to_string()
@@ -237,11 +343,12 @@ impl AgentManagerNode {
         })?;
 
         self.retry_with_exponential_backoff(|| async {
-            self.contract.signer_threshold(agent, setting_digest.into()).call().await
-                .map_err(|_| "Failed to get signer threshold".to_string())
+            critical_call(&self.contract.client(), self.contract.signer_threshold(agent, setting_digest.into())).await
+                .map_err(|e| format!("Failed to get signer threshold: {}", e))
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.validate_data_conversion", skip_all, fields(contract = "AgentManager", method = "validate_data_conversion", agent = %agent_address))]
     pub async fn validate_data_conversion(&self, agent_address: &str, data: &str) -> ApiResult<String> {
         let agent = agent_address.parse::<Address>().map_err(|_| {
             "Invalid agent address format".to_string()
@@ -258,6 +365,7 @@ impl AgentManagerNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentManager.accept_agent", skip_all, fields(contract = "AgentManager", method = "accept_agent", agent = %agent_address, tx_hash = tracing::field::Empty))]
     pub async fn accept_agent(&self, agent_address: &str) -> ApiResult<String> {
         let agent_address = agent_address.parse::<Address>().map_err(|e| {
             format!("Invalid address format: {}", e)
@@ -268,6 +376,7 @@ impl AgentManagerNode {
         let gas_estimate = contract_call.estimate_gas().await.map_err(|e| {
             format!("Failed to estimate gas: {:?}", e)
         })?;
+        tracing::debug!(gas = %gas_estimate, "Estimated gas");
 
         let contract_call_with_gas = contract_call.gas(gas_estimate);
 
@@ -276,11 +385,16 @@ impl AgentManagerNode {
             .await
             .map_err(|e| format!("Failed to send transaction: {:?}", e))?;
 
+        tracing::Span::current().record("tx_hash", format!("{:?}", pending_tx.tx_hash()));
+        tracing::info!("Sent transaction");
+
         let receipt = pending_tx.await.map_err(|e| {
             format!("Transaction failed: {:?}", e)
         })?;
 
         if let Some(receipt) = receipt {
+            tracing::info!(block = ?receipt.block_number, gas_used = ?receipt.gas_used, status = ?receipt.status, "Transaction mined");
+            metrics::observe_receipt("AgentManager", "accept_agent", &receipt);
             Ok(format!("{:?}", receipt))
         } else {
             Err("Transaction did not return a receipt".to_string())
@@ -291,12 +405,18 @@ impl AgentManagerNode {
         let agent_address = agent_address.parse::<Address>().map_err(|e| {
             format!("Invalid address format: {}", e)
         })?;
+        self.send_accept_agent_setting_proposal(agent_address).await.map(|receipt| format!("{:?}", receipt))
+    }
 
+    /// `accept_agent_setting_proposal` returning the receipt itself.
+    #[tracing::instrument(name = "AgentManager.accept_agent_setting_proposal", skip_all, fields(contract = "AgentManager", method = "accept_agent_setting_proposal", agent = ?agent_address, tx_hash = tracing::field::Empty))]
+    pub(crate) async fn send_accept_agent_setting_proposal(&self, agent_address: Address) -> ApiResult<TransactionReceipt> {
         let contract_call = self.contract.accept_agent_setting_proposal(agent_address);
 
         let gas_estimate = contract_call.estimate_gas().await.map_err(|e| {
             format!("Failed to estimate gas: {:?}", e)
         })?;
+        tracing::debug!(gas = %gas_estimate, "Estimated gas");
 
         let contract_call_with_gas = contract_call.gas(gas_estimate);
 
@@ -305,23 +425,30 @@ impl AgentManagerNode {
             .await
             .map_err(|e| format!("Failed to send transaction: {:?}", e))?;
 
+        tracing::Span::current().record("tx_hash", format!("{:?}", pending_tx.tx_hash()));
+        tracing::info!("Sent transaction");
+
         let receipt = pending_tx.await.map_err(|e| {
             format!("Transaction failed: {:?}", e)
         })?;
 
         if let Some(receipt) = receipt {
-            Ok(format!("{:?}", receipt))
+            tracing::info!(block = ?receipt.block_number, gas_used = ?receipt.gas_used, status = ?receipt.status, "Transaction mined");
+            metrics::observe_receipt("AgentManager", "accept_agent_setting_proposal", &receipt);
+            Ok(receipt)
         } else {
             Err("Transaction did not return a receipt".to_string())
         }
     }
 
+    #[tracing::instrument(name = "AgentManager.accept_ownership", skip_all, fields(contract = "AgentManager", method = "accept_ownership", tx_hash = tracing::field::Empty))]
     pub async fn accept_ownership(&self) -> ApiResult<String> {
         let contract_call = self.contract.accept_ownership();
 
         let gas_estimate = contract_call.estimate_gas().await.map_err(|e| {
             format!("Failed to estimate gas: {:?}", e)
         })?;
+        tracing::debug!(gas = %gas_estimate, "Estimated gas");
 
         let contract_call_with_gas = contract_call.gas(gas_estimate);
 
@@ -330,17 +457,24 @@ impl AgentManagerNode {
             .await
             .map_err(|e| format!("Failed to send transaction: {:?}", e))?;
 
+        tracing::Span::current().record("tx_hash", format!("{:?}", pending_tx.tx_hash()));
+        tracing::info!("Sent transaction");
+
         let receipt = pending_tx.await.map_err(|e| {
             format!("Transaction failed: {:?}", e)
         })?;
 
         if let Some(receipt) = receipt {
+            tracing::info!(block = ?receipt.block_number, gas_used = ?receipt.gas_used, status = ?receipt.status, "Transaction mined");
+            metrics::observe_receipt("AgentManager", "accept_ownership", &receipt);
             Ok(format!("{:?}", receipt))
         } else {
             Err("Transaction did not return a receipt".to_string())
         }
     }
 
+    #[tracing::instrument(name = "AgentManager.change_agent_setting_proposal", skip_all, fields(contract = "AgentManager", method = "change_agent_setting_proposal", agent = %agent_address, tx_hash = tracing::field::Empty))]
+    #[allow(clippy::too_many_arguments)]
     pub async fn change_agent_setting_proposal(
         &self,
         agent_address: &str,
@@ -395,6 +529,7 @@ impl AgentManagerNode {
         let gas_estimate = contract_call.estimate_gas().await.map_err(|e| {
             format!("Failed to estimate gas: {:?}", e)
         })?;
+        tracing::debug!(gas = %gas_estimate, "Estimated gas");
 
         let contract_call_with_gas = contract_call.gas(gas_estimate);
 
@@ -403,17 +538,24 @@ impl AgentManagerNode {
             .await
             .map_err(|e| format!("Failed to send transaction: {:?}", e))?;
 
+        tracing::Span::current().record("tx_hash", format!("{:?}", pending_tx.tx_hash()));
+        tracing::info!("Sent transaction");
+
         let receipt = pending_tx.await.map_err(|e| {
             format!("Transaction failed: {:?}", e)
         })?;
 
         if let Some(receipt) = receipt {
+            tracing::info!(block = ?receipt.block_number, gas_used = ?receipt.gas_used, status = ?receipt.status, "Transaction mined");
+            metrics::observe_receipt("AgentManager", "change_agent_setting_proposal", &receipt);
             Ok(format!("{:?}", receipt))
         } else {
             Err("Transaction did not return a receipt".to_string())
         }
     }
 
+    #[tracing::instrument(name = "AgentManager.register_agent", skip_all, fields(contract = "AgentManager", method = "register_agent", agent = %agent_address, tx_hash = tracing::field::Empty))]
+    #[allow(clippy::too_many_arguments)]
     pub async fn register_agent(
         &self,
         agent_address: &str,
@@ -468,6 +610,7 @@ impl AgentManagerNode {
         let gas_estimate = contract_call.estimate_gas().await.map_err(|e| {
             format!("Failed to estimate gas: {:?}", e)
         })?;
+        tracing::debug!(gas = %gas_estimate, "Estimated gas");
 
         let contract_call_with_gas = contract_call.gas(gas_estimate);
 
@@ -476,17 +619,23 @@ impl AgentManagerNode {
             .await
             .map_err(|e| format!("Failed to send transaction: {:?}", e))?;
 
+        tracing::Span::current().record("tx_hash", format!("{:?}", pending_tx.tx_hash()));
+        tracing::info!("Sent transaction");
+
         let receipt = pending_tx.await.map_err(|e| {
             format!("Transaction failed: {:?}", e)
         })?;
 
         if let Some(receipt) = receipt {
+            tracing::info!(block = ?receipt.block_number, gas_used = ?receipt.gas_used, status = ?receipt.status, "Transaction mined");
+            metrics::observe_receipt("AgentManager", "register_agent", &receipt);
             Ok(format!("{:?}", receipt))
         } else {
             Err("Transaction did not return a receipt".to_string())
         }
     }
 
+    #[tracing::instrument(name = "AgentManager.remove_agent", skip_all, fields(contract = "AgentManager", method = "remove_agent", agent = %agent_address, tx_hash = tracing::field::Empty))]
     pub async fn remove_agent(&self, agent_address: &str) -> ApiResult<String> {
         let agent_address = agent_address.parse::<Address>().map_err(|e| {
             format!("Invalid address format for agent: {}", e)
@@ -497,6 +646,7 @@ impl AgentManagerNode {
         let gas_estimate = contract_call.estimate_gas().await.map_err(|e| {
             format!("Failed to estimate gas: {:?}", e)
         })?;
+        tracing::debug!(gas = %gas_estimate, "Estimated gas");
 
         let contract_call_with_gas = contract_call.gas(gas_estimate);
 
@@ -505,17 +655,23 @@ impl AgentManagerNode {
             .await
             .map_err(|e| format!("Failed to send transaction: {:?}", e))?;
 
+        tracing::Span::current().record("tx_hash", format!("{:?}", pending_tx.tx_hash()));
+        tracing::info!("Sent transaction");
+
         let receipt = pending_tx.await.map_err(|e| {
             format!("Transaction failed: {:?}", e)
         })?;
 
         if let Some(receipt) = receipt {
+            tracing::info!(block = ?receipt.block_number, gas_used = ?receipt.gas_used, status = ?receipt.status, "Transaction mined");
+            metrics::observe_receipt("AgentManager", "remove_agent", &receipt);
             Ok(format!("{:?}", receipt))
         } else {
             Err("Transaction did not return a receipt".to_string())
         }
     }
 
+    #[tracing::instrument(name = "AgentManager.set_agent_proxy", skip_all, fields(contract = "AgentManager", method = "set_agent_proxy", tx_hash = tracing::field::Empty))]
     pub async fn set_agent_proxy(&self, proxy_address: &str) -> ApiResult<String> {
         let proxy_address = proxy_address.parse::<Address>().map_err(|e| {
             format!("Invalid address format for proxy: {}", e)
@@ -526,6 +682,7 @@ impl AgentManagerNode {
         let gas_estimate = contract_call.estimate_gas().await.map_err(|e| {
             format!("Failed to estimate gas: {:?}", e)
         })?;
+        tracing::debug!(gas = %gas_estimate, "Estimated gas");
 
         let contract_call_with_gas = contract_call.gas(gas_estimate);
 
@@ -534,17 +691,23 @@ impl AgentManagerNode {
             .await
             .map_err(|e| format!("Failed to send transaction: {:?}", e))?;
 
+        tracing::Span::current().record("tx_hash", format!("{:?}", pending_tx.tx_hash()));
+        tracing::info!("Sent transaction");
+
         let receipt = pending_tx.await.map_err(|e| {
             format!("Transaction failed: {:?}", e)
         })?;
 
         if let Some(receipt) = receipt {
+            tracing::info!(block = ?receipt.block_number, gas_used = ?receipt.gas_used, status = ?receipt.status, "Transaction mined");
+            metrics::observe_receipt("AgentManager", "set_agent_proxy", &receipt);
             Ok(format!("{:?}", receipt))
         } else {
             Err("Transaction did not return a receipt".to_string())
         }
     }
 
+    #[tracing::instrument(name = "AgentManager.transfer_ownership", skip_all, fields(contract = "AgentManager", method = "transfer_ownership", tx_hash = tracing::field::Empty))]
     pub async fn transfer_ownership(&self, new_owner_address: &str) -> ApiResult<String> {
         let new_owner_address = new_owner_address.parse::<Address>().map_err(|e| {
             format!("Invalid address format for new owner: {}", e)
@@ -555,6 +718,7 @@ impl AgentManagerNode {
         let gas_estimate = contract_call.estimate_gas().await.map_err(|e| {
             format!("Failed to estimate gas: {:?}", e)
         })?;
+        tracing::debug!(gas = %gas_estimate, "Estimated gas");
 
         let contract_call_with_gas = contract_call.gas(gas_estimate);
 
@@ -563,11 +727,16 @@ impl AgentManagerNode {
             .await
             .map_err(|e| format!("Failed to send transaction: {:?}", e))?;
 
+        tracing::Span::current().record("tx_hash", format!("{:?}", pending_tx.tx_hash()));
+        tracing::info!("Sent transaction");
+
         let receipt = pending_tx.await.map_err(|e| {
             format!("Transaction failed: {:?}", e)
         })?;
 
         if let Some(receipt) = receipt {
+            tracing::info!(block = ?receipt.block_number, gas_used = ?receipt.gas_used, status = ?receipt.status, "Transaction mined");
+            metrics::observe_receipt("AgentManager", "transfer_ownership", &receipt);
             Ok(format!("{:?}", receipt))
         } else {
             Err("Transaction did not return a receipt".to_string())
@@ -575,6 +744,36 @@ impl AgentManagerNode {
     }
 }
 
+/// JSON for `AgentSettings`, which abigen does not make serializable. The uint256
+/// header fields are decimal strings.
+pub(crate) fn settings_json(settings: &AgentSettings) -> serde_json::Value {
+    let header = &settings.agent_header;
+    serde_json::json!({
+        "signers": settings.signers,
+        "threshold": settings.threshold,
+        "converter_address": settings.converter_address,
+        "agent_header": {
+            "version": header.version,
+            "message_id": header.message_id,
+            "source_agent_id": header.source_agent_id,
+            "source_agent_name": header.source_agent_name,
+            "target_agent_id": header.target_agent_id,
+            "timestamp": header.timestamp.to_string(),
+            "message_type": header.message_type,
+            "priority": header.priority,
+            "ttl": header.ttl.to_string(),
+        },
+    })
+}
+
+/// Prefix of version 1 settings digests, in their first two bytes.
+pub const SETTINGS_DIGEST_PREFIX_V1: u16 = 0x0100;
+
+/// The version prefix of a settings digest, e.g. `SETTINGS_DIGEST_PREFIX_V1`.
+pub fn settings_digest_version(digest: &H256) -> u16 {
+    u16::from_be_bytes([digest[0], digest[1]])
+}
+
 pub fn extract_setting_digests(configs: &str) -> Vec<H256> {
     let mut setting_digests = Vec::new();
     let re = Regex::new(r"config_digest: \[([^\]]+)\]").unwrap();
@@ -618,4 +817,119 @@ mod tests {
         assert_eq!(digests[0], expected_digest);
         assert_eq!(digests[1], expected_digest);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_settings_digest_version() {
+        // the Sepolia digest from test_extract_setting_digests
+        let digest: H256 = "0x0100e5428f61995ca2f61d96b24d90b48de58b818cc91dbb88c1bf74e83df3cb".parse().unwrap();
+        assert_eq!(settings_digest_version(&digest), SETTINGS_DIGEST_PREFIX_V1);
+        assert_eq!(settings_digest_version(&H256::zero()), 0);
+    }
+
+    #[tokio::test]
+    async fn test_spans_carry_call_fields() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Mutex;
+
+        use serde_json::{json, Value};
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        use crate::utils::mock_server::{MockRequest, MockResponse, MockServer};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let server = MockServer::start(Arc::new(move |request: MockRequest| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let response = match body["method"].as_str() {
+                Some("eth_chainId") => json!({ "jsonrpc": "2.0", "id": body["id"], "result": "0x1" }),
+                // the first call fails, so the second attempt shows up in the trace
+                Some("eth_call") if counter.fetch_add(1, Ordering::SeqCst) == 0 => {
+                    json!({ "jsonrpc": "2.0", "id": body["id"], "error": { "code": -32000, "message": "header not found" } })
+                }
+                _ => json!({ "jsonrpc": "2.0", "id": body["id"], "result": format!("0x{:064x}", 1) }),
+            };
+            MockResponse::new(200, "application/json", response.to_string())
+        }))
+        .await
+        .unwrap();
+
+        #[derive(Clone, Default)]
+        struct Captured(Arc<Mutex<Vec<u8>>>);
+        impl std::io::Write for Captured {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_max_level(tracing::Level::DEBUG)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let key = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        let transport = AttpsTransport::from_urls(&server.url()).unwrap();
+        let node = AgentManagerNode::try_new_with_transport(
+            transport,
+            "0x0000000000000000000000000000000000000001",
+            SecretKey::from(key).to_wallet().unwrap(),
+        ).await.unwrap();
+        let agent = "0x9538e13c0e111c5b0525f1592079aa1586b4e9cc";
+        assert!(node.allowed_agent(agent).await.unwrap());
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("AgentManager.allowed_agent{contract=\"AgentManager\" method=\"allowed_agent\" agent=0x9538e13c0e111c5b0525f1592079aa1586b4e9cc}"), "{}", output);
+        assert!(output.contains("attempt{attempt=1}") && output.contains("attempt{attempt=2}"), "{}", output);
+        assert!(output.contains("Retrying"), "{}", output);
+        assert!(!output.contains(key));
+    }
+
+    #[tokio::test]
+    async fn test_find_settings_digest() {
+        use ethers::abi::Tokenizable;
+        use serde_json::{json, Value};
+
+        use crate::utils::mock_server::{MockRequest, MockResponse, MockServer};
+
+        let settings = |threshold: u8| AgentSettings { signers: vec![Address::repeat_byte(0x11)], threshold, ..Default::default() };
+        let config = |digest: u8, block: u32, settings: AgentSettings| AgentConfig {
+            config_digest: H256::repeat_byte(digest).into(),
+            config_block_number: block,
+            is_active: true,
+            settings,
+        };
+        // the same settings were registered twice; the later digest wins
+        let configs = vec![config(1, 10, settings(1)), config(2, 20, settings(2)), config(3, 30, settings(1))];
+        let encoded = format!("0x{}", hex::encode(ethers::abi::encode(&[configs.into_token()])));
+        let server = MockServer::start(Arc::new(move |request: MockRequest| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let result = match body["method"].as_str() {
+                Some("eth_chainId") => json!("0x1"),
+                Some("eth_call") => json!(encoded),
+                _ => Value::Null,
+            };
+            let response = json!({ "jsonrpc": "2.0", "id": body["id"], "result": result });
+            MockResponse::new(200, "application/json", response.to_string())
+        }))
+        .await
+        .unwrap();
+
+        let key = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        let node = AgentManagerNode::try_new_with_transport(
+            AttpsTransport::from_urls(&server.url()).unwrap(),
+            "0x0000000000000000000000000000000000000001",
+            SecretKey::from(key).to_wallet().unwrap(),
+        ).await.unwrap();
+        let agent = "0x9538e13c0e111c5b0525f1592079aa1586b4e9cc";
+        assert_eq!(node.find_settings_digest(agent, &settings(1)).await.unwrap(), Some(H256::repeat_byte(3)));
+        assert_eq!(node.find_settings_digest(agent, &settings(2)).await.unwrap(), Some(H256::repeat_byte(2)));
+        assert_eq!(node.find_settings_digest(agent, &settings(3)).await.unwrap(), None);
+    }
+}
@@ -1,35 +1,121 @@
 use std::sync::Arc;
 use tokio::time::{sleep, Duration};
 
-use ethers::core::k256::ecdsa::SigningKey;
 use ethers::prelude::*;
-use ethers::signers::Wallet;
+use tracing::Instrument;
 use ethers::types::{Address, H256};
 use hex;
 
-use crate::core::ApiResult;
+use crate::core::events::EventStream;
+use crate::core::metrics;
+use crate::core::transport::AttpsTransport;
+use crate::core::{ApiResult, SignerClient};
+use crate::utils::secret::SecretKey;
+use crate::utils::signer::AttpsSigner;
+use crate::core::config::AttpsConfig;
+use crate::utils::common::decode_revert_data;
 
 abigen!(
     AgentProxyContract,
     "./src/abi/AgentProxy.json"
 );
 
+/// Why `send_verify` failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum VerifyError {
+    /// The verification reverted during gas estimation; `reason` is the decoded revert.
+    Reverted { reason: String, message: String },
+    Failed(String),
+}
+
+impl VerifyError {
+    pub(crate) fn into_message(self) -> String {
+        match self {
+            VerifyError::Reverted { message, .. } | VerifyError::Failed(message) => message,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyCallResult {
+    pub passed: bool,
+    pub revert_reason: Option<String>,
+}
+
 pub struct AgentProxyNode {
-    contract: AgentProxyContract<SignerMiddleware<Provider<Http>, Wallet<SigningKey>>>,
+    contract: AgentProxyContract<SignerClient>,
 }
 
 impl AgentProxyNode {
-    pub async fn new(provider_url: &str, contract_addr: &str, private_key: &str) -> Self {
-        let provider = Provider::<Http>::try_from(provider_url).expect("Invalid provider URL");
-        let chain_id = provider.get_chainid().await.expect("Failed to get chain ID").as_u64();
-        let wallet: Wallet<SigningKey> = private_key.parse().expect("Invalid private key");
-        let wallet = wallet.with_chain_id(chain_id);
-        let client = SignerMiddleware::new(provider.clone(), wallet);
+    pub async fn new(provider_url: &str, contract_addr: &str, private_key: &SecretKey) -> Self {
+        Self::try_new(provider_url, contract_addr, private_key)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub async fn try_new(provider_url: &str, contract_addr: &str, private_key: &SecretKey) -> ApiResult<Self> {
+        Self::try_new_with_signer(provider_url, contract_addr, private_key.to_wallet()?).await
+    }
+
+    pub async fn try_new_with_signer(
+        provider_url: &str,
+        contract_addr: &str,
+        signer: impl Into<AttpsSigner>,
+    ) -> ApiResult<Self> {
+        let transport = AttpsTransport::from_urls(provider_url).map_err(|_| {
+            "Invalid provider URL".to_string()
+        })?;
+        Self::try_new_with_transport(transport, contract_addr, signer).await
+    }
+
+    pub async fn try_new_with_transport(
+        transport: AttpsTransport,
+        contract_addr: &str,
+        signer: impl Into<AttpsSigner>,
+    ) -> ApiResult<Self> {
+        let provider = Provider::new(transport);
+        let chain_id = provider.get_chainid().await.map_err(|e| {
+            format!("Failed to get chain ID: {:?}", e)
+        })?.as_u64();
+        let signer = signer.into().with_chain_id(chain_id);
+        let client = SignerMiddleware::new(provider.clone(), signer);
         let provider = Arc::new(client);
 
-        let address = contract_addr.parse::<Address>().unwrap();
+        let address = contract_addr.parse::<Address>().map_err(|_| {
+            "Invalid contract address".to_string()
+        })?;
         let contract = AgentProxyContract::new(address, provider.clone());
-        Self { contract }
+        Ok(Self { contract })
+    }
+
+    /// Streams this contract's events over the transport's WebSocket endpoints,
+    /// starting at `from_block` when given and otherwise at the current head.
+    pub fn events(&self, from_block: Option<u64>) -> ApiResult<EventStream<AgentProxyContractEvents>> {
+        EventStream::new(&self.contract.client(), self.contract.address(), from_block)
+    }
+
+    pub async fn from_config(config: &AttpsConfig) -> ApiResult<Self> {
+        let node = Self::try_new_with_transport(
+            config.transport()?,
+            &format!("{:?}", config.agent_proxy_contract),
+            config.load_signer().await?,
+        ).await?;
+
+        if let Some(expected) = config.chain_id {
+            let chain_id = node.contract.client().signer().chain_id();
+            if chain_id != expected {
+                return Err(format!(
+                    "Provider chain ID {} does not match configured chain ID {}",
+                    chain_id, expected
+                ));
+            }
+        }
+
+        Ok(node)
+    }
+
+    pub(crate) fn contract(&self) -> &AgentProxyContract<SignerClient> {
+        &self.contract
     }
 
     async fn retry_with_exponential_backoff<F, Fut, T>(&self, mut f: F) -> ApiResult<T>
@@ -42,9 +128,11 @@ impl AgentProxyNode {
         let mut delay = Duration::from_millis(100);
 
         while attempts < max_attempts {
-            match f().await {
+            match f().instrument(tracing::debug_span!("attempt", attempt = attempts + 1)).await {
                 Ok(result) => return Ok(result),
-                Err(_) if attempts < max_attempts - 1 => {
+                Err(e) if attempts < max_attempts - 1 => {
+                    tracing::debug!(error = %e, "Retrying");
+                    metrics::count_retry("AgentProxy");
                     sleep(delay).await;
                     delay *= 2;
                 }
@@ -56,6 +144,7 @@ impl AgentProxyNode {
         Err("Max retry attempts reached".to_string())
     }
 
+    #[tracing::instrument(name = "AgentProxy.get_agent_factory", skip_all, fields(contract = "AgentProxy", method = "get_agent_factory"))]
     pub async fn get_agent_factory(&self) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.agent_factory().call().await
@@ -64,6 +153,7 @@ impl AgentProxyNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentProxy.get_agent_manager", skip_all, fields(contract = "AgentProxy", method = "get_agent_manager"))]
     pub async fn get_agent_manager(&self) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.agent_manager().call().await
@@ -72,6 +162,7 @@ impl AgentProxyNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentProxy.get_owner", skip_all, fields(contract = "AgentProxy", method = "get_owner"))]
     pub async fn get_owner(&self) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.owner().call().await
@@ -80,6 +171,7 @@ impl AgentProxyNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentProxy.get_type_and_version", skip_all, fields(contract = "AgentProxy", method = "get_type_and_version"))]
     pub async fn get_type_and_version(&self) -> ApiResult<String> {
         self.retry_with_exponential_backoff(|| async {
             self.contract.type_and_version().call().await
@@ -87,12 +179,14 @@ impl AgentProxyNode {
         }).await
     }
 
+    #[tracing::instrument(name = "AgentProxy.accept_ownership", skip_all, fields(contract = "AgentProxy", method = "accept_ownership", tx_hash = tracing::field::Empty))]
     pub async fn accept_ownership(&self) -> ApiResult<String> {
         let contract_call = self.contract.accept_ownership();
 
         let gas_estimate = contract_call.estimate_gas().await.map_err(|e| {
             format!("Failed to estimate gas: {:?}", e)
         })?;
+        tracing::debug!(gas = %gas_estimate, "Estimated gas");
 
         let contract_call_with_gas = contract_call.gas(gas_estimate);
 
@@ -101,11 +195,16 @@ impl AgentProxyNode {
             .await
             .map_err(|e| format!("Failed to send accept_ownership transaction: {:?}", e))?;
 
+        tracing::Span::current().record("tx_hash", format!("{:?}", pending_tx.tx_hash()));
+        tracing::info!("Sent transaction");
+
         let receipt = pending_tx.await.map_err(|e| {
             format!("Transaction failed: {:?}", e)
         })?;
 
         if let Some(receipt) = receipt {
+            tracing::info!(block = ?receipt.block_number, gas_used = ?receipt.gas_used, status = ?receipt.status, "Transaction mined");
+            metrics::observe_receipt("AgentProxy", "accept_ownership", &receipt);
             Ok(format!("{:?}", receipt))
         } else {
             Err("Transaction did not return a receipt".to_string())
@@ -113,6 +212,7 @@ impl AgentProxyNode {
     }
     
 
+    #[tracing::instrument(name = "AgentProxy.set_agent_factory", skip_all, fields(contract = "AgentProxy", method = "set_agent_factory", tx_hash = tracing::field::Empty))]
     pub async fn set_agent_factory(&self, factory_address: &str) -> ApiResult<String> {
         let factory_address = factory_address.parse::<Address>().map_err(|e| {
             format!("Invalid address format: {}", e)
@@ -123,7 +223,7 @@ impl AgentProxyNode {
         let gas_estimate = contract_call.estimate_gas().await.map_err(|e| {
             format!("Failed to estimate gas: {:?}", e)
         })?;
-        println!("Gas estimate: {}", gas_estimate);
+        tracing::debug!(gas = %gas_estimate, "Estimated gas");
     
         let contract_call_with_gas = contract_call.gas(gas_estimate);
     
@@ -132,17 +232,23 @@ impl AgentProxyNode {
             .await
             .map_err(|e| format!("Failed to send transaction: {:?}", e))?;
     
+        tracing::Span::current().record("tx_hash", format!("{:?}", pending_tx.tx_hash()));
+        tracing::info!("Sent transaction");
+
         let receipt = pending_tx.await.map_err(|e| {
             format!("Transaction failed: {:?}", e)
         })?;
     
         if let Some(receipt) = receipt {
+            tracing::info!(block = ?receipt.block_number, gas_used = ?receipt.gas_used, status = ?receipt.status, "Transaction mined");
+            metrics::observe_receipt("AgentProxy", "set_agent_factory", &receipt);
             Ok(format!("{:?}", receipt))
         } else {
             Err("Transaction did not return a receipt".to_string())
         }
     }
     
+    #[tracing::instrument(name = "AgentProxy.set_agent_manager", skip_all, fields(contract = "AgentProxy", method = "set_agent_manager", tx_hash = tracing::field::Empty))]
     pub async fn set_agent_manager(&self, manager_address: &str) -> ApiResult<String> {
         let manager_address = manager_address.parse::<Address>().map_err(|e| {
             format!("Invalid address format: {}", e)
@@ -153,6 +259,7 @@ impl AgentProxyNode {
         let gas_estimate = contract_call.estimate_gas().await.map_err(|e| {
             format!("Failed to estimate gas: {:?}", e)
         })?;
+        tracing::debug!(gas = %gas_estimate, "Estimated gas");
 
         let contract_call_with_gas = contract_call.gas(gas_estimate);
 
@@ -161,17 +268,23 @@ impl AgentProxyNode {
             .await
             .map_err(|e| format!("Failed to send transaction: {:?}", e))?;
 
+        tracing::Span::current().record("tx_hash", format!("{:?}", pending_tx.tx_hash()));
+        tracing::info!("Sent transaction");
+
         let receipt = pending_tx.await.map_err(|e| {
             format!("Transaction failed: {:?}", e)
         })?;
 
         if let Some(receipt) = receipt {
+            tracing::info!(block = ?receipt.block_number, gas_used = ?receipt.gas_used, status = ?receipt.status, "Transaction mined");
+            metrics::observe_receipt("AgentProxy", "set_agent_manager", &receipt);
             Ok(format!("{:?}", receipt))
         } else {
             Err("Transaction did not return a receipt".to_string())
         }
     }
 
+    #[tracing::instrument(name = "AgentProxy.transfer_ownership", skip_all, fields(contract = "AgentProxy", method = "transfer_ownership", tx_hash = tracing::field::Empty))]
     pub async fn transfer_ownership(&self, new_owner_address: &str) -> ApiResult<String> {
         let new_owner_address = new_owner_address.parse::<Address>().map_err(|e| {
             format!("Invalid address format: {}", e)
@@ -182,6 +295,7 @@ impl AgentProxyNode {
         let gas_estimate = contract_call.estimate_gas().await.map_err(|e| {
             format!("Failed to estimate gas: {:?}", e)
         })?;
+        tracing::debug!(gas = %gas_estimate, "Estimated gas");
 
         let contract_call_with_gas = contract_call.gas(gas_estimate);
 
@@ -190,17 +304,24 @@ impl AgentProxyNode {
             .await
             .map_err(|e| format!("Failed to send transaction: {:?}", e))?;
 
+        tracing::Span::current().record("tx_hash", format!("{:?}", pending_tx.tx_hash()));
+        tracing::info!("Sent transaction");
+
         let receipt = pending_tx.await.map_err(|e| {
             format!("Transaction failed: {:?}", e)
         })?;
 
         if let Some(receipt) = receipt {
+            tracing::info!(block = ?receipt.block_number, gas_used = ?receipt.gas_used, status = ?receipt.status, "Transaction mined");
+            metrics::observe_receipt("AgentProxy", "transfer_ownership", &receipt);
             Ok(format!("{:?}", receipt))
         } else {
             Err("Transaction did not return a receipt".to_string())
         }
     }
 
+    #[tracing::instrument(name = "AgentProxy.create_and_register_agent", skip_all, fields(contract = "AgentProxy", method = "create_and_register_agent", tx_hash = tracing::field::Empty))]
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_and_register_agent(
         &self,
         signers: Vec<&str>,
@@ -250,6 +371,7 @@ impl AgentProxyNode {
         let gas_estimate = contract_call.estimate_gas().await.map_err(|e| {
             format!("Failed to estimate gas: {:?}", e)
         })?;
+        tracing::debug!(gas = %gas_estimate, "Estimated gas");
 
         let contract_call_with_gas = contract_call.gas(gas_estimate);
 
@@ -258,18 +380,25 @@ impl AgentProxyNode {
             .await
             .map_err(|e| format!("Failed to send transaction: {:?}", e))?;
 
+        tracing::Span::current().record("tx_hash", format!("{:?}", pending_tx.tx_hash()));
+        tracing::info!("Sent transaction");
+
         let receipt = pending_tx.await.map_err(|e| {
             format!("Transaction failed: {:?}", e)
         })?;
 
         if let Some(receipt) = receipt {
+            tracing::info!(block = ?receipt.block_number, gas_used = ?receipt.gas_used, status = ?receipt.status, "Transaction mined");
+            metrics::observe_receipt("AgentProxy", "create_and_register_agent", &receipt);
             Ok(format!("{:?}", receipt))
         } else {
             Err("Transaction did not return a receipt".to_string())
         }
     }
 
-    pub async fn verify(
+    #[tracing::instrument(name = "AgentProxy.verify_call", skip_all, fields(contract = "AgentProxy", method = "verify_call", agent = %agent, settings_digest = %settings_digest))]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn verify_call(
         &self,
         agent: &str,
         settings_digest: &str,
@@ -281,66 +410,85 @@ impl AgentProxyNode {
         content_type: &str,
         encoding: &str,
         compression: &str,
-    ) -> ApiResult<String> {
-        let agent = agent.parse::<Address>().map_err(|e| {
-            format!("Invalid agent address format: {}", e)
-        })?;
-        let settings_digest: [u8; 32] = settings_digest.parse::<H256>().map_err(|e| {
-            format!("Invalid settings digest format: {}", e)
-        })?.into();
-        let data = hex::decode(data).map_err(|e| {
-            format!("Invalid data format: {}", e)
-        })?;
-        let data_hash: [u8; 32] = data_hash.parse::<H256>().map_err(|e| {
-            format!("Invalid data hash format: {}", e)
-        })?.into();
-
-        let signature_proof = if signature_proof.starts_with("0x") {
-            &signature_proof[2..]
-        } else {
-            signature_proof
-        };
-        let signature_proof = hex::decode(signature_proof).map_err(|e| {
-            format!("Invalid signature proof format: {}", e)
-        })?;
-
-        let zk_proof = if zk_proof.starts_with("0x") {
-            &zk_proof[2..]
-        } else {
-            zk_proof
-        };
-        let zk_proof = hex::decode(zk_proof).map_err(|e| {
-            format!("Invalid zk proof format: {}", e)
-        })?;
-
-        let merkle_proof = if merkle_proof.starts_with("0x") {
-            &merkle_proof[2..]
-        } else {
-            merkle_proof
-        };
-        let merkle_proof = hex::decode(merkle_proof).map_err(|e| {
-            format!("Invalid merkle proof format: {}", e)
-        })?;
-
-        let proofs = Proofs {
-            signature_proof: signature_proof.into(),
-            zk_proof: zk_proof.into(),
-            merkle_proof: merkle_proof.into(),
-        };
+        block: Option<u64>,
+    ) -> ApiResult<VerifyCallResult> {
+        let (agent, settings_digest, message_payload) = parse_verify_args(
+            agent,
+            settings_digest,
+            data,
+            data_hash,
+            signature_proof,
+            zk_proof,
+            merkle_proof,
+            content_type,
+            encoding,
+            compression,
+        )?;
+
+        let result = self.retry_with_exponential_backoff(|| async {
+            let mut contract_call = self.contract.verify(
+                agent,
+                settings_digest,
+                message_payload.clone(),
+            );
+            if let Some(block) = block {
+                contract_call = contract_call.block(block);
+            }
 
-        let metadata = Metadata {
-            content_type: content_type.to_string(),
-            encoding: encoding.to_string(),
-            compression: compression.to_string(),
-        };
+            // a revert is a verification result, not a failed call, so it is not retried
+            match contract_call.call().await {
+                Ok(()) => Ok(VerifyCallResult { passed: true, revert_reason: None }),
+                Err(e) => match e.as_revert() {
+                    Some(revert_data) => Ok(VerifyCallResult {
+                        passed: false,
+                        revert_reason: Some(decode_revert_data(revert_data)),
+                    }),
+                    None => Err(format!("Failed to call verify: {:?}", e)),
+                },
+            }
+        }).await;
+        metrics::count_verify("call", match &result {
+            Ok(result) if result.passed => "passed",
+            Ok(_) => "reverted",
+            Err(_) => "error",
+        });
+        result
+    }
 
-        let message_payload = MessagePayload {
-            data: data.into(),
+    #[allow(clippy::too_many_arguments)]
+    pub async fn verify(
+        &self,
+        agent: &str,
+        settings_digest: &str,
+        data: &str,
+        data_hash: &str,
+        signature_proof: &str,
+        zk_proof: &str,
+        merkle_proof: &str,
+        content_type: &str,
+        encoding: &str,
+        compression: &str,
+    ) -> ApiResult<String> {
+        let args = parse_verify_args(
+            agent,
+            settings_digest,
+            data,
             data_hash,
-            proofs,
-            metadata,
-        };
+            signature_proof,
+            zk_proof,
+            merkle_proof,
+            content_type,
+            encoding,
+            compression,
+        )?;
+        let receipt = self.send_verify(args).await.map_err(VerifyError::into_message)?;
+        Ok(format!("{:?}", receipt))
+    }
 
+    /// `verify` with parsed arguments and a typed error.
+    #[tracing::instrument(name = "AgentProxy.verify", skip_all, fields(contract = "AgentProxy", method = "verify", agent = ?args.0, settings_digest = %H256::from(args.1), tx_hash = tracing::field::Empty))]
+    pub(crate) async fn send_verify(&self, args: (Address, [u8; 32], MessagePayload)) -> Result<TransactionReceipt, VerifyError> {
+        let (agent, settings_digest, message_payload) = args;
         let contract_call = self.contract.verify(
             agent,
             settings_digest,
@@ -348,24 +496,110 @@ impl AgentProxyNode {
         );
 
         let gas_estimate = contract_call.estimate_gas().await.map_err(|e| {
-            format!("Failed to estimate gas: {:?}", e)
+            // a failing verification usually shows up here, before anything is sent
+            let message = format!("Failed to estimate gas: {:?}", e);
+            match e.as_revert() {
+                Some(revert_data) => {
+                    metrics::count_verify("send", "reverted");
+                    VerifyError::Reverted { reason: decode_revert_data(revert_data), message }
+                }
+                None => {
+                    metrics::count_verify("send", "error");
+                    VerifyError::Failed(message)
+                }
+            }
         })?;
+        tracing::debug!(gas = %gas_estimate, "Estimated gas");
 
         let contract_call_with_gas = contract_call.gas(gas_estimate);
 
         let pending_tx = contract_call_with_gas
             .send()
             .await
-            .map_err(|e| format!("Failed to send transaction: {:?}", e))?;
+            .map_err(|e| {
+                metrics::count_verify("send", "error");
+                VerifyError::Failed(format!("Failed to send transaction: {:?}", e))
+            })?;
+
+        tracing::Span::current().record("tx_hash", format!("{:?}", pending_tx.tx_hash()));
+        tracing::info!("Sent transaction");
 
         let receipt = pending_tx.await.map_err(|e| {
-            format!("Transaction failed: {:?}", e)
+            metrics::count_verify("send", "error");
+            VerifyError::Failed(format!("Transaction failed: {:?}", e))
         })?;
 
         if let Some(receipt) = receipt {
-            Ok(format!("{:?}", receipt))
+            tracing::info!(block = ?receipt.block_number, gas_used = ?receipt.gas_used, status = ?receipt.status, "Transaction mined");
+            metrics::observe_receipt("AgentProxy", "verify", &receipt);
+            metrics::count_verify("send", if receipt.status == Some(1.into()) { "passed" } else { "reverted" });
+            Ok(receipt)
         } else {
-            Err("Transaction did not return a receipt".to_string())
+            metrics::count_verify("send", "error");
+            Err(VerifyError::Failed("Transaction did not return a receipt".to_string()))
         }
     }
 }
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn parse_verify_args(
+    agent: &str,
+    settings_digest: &str,
+    data: &str,
+    data_hash: &str,
+    signature_proof: &str,
+    zk_proof: &str,
+    merkle_proof: &str,
+    content_type: &str,
+    encoding: &str,
+    compression: &str,
+) -> ApiResult<(Address, [u8; 32], MessagePayload)> {
+    let agent = agent.parse::<Address>().map_err(|e| {
+        format!("Invalid agent address format: {}", e)
+    })?;
+    let settings_digest: [u8; 32] = settings_digest.parse::<H256>().map_err(|e| {
+        format!("Invalid settings digest format: {}", e)
+    })?.into();
+    let data = hex::decode(data).map_err(|e| {
+        format!("Invalid data format: {}", e)
+    })?;
+    let data_hash: [u8; 32] = data_hash.parse::<H256>().map_err(|e| {
+        format!("Invalid data hash format: {}", e)
+    })?.into();
+
+    let signature_proof = signature_proof.strip_prefix("0x").unwrap_or(signature_proof);
+    let signature_proof = hex::decode(signature_proof).map_err(|e| {
+        format!("Invalid signature proof format: {}", e)
+    })?;
+
+    let zk_proof = zk_proof.strip_prefix("0x").unwrap_or(zk_proof);
+    let zk_proof = hex::decode(zk_proof).map_err(|e| {
+        format!("Invalid zk proof format: {}", e)
+    })?;
+
+    let merkle_proof = merkle_proof.strip_prefix("0x").unwrap_or(merkle_proof);
+    let merkle_proof = hex::decode(merkle_proof).map_err(|e| {
+        format!("Invalid merkle proof format: {}", e)
+    })?;
+
+    let proofs = Proofs {
+        signature_proof: signature_proof.into(),
+        zk_proof: zk_proof.into(),
+        merkle_proof: merkle_proof.into(),
+    };
+
+    let metadata = Metadata {
+        content_type: content_type.to_string(),
+        encoding: encoding.to_string(),
+        compression: compression.to_string(),
+    };
+
+    let message_payload = MessagePayload {
+        data: data.into(),
+        data_hash,
+        proofs,
+        metadata,
+    };
+
+    Ok((agent, settings_digest, message_payload))
+}
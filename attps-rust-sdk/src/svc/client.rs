@@ -0,0 +1,324 @@
+use ethers::prelude::*;
+
+use crate::core::config::AttpsConfig;
+use crate::core::deployments::{Deployment, DeploymentRegistry};
+use crate::core::transport::AttpsTransport;
+use crate::core::versions::{AbiRegistry, ContractKind, ContractVersions, VersionPolicy};
+use crate::core::ApiResult;
+use crate::svc::agent_factory_node::AgentFactoryNode;
+use crate::svc::agent_manager_node::AgentManagerNode;
+use crate::svc::agent_proxy_node::AgentProxyNode;
+use crate::utils::common::parse_address;
+use crate::utils::signer::AttpsSigner;
+
+/// How `AttpsClient` resolves deployments and checks contract versions.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectOptions {
+    pub deployments: DeploymentRegistry,
+    pub abis: AbiRegistry,
+    pub version_policy: VersionPolicy,
+    /// Bisects `eth_getCode` for a missing deployment block at connect. Costs about 70
+    /// requests and needs a provider that serves historical state.
+    pub find_deployment_block: bool,
+}
+
+impl ConnectOptions {
+    pub fn builtin() -> Self {
+        Self { deployments: DeploymentRegistry::builtin(), ..Default::default() }
+    }
+}
+
+/// The proxy, manager and factory nodes of one deployment, sharing a signer.
+pub struct AttpsClient {
+    proxy: AgentProxyNode,
+    manager: AgentManagerNode,
+    factory: AgentFactoryNode,
+    deployment: Deployment,
+    versions: Option<ContractVersions>,
+}
+
+impl AttpsClient {
+    /// Connects to a built-in deployment by chain name (e.g. `"sepolia"`) or chain ID
+    /// through the built-in profile's provider.
+    pub async fn connect(chain: &str, signer: impl Into<AttpsSigner>) -> ApiResult<Self> {
+        let options = ConnectOptions::builtin();
+        let config = AttpsConfig::profile(&options.deployments.resolve(chain)?.name)?;
+        Self::connect_with_options(chain, &config.provider_url, signer, &options).await
+    }
+
+    pub async fn connect_with_provider(
+        registry: &DeploymentRegistry,
+        chain: &str,
+        provider_url: &str,
+        signer: impl Into<AttpsSigner>,
+    ) -> ApiResult<Self> {
+        let options = ConnectOptions { deployments: registry.clone(), ..Default::default() };
+        Self::connect_with_options(chain, provider_url, signer, &options).await
+    }
+
+    pub async fn connect_with_options(
+        chain: &str,
+        provider_url: &str,
+        signer: impl Into<AttpsSigner>,
+        options: &ConnectOptions,
+    ) -> ApiResult<Self> {
+        let deployment = options.deployments.resolve(chain)?.clone();
+        let transport = AttpsTransport::from_urls(provider_url)?;
+        Self::connect_deployment(deployment, transport, signer.into(), options).await
+    }
+
+    /// Uses the config's registry entry when there is one, otherwise the config's proxy
+    /// address alone.
+    pub async fn from_config(config: &AttpsConfig) -> ApiResult<Self> {
+        Self::from_config_with_options(config, &ConnectOptions::builtin()).await
+    }
+
+    pub async fn from_config_with_options(config: &AttpsConfig, options: &ConnectOptions) -> ApiResult<Self> {
+        let deployment = config.deployment.clone().unwrap_or_else(|| Deployment {
+            name: config.profile.clone().unwrap_or_else(|| "custom".to_string()),
+            chain_id: config.chain_id.unwrap_or_default(),
+            agent_proxy: config.agent_proxy_contract,
+            agent_manager: None,
+            agent_factory: None,
+            deployment_block: None,
+        });
+        Self::connect_deployment(deployment, config.transport()?, config.load_signer().await?, options).await
+    }
+
+    async fn connect_deployment(
+        mut deployment: Deployment,
+        transport: AttpsTransport,
+        signer: AttpsSigner,
+        options: &ConnectOptions,
+    ) -> ApiResult<Self> {
+        // one transport for all three nodes, so they share endpoint health
+        let proxy = AgentProxyNode::try_new_with_transport(
+            transport.clone(),
+            &format!("{:?}", deployment.agent_proxy),
+            signer.clone(),
+        ).await?;
+
+        let chain_id = proxy.contract().client().signer().chain_id();
+        if deployment.chain_id == 0 {
+            deployment.chain_id = chain_id;
+        } else if deployment.chain_id != chain_id {
+            return Err(format!(
+                "Provider chain ID {} does not match deployment \"{}\" on chain ID {}",
+                chain_id, deployment.name, deployment.chain_id
+            ));
+        }
+
+        let manager_address = match deployment.agent_manager {
+            Some(address) => address,
+            None => parse_address(&proxy.get_agent_manager().await?)?,
+        };
+        let factory_address = match deployment.agent_factory {
+            Some(address) => address,
+            None => parse_address(&proxy.get_agent_factory().await?)?,
+        };
+        deployment.agent_manager = Some(manager_address);
+        deployment.agent_factory = Some(factory_address);
+
+        // event scans start at the deployment block, so look it up rather than scan from genesis
+        if deployment.deployment_block.is_none() && options.find_deployment_block {
+            let addresses = [deployment.agent_proxy, manager_address, factory_address];
+            match first_block_with_code(proxy.contract().client().provider(), &addresses).await {
+                Ok(block) => deployment.deployment_block = Some(block),
+                Err(e) => tracing::warn!("Failed to find the deployment block, event scans start at genesis: {}", e),
+            }
+        }
+
+        let manager = AgentManagerNode::try_new_with_transport(
+            transport.clone(),
+            &format!("{:?}", manager_address),
+            signer.clone(),
+        ).await?;
+        let factory = AgentFactoryNode::try_new_with_transport(
+            transport,
+            &format!("{:?}", factory_address),
+            signer,
+        ).await?;
+
+        let mut client = Self { proxy, manager, factory, deployment, versions: None };
+        if options.version_policy != VersionPolicy::Skip {
+            match client.read_versions().await {
+                Ok(versions) => {
+                    if let Err(e) = check_versions(&options.abis, &versions) {
+                        if options.version_policy == VersionPolicy::Refuse {
+                            return Err(e);
+                        }
+                        tracing::warn!("{}", e);
+                    }
+                    client.versions = Some(versions);
+                }
+                Err(e) if options.version_policy == VersionPolicy::Refuse => return Err(e),
+                Err(e) => tracing::warn!("Failed to read contract versions: {}", e),
+            }
+        }
+
+        Ok(client)
+    }
+
+    pub async fn read_versions(&self) -> ApiResult<ContractVersions> {
+        Ok(ContractVersions {
+            agent_proxy: self.proxy.get_type_and_version().await?,
+            agent_manager: self.manager.get_type_and_version().await?,
+            agent_factory: self.factory.type_and_version().await?,
+            agent_version: self.manager.agent_version().await?,
+        })
+    }
+
+    pub fn proxy(&self) -> &AgentProxyNode {
+        &self.proxy
+    }
+
+    pub fn manager(&self) -> &AgentManagerNode {
+        &self.manager
+    }
+
+    pub fn factory(&self) -> &AgentFactoryNode {
+        &self.factory
+    }
+
+    /// The deployment with manager and factory addresses filled in.
+    pub fn deployment(&self) -> &Deployment {
+        &self.deployment
+    }
+
+    /// The versions read at connect time, unless the policy was `Skip` or the reads
+    /// failed under `Warn`.
+    pub fn versions(&self) -> Option<&ContractVersions> {
+        self.versions.as_ref()
+    }
+}
+
+pub fn check_versions(abis: &AbiRegistry, versions: &ContractVersions) -> ApiResult<()> {
+    abis.check(ContractKind::AgentProxy, &versions.agent_proxy, None)?;
+    abis.check(ContractKind::AgentManager, &versions.agent_manager, Some(&versions.agent_version))?;
+    abis.check(ContractKind::AgentFactory, &versions.agent_factory, None)?;
+    Ok(())
+}
+
+/// The first block at which any of `addresses` has code, found by bisecting `eth_getCode`.
+async fn first_block_with_code(provider: &Provider<AttpsTransport>, addresses: &[Address]) -> ApiResult<u64> {
+    let has_code = |block: u64| async move {
+        for address in addresses {
+            let code = provider
+                .get_code(*address, Some(block.into()))
+                .await
+                .map_err(|e| format!("Failed to get code at block {}: {}", block, e))?;
+            if !code.is_empty() {
+                return Ok(true);
+            }
+        }
+        Ok::<bool, String>(false)
+    };
+
+    let mut high = provider
+        .get_block_number()
+        .await
+        .map_err(|e| format!("Failed to get block number: {}", e))?
+        .as_u64();
+    if !has_code(high).await? {
+        return Err("No contract code at the latest block".to_string());
+    }
+    let mut low = 0;
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if has_code(middle).await? {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    Ok(low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use serde_json::{json, Value};
+
+    use crate::utils::mock_server::{MockRequest, MockResponse, MockServer};
+
+    #[tokio::test]
+    async fn test_first_block_with_code() {
+        let (proxy, manager) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        // the manager is deployed at block 4_321, the proxy later
+        let server = MockServer::start(Arc::new(move |request: MockRequest| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let result = match body["method"].as_str() {
+                Some("eth_blockNumber") => json!("0x989680"),
+                Some("eth_getCode") => {
+                    let address: Address = serde_json::from_value(body["params"][0].clone()).unwrap();
+                    let block = u64::from_str_radix(body["params"][1].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+                    let deployed = if address == manager { 4_321 } else { 5_000 };
+                    json!(if block >= deployed { "0x6080" } else { "0x" })
+                }
+                _ => Value::Null,
+            };
+            let response = json!({ "jsonrpc": "2.0", "id": body["id"], "result": result });
+            MockResponse::new(200, "application/json", response.to_string())
+        }))
+        .await
+        .unwrap();
+
+        let provider = Provider::new(AttpsTransport::from_urls(&server.url()).unwrap());
+        assert_eq!(first_block_with_code(&provider, &[proxy, manager]).await.unwrap(), 4_321);
+        assert_eq!(
+            first_block_with_code(&provider, &[Address::repeat_byte(3), Address::zero()]).await.unwrap(),
+            5_000
+        );
+    }
+
+    #[tokio::test]
+    async fn test_warn_policy_survives_failed_version_reads() {
+        use crate::utils::secret::SecretKey;
+
+        // every eth_call returns one address word, which no typeAndVersion string decodes from
+        let code_reads = Arc::new(AtomicUsize::new(0));
+        let counter = code_reads.clone();
+        let server = MockServer::start(Arc::new(move |request: MockRequest| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let result = match body["method"].as_str() {
+                Some("eth_chainId") => json!("0x7a69"),
+                Some("eth_getCode") => {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    json!("0x")
+                }
+                Some("eth_call") => json!(format!("0x{:064x}", 2)),
+                _ => Value::Null,
+            };
+            let response = json!({ "jsonrpc": "2.0", "id": body["id"], "result": result });
+            MockResponse::new(200, "application/json", response.to_string())
+        }))
+        .await
+        .unwrap();
+
+        let deployment = Deployment {
+            name: "local".to_string(),
+            chain_id: 31337,
+            agent_proxy: Address::repeat_byte(1),
+            agent_manager: None,
+            agent_factory: None,
+            deployment_block: None,
+        };
+        let mut options = ConnectOptions { version_policy: VersionPolicy::Refuse, ..Default::default() };
+        options.deployments.insert(deployment);
+        let wallet = SecretKey::from("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80").to_wallet().unwrap();
+
+        let refused = AttpsClient::connect_with_options("local", &server.url(), wallet.clone(), &options).await;
+        assert!(refused.is_err());
+
+        options.version_policy = VersionPolicy::Warn;
+        let client = AttpsClient::connect_with_options("local", &server.url(), wallet, &options).await.unwrap();
+        assert!(client.versions().is_none());
+        assert_eq!(client.deployment().agent_manager, Some(Address::from_low_u64_be(2)));
+        // the deployment block is only looked up when asked for
+        assert_eq!(client.deployment().deployment_block, None);
+        assert_eq!(code_reads.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,676 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{Path, Request, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, MethodRouter};
+use axum::{Json, Router};
+use ethers::types::{Address, H256};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+use crate::core::ApiResult;
+use crate::svc::agent_manager_node::{settings_json, AgentConfig};
+use crate::svc::agent_proxy_node::{parse_verify_args, MessagePayload, VerifyError};
+use crate::svc::client::AttpsClient;
+use crate::utils::secret::SecretKey;
+
+const OPENAPI_SPEC: &str = include_str!("gateway_openapi.json");
+
+/// A gateway route. Routes can be switched on and off by name in `GatewayConfig`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GatewayRoute {
+    ProxyAgentManager,
+    ProxyAgentFactory,
+    ProxyOwner,
+    ProxyTypeAndVersion,
+    ManagerOwner,
+    ManagerTypeAndVersion,
+    ManagerAgentVersion,
+    ManagerAllowedAgents,
+    ManagerRegisteringAgents,
+    ManagerAllowedAgent,
+    ManagerAgentConfigs,
+    ManagerSignerThreshold,
+    ManagerAllowedSigner,
+    ManagerValidMessageId,
+    ManagerValidSourceAgentId,
+    FactoryAgents,
+    FactoryHasAgent,
+    VerifyPreflight,
+    Verify,
+}
+
+impl GatewayRoute {
+    pub const ALL: [GatewayRoute; 19] = [
+        GatewayRoute::ProxyAgentManager,
+        GatewayRoute::ProxyAgentFactory,
+        GatewayRoute::ProxyOwner,
+        GatewayRoute::ProxyTypeAndVersion,
+        GatewayRoute::ManagerOwner,
+        GatewayRoute::ManagerTypeAndVersion,
+        GatewayRoute::ManagerAgentVersion,
+        GatewayRoute::ManagerAllowedAgents,
+        GatewayRoute::ManagerRegisteringAgents,
+        GatewayRoute::ManagerAllowedAgent,
+        GatewayRoute::ManagerAgentConfigs,
+        GatewayRoute::ManagerSignerThreshold,
+        GatewayRoute::ManagerAllowedSigner,
+        GatewayRoute::ManagerValidMessageId,
+        GatewayRoute::ManagerValidSourceAgentId,
+        GatewayRoute::FactoryAgents,
+        GatewayRoute::FactoryHasAgent,
+        GatewayRoute::VerifyPreflight,
+        GatewayRoute::Verify,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GatewayRoute::ProxyAgentManager => "proxy.agent_manager",
+            GatewayRoute::ProxyAgentFactory => "proxy.agent_factory",
+            GatewayRoute::ProxyOwner => "proxy.owner",
+            GatewayRoute::ProxyTypeAndVersion => "proxy.type_and_version",
+            GatewayRoute::ManagerOwner => "manager.owner",
+            GatewayRoute::ManagerTypeAndVersion => "manager.type_and_version",
+            GatewayRoute::ManagerAgentVersion => "manager.agent_version",
+            GatewayRoute::ManagerAllowedAgents => "manager.allowed_agents",
+            GatewayRoute::ManagerRegisteringAgents => "manager.registering_agents",
+            GatewayRoute::ManagerAllowedAgent => "manager.allowed_agent",
+            GatewayRoute::ManagerAgentConfigs => "manager.agent_configs",
+            GatewayRoute::ManagerSignerThreshold => "manager.signer_threshold",
+            GatewayRoute::ManagerAllowedSigner => "manager.allowed_signer",
+            GatewayRoute::ManagerValidMessageId => "manager.valid_message_id",
+            GatewayRoute::ManagerValidSourceAgentId => "manager.valid_source_agent_id",
+            GatewayRoute::FactoryAgents => "factory.agents",
+            GatewayRoute::FactoryHasAgent => "factory.has_agent",
+            GatewayRoute::VerifyPreflight => "proxy.verify_preflight",
+            GatewayRoute::Verify => "proxy.verify",
+        }
+    }
+
+    pub fn path(&self) -> &'static str {
+        match self {
+            GatewayRoute::ProxyAgentManager => "/v1/proxy/agent-manager",
+            GatewayRoute::ProxyAgentFactory => "/v1/proxy/agent-factory",
+            GatewayRoute::ProxyOwner => "/v1/proxy/owner",
+            GatewayRoute::ProxyTypeAndVersion => "/v1/proxy/type-and-version",
+            GatewayRoute::ManagerOwner => "/v1/manager/owner",
+            GatewayRoute::ManagerTypeAndVersion => "/v1/manager/type-and-version",
+            GatewayRoute::ManagerAgentVersion => "/v1/manager/agent-version",
+            GatewayRoute::ManagerAllowedAgents => "/v1/manager/agents",
+            GatewayRoute::ManagerRegisteringAgents => "/v1/manager/registering-agents",
+            GatewayRoute::ManagerAllowedAgent => "/v1/manager/agents/{agent}/allowed",
+            GatewayRoute::ManagerAgentConfigs => "/v1/manager/agents/{agent}/configs",
+            GatewayRoute::ManagerSignerThreshold => "/v1/manager/agents/{agent}/configs/{digest}/threshold",
+            GatewayRoute::ManagerAllowedSigner => "/v1/manager/agents/{agent}/configs/{digest}/signers/{signer}",
+            GatewayRoute::ManagerValidMessageId => "/v1/manager/message-ids/{id}",
+            GatewayRoute::ManagerValidSourceAgentId => "/v1/manager/source-agent-ids/{id}",
+            GatewayRoute::FactoryAgents => "/v1/factory/agents",
+            GatewayRoute::FactoryHasAgent => "/v1/factory/agents/{agent}",
+            GatewayRoute::VerifyPreflight => "/v1/proxy/verify/preflight",
+            GatewayRoute::Verify => "/v1/proxy/verify",
+        }
+    }
+
+    /// Whether the route sends transactions from the gateway's wallet.
+    pub fn writes(&self) -> bool {
+        matches!(self, GatewayRoute::Verify)
+    }
+
+    pub fn from_name(name: &str) -> ApiResult<Self> {
+        GatewayRoute::ALL
+            .into_iter()
+            .find(|route| route.name() == name)
+            .ok_or_else(|| format!("Unknown gateway route \"{}\"", name))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GatewayConfig {
+    /// Accepted in an `X-API-Key` header or as `Authorization: Bearer <key>`.
+    #[serde(default)]
+    pub api_keys: Vec<SecretKey>,
+    /// Serve without API keys, e.g. behind an authenticating proxy.
+    #[serde(default)]
+    pub allow_unauthenticated: bool,
+    /// GatewayRoute name to enabled flag. Read and preflight routes are on and write routes
+    /// off unless listed here.
+    #[serde(default)]
+    pub routes: HashMap<String, bool>,
+}
+
+impl GatewayConfig {
+    pub fn validate(&self) -> ApiResult<()> {
+        if self.api_keys.is_empty() && !self.allow_unauthenticated {
+            return Err("The gateway needs at least one API key (or allow_unauthenticated)".to_string());
+        }
+        if self.api_keys.iter().any(|key| key.expose_secret().is_empty()) {
+            return Err("Gateway API keys must not be empty".to_string());
+        }
+        for name in self.routes.keys() {
+            GatewayRoute::from_name(name)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_enabled(&self, route: GatewayRoute) -> bool {
+        self.routes.get(route.name()).copied().unwrap_or(!route.writes())
+    }
+
+    fn authorized(&self, headers: &HeaderMap) -> bool {
+        if self.api_keys.is_empty() {
+            return self.allow_unauthenticated;
+        }
+        let presented = headers
+            .get("x-api-key")
+            .and_then(|value| value.to_str().ok())
+            .or_else(|| {
+                headers
+                    .get("authorization")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.strip_prefix("Bearer "))
+            });
+        presented.is_some_and(|presented| {
+            self.api_keys
+                .iter()
+                .any(|key| constant_time_eq(key.expose_secret().as_bytes(), presented.trim().as_bytes()))
+        })
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Serves an `AttpsClient` over REST/JSON. Successful responses are `{"result": ...}`,
+/// failures `{"error": "..."}` with the status of a `GatewayError`. `/health` and
+/// `/openapi.json` need no API key.
+pub struct Gateway {
+    client: Arc<AttpsClient>,
+    config: Arc<GatewayConfig>,
+}
+
+type GatewayState = Arc<AttpsClient>;
+
+impl Gateway {
+    pub fn new(client: AttpsClient, config: GatewayConfig) -> ApiResult<Self> {
+        config.validate()?;
+        Ok(Self { client: Arc::new(client), config: Arc::new(config) })
+    }
+
+    pub fn router(&self) -> Router {
+        let mut api = Router::new();
+        for route in GatewayRoute::ALL.into_iter().filter(|route| self.config.is_enabled(*route)) {
+            api = api.route(route.path(), handler(route));
+        }
+        let api = api
+            .with_state(self.client.clone())
+            .layer(middleware::from_fn_with_state(self.config.clone(), authorize));
+
+        Router::new()
+            .route("/health", get(|| async { Json(json!({ "result": "ok" })) }))
+            .route("/openapi.json", get(openapi))
+            .merge(api)
+    }
+
+    pub async fn serve(&self, listener: TcpListener) -> ApiResult<()> {
+        axum::serve(listener, self.router())
+            .await
+            .map_err(|e| format!("Gateway server failed: {}", e))
+    }
+}
+
+async fn authorize(State(config): State<Arc<GatewayConfig>>, request: Request, next: Next) -> Response {
+    if !config.authorized(request.headers()) {
+        return (StatusCode::UNAUTHORIZED, Json(json!({ "error": "Missing or invalid API key" }))).into_response();
+    }
+    next.run(request).await
+}
+
+async fn openapi() -> Response {
+    ([("content-type", "application/json")], OPENAPI_SPEC).into_response()
+}
+
+/// Why a request failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum GatewayError {
+    /// Malformed input, rejected before anything reaches the chain (400).
+    BadRequest(String),
+    /// `verify` reverted during gas estimation, so nothing was sent (422).
+    Reverted { error: String, revert_reason: String },
+    /// The node failed or could not be reached (502).
+    Upstream(String),
+}
+
+impl From<VerifyError> for GatewayError {
+    fn from(error: VerifyError) -> Self {
+        match error {
+            VerifyError::Reverted { reason, message } => GatewayError::Reverted { error: message, revert_reason: reason },
+            VerifyError::Failed(message) => GatewayError::Upstream(message),
+        }
+    }
+}
+
+// axum answers a bad body with a plain-text 422, which clients would read as a revert
+impl From<JsonRejection> for GatewayError {
+    fn from(rejection: JsonRejection) -> Self {
+        GatewayError::BadRequest(rejection.body_text())
+    }
+}
+
+impl IntoResponse for GatewayError {
+    fn into_response(self) -> Response {
+        let (status, body) = match self {
+            GatewayError::BadRequest(error) => (StatusCode::BAD_REQUEST, json!({ "error": error })),
+            GatewayError::Reverted { error, revert_reason } => {
+                (StatusCode::UNPROCESSABLE_ENTITY, json!({ "error": error, "revert_reason": revert_reason }))
+            }
+            GatewayError::Upstream(error) => (StatusCode::BAD_GATEWAY, json!({ "error": error })),
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+fn respond(result: Result<Value, GatewayError>) -> Response {
+    match result {
+        Ok(result) => Json(json!({ "result": result })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+/// Runs a node call whose input was already checked; any failure is the node's.
+async fn upstream<T>(call: impl std::future::Future<Output = ApiResult<T>>, to_json: impl FnOnce(T) -> Value) -> Response {
+    respond(call.await.map(to_json).map_err(GatewayError::Upstream))
+}
+
+fn check_address(name: &str, value: &str) -> Result<(), GatewayError> {
+    value
+        .parse::<Address>()
+        .map(|_| ())
+        .map_err(|e| GatewayError::BadRequest(format!("Invalid {} address format: {}", name, e)))
+}
+
+fn check_digest(value: &str) -> Result<(), GatewayError> {
+    value
+        .parse::<H256>()
+        .map(|_| ())
+        .map_err(|e| GatewayError::BadRequest(format!("Invalid setting digest format: {}", e)))
+}
+
+fn config_json(config: &AgentConfig) -> Value {
+    json!({
+        "config_digest": H256::from(config.config_digest),
+        "config_block_number": config.config_block_number,
+        "is_active": config.is_active,
+        "settings": settings_json(&config.settings),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifyBody {
+    agent: String,
+    settings_digest: String,
+    data: String,
+    data_hash: String,
+    signature_proof: String,
+    zk_proof: String,
+    merkle_proof: String,
+    content_type: String,
+    encoding: String,
+    compression: String,
+    /// Preflight only: the block to simulate at.
+    #[serde(default)]
+    block: Option<u64>,
+}
+
+impl VerifyBody {
+    fn parse(&self) -> Result<(Address, [u8; 32], MessagePayload), GatewayError> {
+        parse_verify_args(
+            &self.agent,
+            &self.settings_digest,
+            &self.data,
+            &self.data_hash,
+            &self.signature_proof,
+            &self.zk_proof,
+            &self.merkle_proof,
+            &self.content_type,
+            &self.encoding,
+            &self.compression,
+        )
+        .map_err(GatewayError::BadRequest)
+    }
+}
+
+fn handler(route: GatewayRoute) -> MethodRouter<GatewayState> {
+    match route {
+        GatewayRoute::ProxyAgentManager => get(|State(client): State<GatewayState>| async move {
+            upstream(client.proxy().get_agent_manager(), Value::from).await
+        }),
+        GatewayRoute::ProxyAgentFactory => get(|State(client): State<GatewayState>| async move {
+            upstream(client.proxy().get_agent_factory(), Value::from).await
+        }),
+        GatewayRoute::ProxyOwner => get(|State(client): State<GatewayState>| async move {
+            upstream(client.proxy().get_owner(), Value::from).await
+        }),
+        GatewayRoute::ProxyTypeAndVersion => get(|State(client): State<GatewayState>| async move {
+            upstream(client.proxy().get_type_and_version(), Value::from).await
+        }),
+        GatewayRoute::ManagerOwner => get(|State(client): State<GatewayState>| async move {
+            upstream(client.manager().get_owner(), Value::from).await
+        }),
+        GatewayRoute::ManagerTypeAndVersion => get(|State(client): State<GatewayState>| async move {
+            upstream(client.manager().get_type_and_version(), Value::from).await
+        }),
+        GatewayRoute::ManagerAgentVersion => get(|State(client): State<GatewayState>| async move {
+            upstream(client.manager().agent_version(), Value::from).await
+        }),
+        GatewayRoute::ManagerAllowedAgents => get(|State(client): State<GatewayState>| async move {
+            upstream(client.manager().allowed_agents(), |agents| json!(agents)).await
+        }),
+        GatewayRoute::ManagerRegisteringAgents => get(|State(client): State<GatewayState>| async move {
+            upstream(client.manager().registering_agents(), |agents| json!(agents)).await
+        }),
+        GatewayRoute::ManagerAllowedAgent => get(|State(client): State<GatewayState>, Path(agent): Path<String>| async move {
+            check_address("agent", &agent)?;
+            Ok::<_, GatewayError>(upstream(client.manager().allowed_agent(&agent), Value::from).await)
+        }),
+        GatewayRoute::ManagerAgentConfigs => get(|State(client): State<GatewayState>, Path(agent): Path<String>| async move {
+            check_address("agent", &agent)?;
+            Ok::<_, GatewayError>(
+                upstream(client.manager().agent_configs(&agent), |configs| configs.iter().map(config_json).collect()).await,
+            )
+        }),
+        GatewayRoute::ManagerSignerThreshold => get(
+            |State(client): State<GatewayState>, Path((agent, digest)): Path<(String, String)>| async move {
+                check_address("agent", &agent)?;
+                check_digest(&digest)?;
+                Ok::<_, GatewayError>(upstream(client.manager().signer_threshold(&agent, &digest), Value::from).await)
+            },
+        ),
+        GatewayRoute::ManagerAllowedSigner => get(
+            |State(client): State<GatewayState>, Path((agent, digest, signer)): Path<(String, String, String)>| async move {
+                check_address("agent", &agent)?;
+                check_digest(&digest)?;
+                check_address("signer", &signer)?;
+                Ok::<_, GatewayError>(upstream(client.manager().allowed_signer(&agent, &digest, &signer), Value::from).await)
+            },
+        ),
+        GatewayRoute::ManagerValidMessageId => get(|State(client): State<GatewayState>, Path(id): Path<String>| async move {
+            upstream(client.manager().is_valid_message_id(&id), Value::from).await
+        }),
+        GatewayRoute::ManagerValidSourceAgentId => get(|State(client): State<GatewayState>, Path(id): Path<String>| async move {
+            upstream(client.manager().is_valid_source_agent_id(&id), Value::from).await
+        }),
+        GatewayRoute::FactoryAgents => get(|State(client): State<GatewayState>| async move {
+            upstream(client.factory().agents(), |agents| json!(agents)).await
+        }),
+        GatewayRoute::FactoryHasAgent => get(|State(client): State<GatewayState>, Path(agent): Path<String>| async move {
+            check_address("agent", &agent)?;
+            Ok::<_, GatewayError>(upstream(client.factory().has_agent(&agent), Value::from).await)
+        }),
+        GatewayRoute::VerifyPreflight => post(|State(client): State<GatewayState>, body: Result<Json<VerifyBody>, JsonRejection>| async move {
+            let Json(body) = body?;
+            body.parse()?;
+            let result = client.proxy().verify_call(
+                &body.agent,
+                &body.settings_digest,
+                &body.data,
+                &body.data_hash,
+                &body.signature_proof,
+                &body.zk_proof,
+                &body.merkle_proof,
+                &body.content_type,
+                &body.encoding,
+                &body.compression,
+                body.block,
+            );
+            Ok::<_, GatewayError>(
+                upstream(result, |result| json!({ "passed": result.passed, "revert_reason": result.revert_reason })).await,
+            )
+        }),
+        GatewayRoute::Verify => post(|State(client): State<GatewayState>, body: Result<Json<VerifyBody>, JsonRejection>| async move {
+            let Json(body) = body?;
+            let receipt = client.proxy().send_verify(body.parse()?).await.map_err(GatewayError::from)?;
+            Ok::<_, GatewayError>(respond(Ok(json!({ "receipt": receipt }))))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_config_and_auth() {
+        let mut config = GatewayConfig::default();
+        assert!(config.validate().unwrap_err().contains("at least one API key"));
+
+        config.api_keys = vec![SecretKey::from("key-1")];
+        config.routes.insert("proxy.verify".to_string(), true);
+        config.routes.insert("manager.owner".to_string(), false);
+        config.validate().unwrap();
+        assert!(config.is_enabled(GatewayRoute::Verify) && config.is_enabled(GatewayRoute::ProxyOwner));
+        assert!(!config.is_enabled(GatewayRoute::ManagerOwner));
+        assert!(!GatewayConfig::default().is_enabled(GatewayRoute::Verify));
+
+        let mut headers = HeaderMap::new();
+        assert!(!config.authorized(&headers));
+        headers.insert("authorization", HeaderValue::from_static("Bearer key-1"));
+        assert!(config.authorized(&headers));
+        headers.insert("x-api-key", HeaderValue::from_static("key-2"));
+        assert!(!config.authorized(&headers));
+
+        config.routes.insert("proxy.delete_everything".to_string(), true);
+        assert_eq!(config.validate().unwrap_err(), "Unknown gateway route \"proxy.delete_everything\"");
+
+        let spec: Value = serde_json::from_str(OPENAPI_SPEC).unwrap();
+        for route in GatewayRoute::ALL {
+            assert!(spec["paths"][route.path()].is_object(), "{} is missing from the spec", route.path());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gateway_against_mock_node() {
+        use std::sync::Arc;
+
+        use ethers::abi::Token;
+        use ethers::types::Address;
+
+        use crate::core::deployments::Deployment;
+        use crate::core::versions::VersionPolicy;
+        use crate::svc::client::ConnectOptions;
+        use crate::utils::mock_server::{MockRequest, MockResponse, MockServer};
+
+        // getters return one address word, verify reverts and owner() fails
+        let revert = [&[0x08, 0xc3, 0x79, 0xa0][..], &ethers::abi::encode(&[Token::String("unknown agent".to_string())])].concat();
+        let server = MockServer::start(Arc::new(move |request: MockRequest| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let call = &body["params"][0];
+            let input = call["input"].as_str().or(call["data"].as_str()).unwrap_or_default().to_string();
+            let outcome = match body["method"].as_str() {
+                Some("eth_chainId") => Ok(json!("0x7a69")),
+                Some("eth_call") if input.starts_with("0x8da5cb5b") => Err(json!({ "code": -32000, "message": "node is down" })),
+                Some("eth_call") if input.len() <= 10 => Ok(json!(format!("0x{:064x}", 2))),
+                Some("eth_call") | Some("eth_estimateGas") => Err(json!({
+                    "code": 3,
+                    "message": "execution reverted",
+                    "data": format!("0x{}", hex::encode(&revert)),
+                })),
+                _ => Ok(Value::Null),
+            };
+            let response = match outcome {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": body["id"], "result": result }),
+                Err(error) => json!({ "jsonrpc": "2.0", "id": body["id"], "error": error }),
+            };
+            MockResponse::new(200, "application/json", response.to_string())
+        }))
+        .await
+        .unwrap();
+
+        let mut options = ConnectOptions { version_policy: VersionPolicy::Skip, ..Default::default() };
+        options.deployments.insert(Deployment {
+            name: "local".to_string(),
+            chain_id: 31337,
+            agent_proxy: Address::repeat_byte(1),
+            agent_manager: None,
+            agent_factory: None,
+            deployment_block: Some(0),
+        });
+        // anvil's first dev account
+        let wallet = SecretKey::from("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80").to_wallet().unwrap();
+        let serve = |config: GatewayConfig| {
+            let (url, options, wallet) = (server.url(), options.clone(), wallet.clone());
+            async move {
+                let client = AttpsClient::connect_with_options("local", &url, wallet, &options).await.unwrap();
+                let gateway = Gateway::new(client, config).unwrap();
+                let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+                let url = format!("http://{}", listener.local_addr().unwrap());
+                tokio::spawn(async move { gateway.serve(listener).await });
+                url
+            }
+        };
+        let mut config = GatewayConfig { api_keys: vec![SecretKey::from("test-key")], ..Default::default() };
+        let url = serve(config.clone()).await;
+        config.routes.insert("proxy.verify".to_string(), true);
+        let write_url = serve(config).await;
+
+        let http = reqwest::Client::new();
+        let get = |path: String| http.get(path).header("x-api-key", "test-key").send();
+        let verify = json!({
+            "agent": format!("{:?}", Address::repeat_byte(0xaa)),
+            "settings_digest": format!("{:?}", H256::repeat_byte(0xbb)),
+            "data": "00",
+            "data_hash": format!("{:?}", H256::zero()),
+            "signature_proof": "0x",
+            "zk_proof": "0x",
+            "merkle_proof": "0x",
+            "content_type": "",
+            "encoding": "",
+            "compression": "",
+        });
+        let post = |url: &str, path: &str, body: &Value| {
+            http.post(format!("{}{}", url, path)).header("x-api-key", "test-key").json(body).send()
+        };
+
+        let response = http.get(format!("{}/v1/proxy/agent-manager", url)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response: Value = get(format!("{}/v1/proxy/agent-manager", url)).await.unwrap().json().await.unwrap();
+        assert_eq!(response["result"], json!(format!("{:?}", Address::from_low_u64_be(2))));
+
+        let response = get(format!("{}/v1/manager/agents/not-an-address/allowed", url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = get(format!("{}/v1/proxy/owner", url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+
+        let response: Value = post(&url, "/v1/proxy/verify/preflight", &verify).await.unwrap().json().await.unwrap();
+        assert_eq!(response["result"], json!({ "passed": false, "revert_reason": "unknown agent" }));
+
+        // write routes are off by default
+        let response = post(&url, "/v1/proxy/verify", &verify).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let mut invalid = verify.clone();
+        invalid["data_hash"] = json!("0x12");
+        let response = post(&write_url, "/v1/proxy/verify", &invalid).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // a body that does not deserialize is a 400 with a JSON error, never the 422 of a revert
+        let mut incomplete = verify.clone();
+        incomplete.as_object_mut().unwrap().remove("agent");
+        for path in ["/v1/proxy/verify", "/v1/proxy/verify/preflight"] {
+            let response = post(&write_url, path, &incomplete).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let response: Value = response.json().await.unwrap();
+            assert!(response["error"].as_str().unwrap().contains("agent"), "{}", response);
+        }
+        let response = http
+            .post(format!("{}/v1/proxy/verify", write_url))
+            .header("x-api-key", "test-key")
+            .header("content-type", "application/json")
+            .body("{\"agent\":")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = post(&write_url, "/v1/proxy/verify", &verify).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let response: Value = response.json().await.unwrap();
+        assert_eq!(response["revert_reason"], "unknown agent");
+    }
+
+    /// Runs against a local chain, e.g. anvil with the ATTPS contracts deployed:
+    /// `ATTPS_TEST_PROVIDER=http://127.0.0.1:8545 ATTPS_TEST_AGENT_PROXY=0x... cargo test -- --ignored`.
+    #[tokio::test]
+    #[ignore = "needs a local chain with the ATTPS contracts deployed"]
+    async fn test_gateway_against_local_chain() {
+        use crate::core::deployments::Deployment;
+        use crate::svc::client::ConnectOptions;
+
+        let provider = std::env::var("ATTPS_TEST_PROVIDER").unwrap_or_else(|_| "http://127.0.0.1:8545".to_string());
+        let agent_proxy = std::env::var("ATTPS_TEST_AGENT_PROXY").expect("ATTPS_TEST_AGENT_PROXY is not set");
+        let mut options = ConnectOptions::default();
+        options.deployments.insert(Deployment {
+            name: "local".to_string(),
+            chain_id: 0,
+            agent_proxy: agent_proxy.parse().unwrap(),
+            agent_manager: None,
+            agent_factory: None,
+            deployment_block: Some(0),
+        });
+        // anvil's first dev account
+        let wallet = SecretKey::from("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80").to_wallet().unwrap();
+        let client = AttpsClient::connect_with_options("local", &provider, wallet, &options).await.unwrap();
+        let manager = client.deployment().agent_manager.unwrap();
+
+        let mut config = GatewayConfig { api_keys: vec![SecretKey::from("test-key")], ..Default::default() };
+        config.routes.insert("proxy.verify".to_string(), true);
+        let gateway = Gateway::new(client, config).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { gateway.serve(listener).await });
+
+        let http = reqwest::Client::new();
+        let response: Value = http
+            .get(format!("{}/v1/proxy/agent-manager", url))
+            .header("x-api-key", "test-key")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response["result"], json!(format!("{:?}", manager)));
+
+        // no agent is registered under this address, so verify reverts before anything is sent
+        let verify = json!({
+            "agent": format!("{:?}", Address::repeat_byte(0xaa)),
+            "settings_digest": format!("{:?}", H256::repeat_byte(0xbb)),
+            "data": "00",
+            "data_hash": format!("{:?}", H256::zero()),
+            "signature_proof": "0x",
+            "zk_proof": "0x",
+            "merkle_proof": "0x",
+            "content_type": "",
+            "encoding": "",
+            "compression": "",
+        });
+        let response = http
+            .post(format!("{}/v1/proxy/verify", url))
+            .header("x-api-key", "test-key")
+            .json(&verify)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let response: Value = response.json().await.unwrap();
+        assert!(response["revert_reason"].is_string(), "{}", response);
+    }
+}
@@ -0,0 +1,740 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use ethers::abi::{AbiDecode, AbiEncode, RawLog};
+use ethers::contract::EthLogDecode;
+use ethers::prelude::*;
+use rusqlite::{params, Connection, OptionalExtension, Params, Row, Transaction};
+
+use crate::core::transport::AttpsTransport;
+use crate::core::ApiResult;
+use crate::svc::agent_factory_node::AgentCreatedFilter;
+use crate::svc::agent_manager_node::{AgentConfig, AgentManagerContractEvents, AgentSettings};
+use crate::svc::agent_proxy_node::AgentProxyContractEvents;
+use crate::svc::client::AttpsClient;
+use crate::utils::common::parse_address;
+
+const SYNC_CHUNK: u64 = 2_000;
+/// Block hashes kept for reorg detection. A reorg deeper than this rebuilds the index.
+const REORG_DEPTH: u64 = 256;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+CREATE TABLE IF NOT EXISTS blocks (number INTEGER PRIMARY KEY, hash TEXT NOT NULL);
+-- created (factory), registered and removed (manager)
+CREATE TABLE IF NOT EXISTS agents (
+    block_number INTEGER NOT NULL,
+    log_index INTEGER NOT NULL,
+    agent TEXT NOT NULL,
+    event TEXT NOT NULL,
+    owner TEXT,
+    agent_id TEXT,
+    PRIMARY KEY (block_number, log_index)
+);
+-- accepted and updated settings, with their digest
+CREATE TABLE IF NOT EXISTS configs (
+    block_number INTEGER NOT NULL,
+    log_index INTEGER NOT NULL,
+    agent TEXT NOT NULL,
+    digest TEXT NOT NULL,
+    event TEXT NOT NULL,
+    threshold INTEGER NOT NULL,
+    settings BLOB NOT NULL,
+    PRIMARY KEY (block_number, log_index)
+);
+CREATE TABLE IF NOT EXISTS config_signers (
+    block_number INTEGER NOT NULL,
+    log_index INTEGER NOT NULL,
+    signer TEXT NOT NULL,
+    PRIMARY KEY (block_number, log_index, signer)
+);
+-- settings from registrations and proposals, waiting to be accepted
+CREATE TABLE IF NOT EXISTS proposals (
+    block_number INTEGER NOT NULL,
+    log_index INTEGER NOT NULL,
+    agent TEXT NOT NULL,
+    event TEXT NOT NULL,
+    threshold INTEGER NOT NULL,
+    settings BLOB NOT NULL,
+    PRIMARY KEY (block_number, log_index)
+);
+CREATE TABLE IF NOT EXISTS ownership (
+    block_number INTEGER NOT NULL,
+    log_index INTEGER NOT NULL,
+    contract TEXT NOT NULL,
+    event TEXT NOT NULL,
+    from_address TEXT NOT NULL,
+    to_address TEXT NOT NULL,
+    PRIMARY KEY (block_number, log_index)
+);
+CREATE INDEX IF NOT EXISTS agents_by_agent ON agents (agent);
+CREATE INDEX IF NOT EXISTS configs_by_agent ON configs (agent);
+CREATE INDEX IF NOT EXISTS config_signers_by_signer ON config_signers (signer);
+CREATE INDEX IF NOT EXISTS proposals_by_agent ON proposals (agent);
+-- the latest config of each agent that was not removed afterwards
+CREATE VIEW IF NOT EXISTS active_configs AS
+    SELECT * FROM configs c
+    WHERE NOT EXISTS (
+        SELECT 1 FROM configs n
+        WHERE n.agent = c.agent AND (n.block_number, n.log_index) > (c.block_number, c.log_index)
+    ) AND NOT EXISTS (
+        SELECT 1 FROM agents r
+        WHERE r.agent = c.agent AND r.event = 'removed' AND (r.block_number, r.log_index) > (c.block_number, c.log_index)
+    );
+";
+
+const EVENT_TABLES: [&str; 5] = ["agents", "configs", "config_signers", "proposals", "ownership"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncReport {
+    pub from_block: u64,
+    pub to_block: u64,
+    pub events: usize,
+    /// Set when a reorg was found; everything after this block was indexed again.
+    pub reorged_after: Option<u64>,
+}
+
+/// Indexes AgentFactory, AgentManager and AgentProxy events into a SQLite database.
+///
+/// Every row keeps the block and log index of its event, so a reorg is undone by
+/// deleting the rows after the fork. The hashes of recent blocks with events, and of
+/// the last block of each sync, are compared with the chain at the start of each sync
+/// to find the fork.
+pub struct Indexer {
+    provider: Provider<AttpsTransport>,
+    proxy: Address,
+    manager: Address,
+    factory: Address,
+    start_block: u64,
+    db: Mutex<Connection>,
+}
+
+impl Indexer {
+    /// Opens or creates the index at `path` for the client's deployment. Indexing starts
+    /// at the deployment's `deployment_block` when it has one.
+    pub fn open(path: impl AsRef<Path>, client: &AttpsClient) -> ApiResult<Self> {
+        let db = Connection::open(path.as_ref())
+            .map_err(|e| format!("Failed to open index {}: {}", path.as_ref().display(), e))?;
+        let deployment = client.deployment();
+        Self::with_connection(
+            db,
+            client.proxy().contract().client().inner().clone(),
+            deployment.agent_proxy,
+            deployment.agent_manager.ok_or("The deployment has no AgentManager address")?,
+            deployment.agent_factory.ok_or("The deployment has no AgentFactory address")?,
+            deployment.deployment_block.unwrap_or_default(),
+        )
+    }
+
+    fn with_connection(
+        db: Connection,
+        provider: Provider<AttpsTransport>,
+        proxy: Address,
+        manager: Address,
+        factory: Address,
+        start_block: u64,
+    ) -> ApiResult<Self> {
+        db.execute_batch(SCHEMA).map_err(db_error)?;
+
+        let contracts = format!("{:?},{:?},{:?}", proxy, manager, factory);
+        match read_meta(&db, "contracts")? {
+            Some(indexed) if indexed != contracts => {
+                return Err(format!("The index was built for contracts {}, not {}", indexed, contracts));
+            }
+            Some(_) => {}
+            None => write_meta(&db, "contracts", &contracts)?,
+        }
+
+        Ok(Self { provider, proxy, manager, factory, start_block, db: Mutex::new(db) })
+    }
+
+    /// The last block whose events are in the index.
+    pub fn last_block(&self) -> ApiResult<Option<u64>> {
+        let db = self.db.lock().unwrap();
+        read_meta(&db, "last_block")?
+            .map(|block| block.parse().map_err(|_| format!("Invalid last_block in index: {}", block)))
+            .transpose()
+    }
+
+    /// Indexes everything up to the current head, first undoing any blocks that were
+    /// reorged out since the last sync.
+    pub async fn sync(&self) -> ApiResult<SyncReport> {
+        let reorged_after = self.undo_reorg().await?;
+        let from_block = self.last_block()?.map_or(self.start_block, |block| block + 1);
+        let head = self.provider.get_block_number().await
+            .map_err(|e| format!("Failed to get block number: {}", e))?
+            .as_u64();
+
+        let mut report = SyncReport { from_block, to_block: head, events: 0, reorged_after };
+        let filter = Filter::new().address(vec![self.proxy, self.manager, self.factory]);
+        let mut start = from_block;
+        while start <= head {
+            let end = (start + SYNC_CHUNK - 1).min(head);
+            // read the hash first: if the chain reorgs while the logs are read, the next
+            // sync sees a different hash at `end`
+            let end_hash = self.block_hash(end).await?
+                .ok_or_else(|| format!("Block {} is not available", end))?;
+            let logs = self.provider.get_logs(&filter.clone().from_block(start).to_block(end)).await
+                .map_err(|e| format!("Failed to get logs for blocks {}-{}: {}", start, end, e))?;
+
+            let mut db = self.db.lock().unwrap();
+            let tx = db.transaction().map_err(db_error)?;
+            for log in &logs {
+                report.events += self.index_log(&tx, log)?;
+                if let (Some(number), Some(hash)) = (log.block_number, log.block_hash) {
+                    tx.execute("INSERT OR REPLACE INTO blocks (number, hash) VALUES (?1, ?2)", params![number.as_u64(), format!("{:?}", hash)])
+                        .map_err(db_error)?;
+                }
+            }
+            tx.execute("INSERT OR REPLACE INTO blocks (number, hash) VALUES (?1, ?2)", params![end, format!("{:?}", end_hash)])
+                .map_err(db_error)?;
+            tx.execute("DELETE FROM blocks WHERE number < ?1", params![end.saturating_sub(REORG_DEPTH)])
+                .map_err(db_error)?;
+            write_meta(&tx, "last_block", &end.to_string())?;
+            tx.commit().map_err(db_error)?;
+
+            tracing::debug!(start, end, logs = logs.len(), "Indexed blocks");
+            start = end + 1;
+        }
+        Ok(report)
+    }
+
+    /// Syncs every `interval` until an error that is not a provider failure.
+    pub async fn follow(&self, interval: Duration) -> ApiResult<()> {
+        loop {
+            match self.sync().await {
+                Ok(report) if report.events > 0 || report.reorged_after.is_some() => {
+                    tracing::info!(?report, "Index updated");
+                }
+                Ok(_) => {}
+                Err(e) if e.starts_with("Index database error") => return Err(e),
+                Err(e) => tracing::warn!("Index sync failed: {}", e),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Drops everything indexed and syncs again from the start block.
+    pub async fn rebuild(&self) -> ApiResult<SyncReport> {
+        {
+            let mut db = self.db.lock().unwrap();
+            let tx = db.transaction().map_err(db_error)?;
+            rollback(&tx, None)?;
+            tx.commit().map_err(db_error)?;
+        }
+        self.sync().await
+    }
+
+    /// Every config an agent has had, oldest first, like `AgentManagerNode::agent_configs`.
+    /// Only the latest is active, unless the agent was removed after it.
+    pub fn agent_configs(&self, agent_address: &str) -> ApiResult<Vec<AgentConfig>> {
+        let agent = parse_address(agent_address)?;
+        let active = self.query(
+            "SELECT block_number, digest, settings FROM active_configs WHERE agent = ?1",
+            [format!("{:?}", agent)],
+            |row| row_config(row, true),
+        )?;
+        let mut configs = self.query(
+            "SELECT block_number, digest, settings FROM configs WHERE agent = ?1 ORDER BY block_number, log_index",
+            [format!("{:?}", agent)],
+            |row| row_config(row, false),
+        )?;
+        if let (Some(active), Some(last)) = (active.into_iter().next(), configs.last_mut()) {
+            *last = active;
+        }
+        Ok(configs)
+    }
+
+    /// The config an agent had at the end of `block`.
+    pub fn agent_config_at(&self, agent_address: &str, block: u64) -> ApiResult<Option<AgentConfig>> {
+        let agent = parse_address(agent_address)?;
+        let configs = self.query(
+            "SELECT block_number, digest, settings FROM configs WHERE agent = ?1 AND block_number <= ?2
+             ORDER BY block_number DESC, log_index DESC LIMIT 1",
+            params![format!("{:?}", agent), block],
+            |row| row_config(row, false),
+        )?;
+        let removed = self.query(
+            "SELECT block_number FROM agents WHERE agent = ?1 AND event = 'removed' AND block_number <= ?2",
+            params![format!("{:?}", agent), block],
+            |row| row.get::<_, u64>(0),
+        )?;
+        Ok(configs.into_iter().next().map(|mut config| {
+            config.is_active = !removed.iter().any(|removed| *removed >= config.config_block_number as u64);
+            config
+        }))
+    }
+
+    /// Agents whose active config lists `signer_address`.
+    pub fn agents_by_signer(&self, signer_address: &str) -> ApiResult<Vec<Address>> {
+        let signer = parse_address(signer_address)?;
+        self.query(
+            "SELECT DISTINCT c.agent FROM active_configs c
+             JOIN config_signers s ON s.block_number = c.block_number AND s.log_index = c.log_index
+             WHERE s.signer = ?1 ORDER BY c.agent",
+            [format!("{:?}", signer)],
+            row_address,
+        )
+    }
+
+    /// Agents whose active config needs fewer than `threshold` signatures, with that config.
+    pub fn agents_with_threshold_below(&self, threshold: u8) -> ApiResult<Vec<(Address, AgentConfig)>> {
+        self.query(
+            "SELECT block_number, digest, settings, agent FROM active_configs WHERE threshold < ?1 ORDER BY agent",
+            [threshold],
+            |row| Ok((parse_column(row.get::<_, String>(3)?)?, row_config(row, true)?)),
+        )
+    }
+
+    /// Setting proposals that were neither accepted nor replaced, oldest first.
+    pub fn pending_proposals(&self) -> ApiResult<Vec<(Address, AgentSettings)>> {
+        self.query(
+            "SELECT agent, settings FROM proposals p
+             WHERE p.event = 'proposed'
+             AND NOT EXISTS (
+                 SELECT 1 FROM proposals n
+                 WHERE n.agent = p.agent AND (n.block_number, n.log_index) > (p.block_number, p.log_index)
+             ) AND NOT EXISTS (
+                 SELECT 1 FROM configs c
+                 WHERE c.agent = p.agent AND (c.block_number, c.log_index) > (p.block_number, p.log_index)
+             ) AND NOT EXISTS (
+                 SELECT 1 FROM agents r
+                 WHERE r.agent = p.agent AND r.event = 'removed' AND (r.block_number, r.log_index) > (p.block_number, p.log_index)
+             )
+             ORDER BY block_number, log_index",
+            [],
+            |row| Ok((parse_column(row.get::<_, String>(0)?)?, decode_settings(row.get(1)?)?)),
+        )
+    }
+
+    /// Registered agents that were not accepted (or removed) yet, like
+    /// `AgentManagerNode::registering_agents`.
+    pub fn registering_agents(&self) -> ApiResult<Vec<Address>> {
+        self.query(
+            "SELECT DISTINCT agent FROM agents a
+             WHERE a.event = 'registered'
+             AND NOT EXISTS (
+                 SELECT 1 FROM configs c
+                 WHERE c.agent = a.agent AND (c.block_number, c.log_index) > (a.block_number, a.log_index)
+             ) AND NOT EXISTS (
+                 SELECT 1 FROM agents r
+                 WHERE r.agent = a.agent AND r.event = 'removed' AND (r.block_number, r.log_index) > (a.block_number, a.log_index)
+             )
+             ORDER BY agent",
+            [],
+            row_address,
+        )
+    }
+
+    fn query<T>(&self, sql: &str, params: impl Params, map: impl FnMut(&Row) -> rusqlite::Result<T>) -> ApiResult<Vec<T>> {
+        let db = self.db.lock().unwrap();
+        let mut statement = db.prepare(sql).map_err(db_error)?;
+        let rows = statement.query_map(params, map).map_err(db_error)?;
+        rows.collect::<Result<_, _>>().map_err(db_error)
+    }
+
+    async fn block_hash(&self, number: u64) -> ApiResult<Option<H256>> {
+        let block = self.provider.get_block(number).await
+            .map_err(|e| format!("Failed to get block {}: {}", number, e))?;
+        Ok(block.and_then(|block| block.hash))
+    }
+
+    /// Compares the stored block hashes with the chain, newest first, and deletes
+    /// everything after the newest block that still matches.
+    async fn undo_reorg(&self) -> ApiResult<Option<u64>> {
+        let stored: Vec<(u64, String)> = {
+            let db = self.db.lock().unwrap();
+            let mut statement = db.prepare("SELECT number, hash FROM blocks ORDER BY number DESC").map_err(db_error)?;
+            let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).map_err(db_error)?;
+            rows.collect::<Result<_, _>>().map_err(db_error)?
+        };
+
+        if stored.is_empty() {
+            return Ok(None);
+        }
+
+        let mut fork = None;
+        for (i, (number, hash)) in stored.iter().enumerate() {
+            let canonical = self.block_hash(*number).await?.map(|hash| format!("{:?}", hash));
+            if canonical.as_ref() == Some(hash) {
+                if i == 0 {
+                    return Ok(None);
+                }
+                fork = Some(*number);
+                break;
+            }
+        }
+
+        tracing::warn!(fork, "Reorg found, undoing indexed blocks");
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction().map_err(db_error)?;
+        rollback(&tx, fork)?;
+        tx.commit().map_err(db_error)?;
+        Ok(Some(fork.unwrap_or(self.start_block.saturating_sub(1))))
+    }
+
+    fn index_log(&self, tx: &Transaction, log: &Log) -> ApiResult<usize> {
+        if log.removed == Some(true) {
+            return Ok(0);
+        }
+        let block = log.block_number.ok_or("Log without a block number")?.as_u64();
+        let index = log.log_index.ok_or("Log without a log index")?.as_u64();
+        let raw = RawLog::from(log.clone());
+
+        if log.address == self.factory {
+            let Ok(event) = <AgentCreatedFilter as EthLogDecode>::decode_log(&raw) else {
+                return Ok(0);
+            };
+            insert_agent(tx, block, index, event.agent, "created", Some(event.owner), Some(event.agent_id))?;
+            return Ok(1);
+        }
+
+        if log.address == self.proxy {
+            return match AgentProxyContractEvents::decode_log(&raw) {
+                Ok(AgentProxyContractEvents::OwnershipTransferRequestedFilter(e)) => {
+                    insert_ownership(tx, block, index, self.proxy, "requested", e.from, e.to)
+                }
+                Ok(AgentProxyContractEvents::OwnershipTransferredFilter(e)) => {
+                    insert_ownership(tx, block, index, self.proxy, "transferred", e.from, e.to)
+                }
+                _ => Ok(0),
+            };
+        }
+
+        if log.address != self.manager {
+            return Ok(0);
+        }
+        match AgentManagerContractEvents::decode_log(&raw) {
+            Ok(AgentManagerContractEvents::AgentRegisteredFilter(e)) => {
+                insert_agent(tx, block, index, e.agent, "registered", None, None)?;
+                insert_proposal(tx, block, index, e.agent, "registered", &e.agent_settings)
+            }
+            Ok(AgentManagerContractEvents::AgentSettingsProposedFilter(e)) => {
+                insert_proposal(tx, block, index, e.agent, "proposed", &e.agent_settings)
+            }
+            Ok(AgentManagerContractEvents::AgentAcceptedFilter(e)) => {
+                insert_config(tx, block, index, e.agent, e.digest, "accepted", &e.agent_settings)
+            }
+            Ok(AgentManagerContractEvents::AgentSettingsUpdatedFilter(e)) => {
+                insert_config(tx, block, index, e.agent, e.digest, "updated", &e.agent_settings)
+            }
+            Ok(AgentManagerContractEvents::AgentRemovedFilter(e)) => {
+                insert_agent(tx, block, index, e.agent, "removed", None, None)
+            }
+            Ok(AgentManagerContractEvents::OwnershipTransferRequestedFilter(e)) => {
+                insert_ownership(tx, block, index, self.manager, "requested", e.from, e.to)
+            }
+            Ok(AgentManagerContractEvents::OwnershipTransferredFilter(e)) => {
+                insert_ownership(tx, block, index, self.manager, "transferred", e.from, e.to)
+            }
+            Ok(AgentManagerContractEvents::AgentProxySetFilter(_)) | Err(_) => Ok(0),
+        }
+    }
+}
+
+fn db_error(e: rusqlite::Error) -> String {
+    format!("Index database error: {}", e)
+}
+
+/// Parses a column written by the indexer; a failure means the database was edited.
+fn parse_column<T: std::str::FromStr>(value: String) -> rusqlite::Result<T> {
+    value.parse().map_err(|_| rusqlite::Error::InvalidColumnType(0, value, rusqlite::types::Type::Text))
+}
+
+fn decode_settings(settings: Vec<u8>) -> rusqlite::Result<AgentSettings> {
+    AgentSettings::decode(settings).map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Blob, Box::new(e)))
+}
+
+fn row_address(row: &Row) -> rusqlite::Result<Address> {
+    parse_column(row.get(0)?)
+}
+
+/// Reads `block_number, digest, settings` columns.
+fn row_config(row: &Row, is_active: bool) -> rusqlite::Result<AgentConfig> {
+    let digest: H256 = parse_column(row.get(1)?)?;
+    Ok(AgentConfig {
+        config_digest: digest.0,
+        config_block_number: row.get(0)?,
+        is_active,
+        settings: decode_settings(row.get(2)?)?,
+    })
+}
+
+fn read_meta(db: &Connection, key: &str) -> ApiResult<Option<String>> {
+    db.query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| row.get(0))
+        .optional()
+        .map_err(db_error)
+}
+
+fn write_meta(db: &Connection, key: &str, value: &str) -> ApiResult<()> {
+    db.execute("INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)", [key, value])
+        .map(|_| ())
+        .map_err(db_error)
+}
+
+/// Deletes everything after `block`, or everything when `block` is `None`.
+fn rollback(tx: &Transaction, block: Option<u64>) -> ApiResult<()> {
+    for table in EVENT_TABLES.iter().chain(&["blocks"]) {
+        let column = if *table == "blocks" { "number" } else { "block_number" };
+        match block {
+            Some(block) => tx.execute(&format!("DELETE FROM {} WHERE {} > ?1", table, column), [block]),
+            None => tx.execute(&format!("DELETE FROM {}", table), []),
+        }
+        .map_err(db_error)?;
+    }
+    match block {
+        Some(block) => write_meta(tx, "last_block", &block.to_string()),
+        None => tx.execute("DELETE FROM meta WHERE key = 'last_block'", []).map(|_| ()).map_err(db_error),
+    }
+}
+
+fn insert_agent(
+    tx: &Transaction,
+    block: u64,
+    index: u64,
+    agent: Address,
+    event: &str,
+    owner: Option<Address>,
+    agent_id: Option<U256>,
+) -> ApiResult<usize> {
+    tx.execute(
+        "INSERT OR REPLACE INTO agents (block_number, log_index, agent, event, owner, agent_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![block, index, format!("{:?}", agent), event, owner.map(|owner| format!("{:?}", owner)), agent_id.map(|id| id.to_string())],
+    )
+    .map_err(db_error)
+}
+
+fn insert_proposal(
+    tx: &Transaction,
+    block: u64,
+    index: u64,
+    agent: Address,
+    event: &str,
+    settings: &AgentSettings,
+) -> ApiResult<usize> {
+    tx.execute(
+        "INSERT OR REPLACE INTO proposals (block_number, log_index, agent, event, threshold, settings) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![block, index, format!("{:?}", agent), event, settings.threshold, settings.clone().encode()],
+    )
+    .map_err(db_error)
+}
+
+fn insert_config(
+    tx: &Transaction,
+    block: u64,
+    index: u64,
+    agent: Address,
+    digest: [u8; 32],
+    event: &str,
+    settings: &AgentSettings,
+) -> ApiResult<usize> {
+    tx.execute(
+        "INSERT OR REPLACE INTO configs (block_number, log_index, agent, digest, event, threshold, settings) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![block, index, format!("{:?}", agent), format!("{:?}", H256::from(digest)), event, settings.threshold, settings.clone().encode()],
+    )
+    .map_err(db_error)?;
+    for signer in &settings.signers {
+        tx.execute(
+            "INSERT OR REPLACE INTO config_signers (block_number, log_index, signer) VALUES (?1, ?2, ?3)",
+            params![block, index, format!("{:?}", signer)],
+        )
+        .map_err(db_error)?;
+    }
+    Ok(1)
+}
+
+fn insert_ownership(
+    tx: &Transaction,
+    block: u64,
+    index: u64,
+    contract: Address,
+    event: &str,
+    from: Address,
+    to: Address,
+) -> ApiResult<usize> {
+    tx.execute(
+        "INSERT OR REPLACE INTO ownership (block_number, log_index, contract, event, from_address, to_address) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![block, index, format!("{:?}", contract), event, format!("{:?}", from), format!("{:?}", to)],
+    )
+    .map_err(db_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    use ethers::abi::Tokenizable;
+    use ethers::contract::EthEvent;
+    use serde_json::{json, Value};
+
+    use crate::svc::agent_manager_node::{AgentAcceptedFilter, AgentHeader, AgentRegisteredFilter};
+    use crate::utils::mock_server::{MockRequest, MockResponse, MockServer};
+
+    #[derive(Default)]
+    struct Chain {
+        hashes: BTreeMap<u64, H256>,
+        logs: Vec<Log>,
+    }
+
+    impl Chain {
+        fn mine(&mut self, to: u64, fork: u8) {
+            let next = self.hashes.keys().last().map_or(0, |block| block + 1);
+            for number in next..=to {
+                let mut hash = H256::from_low_u64_be(number);
+                hash.0[0] = fork;
+                self.hashes.insert(number, hash);
+            }
+        }
+
+        fn reorg(&mut self, after: u64, to: u64) {
+            self.hashes.retain(|number, _| *number <= after);
+            self.logs.retain(|log| log.block_number.unwrap().as_u64() <= after);
+            self.mine(to, 1);
+        }
+
+        fn emit(&mut self, address: Address, block: u64, topics: Vec<H256>, data: Vec<u8>) {
+            self.logs.push(Log {
+                address,
+                topics,
+                data: data.into(),
+                block_number: Some(block.into()),
+                block_hash: Some(self.hashes[&block]),
+                log_index: Some(U256::from(self.logs.len())),
+                ..Default::default()
+            });
+        }
+
+        fn respond(&self, method: &str, params: &Value) -> Value {
+            let number = |value: &Value| u64::from_str_radix(value.as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+            match method {
+                "eth_blockNumber" => json!(U64::from(*self.hashes.keys().last().unwrap())),
+                "eth_getBlockByNumber" => {
+                    let number = number(&params[0]);
+                    self.hashes.get(&number).map_or(Value::Null, |hash| {
+                        let block = Block::<H256> { hash: Some(*hash), number: Some(number.into()), ..Default::default() };
+                        serde_json::to_value(block).unwrap()
+                    })
+                }
+                "eth_getLogs" => {
+                    let (from, to) = (number(&params[0]["fromBlock"]), number(&params[0]["toBlock"]));
+                    let logs: Vec<&Log> = self.logs.iter()
+                        .filter(|log| (from..=to).contains(&log.block_number.unwrap().as_u64()))
+                        .collect();
+                    json!(logs)
+                }
+                _ => Value::Null,
+            }
+        }
+    }
+
+    fn settings(threshold: u8) -> AgentSettings {
+        AgentSettings {
+            signers: vec![Address::repeat_byte(0x11), Address::repeat_byte(0x22)],
+            threshold,
+            converter_address: Address::zero(),
+            agent_header: AgentHeader {
+                version: "1.0".to_string(),
+                message_id: "m".to_string(),
+                source_agent_id: "s".to_string(),
+                source_agent_name: "Source".to_string(),
+                target_agent_id: "t".to_string(),
+                timestamp: U256::from(1_700_000_000u64),
+                message_type: 0,
+                priority: 1,
+                ttl: U256::from(3600),
+            },
+        }
+    }
+
+    fn count(indexer: &Indexer, table: &str) -> u64 {
+        let db = indexer.db.lock().unwrap();
+        db.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sync_resume_and_reorg() {
+        let (proxy, manager, factory) = (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+        let agent = Address::repeat_byte(0xaa);
+        let chain = Arc::new(Mutex::new(Chain::default()));
+        {
+            let mut chain = chain.lock().unwrap();
+            chain.mine(10, 0);
+            let data = ethers::abi::encode(&[manager.into_token(), U256::from(7).into_token()]);
+            chain.emit(factory, 3, vec![AgentCreatedFilter::signature(), H256::from(Address::repeat_byte(9)), H256::from(agent)], data);
+            let data = ethers::abi::encode(&[settings(2).into_token()]);
+            chain.emit(manager, 4, vec![AgentRegisteredFilter::signature(), H256::from(agent)], data.clone());
+            chain.emit(manager, 6, vec![AgentAcceptedFilter::signature(), H256::from(agent), H256::repeat_byte(0xdd)], data);
+        }
+
+        let state = chain.clone();
+        let server = MockServer::start(Arc::new(move |request: MockRequest| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let result = state.lock().unwrap().respond(body["method"].as_str().unwrap_or_default(), &body["params"]);
+            let response = json!({ "jsonrpc": "2.0", "id": body["id"], "result": result });
+            MockResponse::new(200, "application/json", response.to_string())
+        }))
+        .await
+        .unwrap();
+
+        let provider = Provider::new(AttpsTransport::from_urls(&server.url()).unwrap());
+        let indexer = Indexer::with_connection(Connection::open_in_memory().unwrap(), provider, proxy, manager, factory, 2).unwrap();
+        let report = indexer.sync().await.unwrap();
+        assert_eq!(report, SyncReport { from_block: 2, to_block: 10, events: 3, reorged_after: None });
+        assert_eq!((count(&indexer, "agents"), count(&indexer, "proposals"), count(&indexer, "configs")), (2, 1, 1));
+        assert_eq!(count(&indexer, "config_signers"), 2);
+
+        // resumes after the last indexed block
+        chain.lock().unwrap().mine(12, 0);
+        let report = indexer.sync().await.unwrap();
+        assert_eq!((report.from_block, report.events), (11, 0));
+
+        // blocks after 5 are replaced and the acceptance at block 6 is gone; block 4 is
+        // the newest indexed block still on the chain
+        chain.lock().unwrap().reorg(5, 14);
+        let report = indexer.sync().await.unwrap();
+        assert_eq!((report.reorged_after, report.from_block, report.to_block), (Some(4), 5, 14));
+        assert_eq!((count(&indexer, "agents"), count(&indexer, "proposals"), count(&indexer, "configs")), (2, 1, 0));
+        assert_eq!(indexer.last_block().unwrap(), Some(14));
+
+        let report = indexer.rebuild().await.unwrap();
+        assert_eq!((report.from_block, report.events), (2, 2));
+    }
+
+    #[test]
+    fn test_registry_queries() {
+        let provider = Provider::new(AttpsTransport::from_urls("http://127.0.0.1:1").unwrap());
+        let (a, b, c) = (Address::repeat_byte(0xa), Address::repeat_byte(0xb), Address::repeat_byte(0xc));
+        let indexer = Indexer::with_connection(Connection::open_in_memory().unwrap(), provider, Address::zero(), Address::zero(), Address::zero(), 0).unwrap();
+        {
+            let mut db = indexer.db.lock().unwrap();
+            let tx = db.transaction().unwrap();
+            // a: accepted with threshold 2, then updated to 1, then a new proposal
+            insert_agent(&tx, 1, 0, a, "registered", None, None).unwrap();
+            insert_config(&tx, 2, 0, a, [1; 32], "accepted", &settings(2)).unwrap();
+            insert_config(&tx, 5, 0, a, [2; 32], "updated", &settings(1)).unwrap();
+            insert_proposal(&tx, 6, 0, a, "proposed", &settings(3)).unwrap();
+            // b: registered only; c: accepted and removed
+            insert_agent(&tx, 3, 0, b, "registered", None, None).unwrap();
+            insert_config(&tx, 3, 1, c, [3; 32], "accepted", &settings(1)).unwrap();
+            insert_agent(&tx, 4, 0, c, "removed", None, None).unwrap();
+            tx.commit().unwrap();
+        }
+
+        let configs = indexer.agent_configs(&format!("{:?}", a)).unwrap();
+        assert_eq!(configs.iter().map(|config| (config.config_block_number, config.is_active)).collect::<Vec<_>>(), vec![(2, false), (5, true)]);
+        assert_eq!(configs[1].settings, settings(1));
+        assert_eq!(indexer.agent_config_at(&format!("{:?}", a), 4).unwrap().unwrap().config_digest, [1; 32]);
+        assert!(!indexer.agent_configs(&format!("{:?}", c)).unwrap()[0].is_active);
+
+        let signer = format!("{:?}", Address::repeat_byte(0x11));
+        assert_eq!(indexer.agents_by_signer(&signer).unwrap(), vec![a]);
+        let below = indexer.agents_with_threshold_below(2).unwrap();
+        assert_eq!(below.iter().map(|(agent, config)| (*agent, config.config_digest)).collect::<Vec<_>>(), vec![(a, [2; 32])]);
+        assert_eq!(indexer.pending_proposals().unwrap(), vec![(a, settings(3))]);
+        assert_eq!(indexer.registering_agents().unwrap(), vec![b]);
+        assert!(indexer.agents_by_signer("0x1234").unwrap_err().starts_with("Invalid address format: "));
+    }
+}
@@ -0,0 +1,406 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ethers::types::{Address, H256, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::core::ApiResult;
+use crate::svc::agent_manager_node::{AgentConfig, AgentHeader, AgentManagerNode, AgentSettings};
+
+/// Desired settings for a set of agents, kept in version control as YAML or TOML.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentManifest {
+    pub agents: Vec<ManifestAgent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestAgent {
+    pub agent: Address,
+    pub signers: Vec<Address>,
+    pub threshold: u8,
+    #[serde(default)]
+    pub converter: Address,
+    pub header: ManifestHeader,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestHeader {
+    pub version: String,
+    pub message_id: String,
+    pub source_agent_id: String,
+    pub source_agent_name: String,
+    pub target_agent_id: String,
+    /// Not compared when omitted; the time of `apply` is sent instead.
+    #[serde(default)]
+    pub timestamp: Option<u64>,
+    #[serde(default)]
+    pub message_type: u8,
+    #[serde(default)]
+    pub priority: u8,
+    pub ttl: u64,
+}
+
+impl AgentManifest {
+    /// Reads a `.yaml`/`.yml` or `.toml` manifest.
+    pub fn from_file(path: impl AsRef<Path>) -> ApiResult<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read manifest {}: {}", path.display(), e))?;
+
+        let manifest = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml_str(&contents),
+            _ => Self::from_yaml_str(&contents),
+        };
+        manifest.map_err(|e| format!("Invalid manifest {}: {}", path.display(), e))
+    }
+
+    pub fn from_yaml_str(yaml: &str) -> ApiResult<Self> {
+        let manifest: Self = serde_yaml::from_str(yaml).map_err(|e| e.to_string())?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    pub fn from_toml_str(toml: &str) -> ApiResult<Self> {
+        let manifest: Self = toml::from_str(toml).map_err(|e| e.to_string())?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    pub fn validate(&self) -> ApiResult<()> {
+        let mut seen = HashSet::new();
+        for agent in &self.agents {
+            if !seen.insert(agent.agent) {
+                return Err(format!("Agent {:?} is listed more than once", agent.agent));
+            }
+            if agent.threshold == 0 || usize::from(agent.threshold) > agent.signers.len() {
+                return Err(format!(
+                    "Agent {:?}: threshold {} needs between 1 and {} signers",
+                    agent.agent,
+                    agent.threshold,
+                    agent.signers.len()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Compares every agent with its latest on-chain config.
+    pub async fn plan(&self, manager: &AgentManagerNode) -> ApiResult<Plan> {
+        let registering = manager.registering_agents().await?;
+        let mut changes = Vec::with_capacity(self.agents.len());
+        for agent in &self.agents {
+            let configs = manager.agent_configs(&format!("{:?}", agent.agent)).await?;
+            changes.push(plan_agent(agent, &configs, registering.contains(&agent.agent)));
+        }
+        Ok(Plan { changes })
+    }
+}
+
+impl ManifestAgent {
+    fn settings(&self) -> AgentSettings {
+        let timestamp = self.header.timestamp.unwrap_or_else(|| {
+            SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or_default()
+        });
+        AgentSettings {
+            signers: self.signers.clone(),
+            threshold: self.threshold,
+            converter_address: self.converter,
+            agent_header: AgentHeader {
+                version: self.header.version.clone(),
+                message_id: self.header.message_id.clone(),
+                source_agent_id: self.header.source_agent_id.clone(),
+                source_agent_name: self.header.source_agent_name.clone(),
+                target_agent_id: self.header.target_agent_id.clone(),
+                timestamp: U256::from(timestamp),
+                message_type: self.header.message_type,
+                priority: self.header.priority,
+                ttl: U256::from(self.header.ttl),
+            },
+        }
+    }
+
+    /// Field-by-field differences from `current`, empty when they match.
+    fn diff(&self, current: &AgentSettings) -> Vec<String> {
+        diff_settings(current, &self.settings(), self.header.timestamp.is_some())
+    }
+}
+
+/// Field-by-field differences between two settings, empty when they match. Signer order
+/// does not matter.
+pub(crate) fn diff_settings(current: &AgentSettings, desired: &AgentSettings, compare_timestamp: bool) -> Vec<String> {
+    let mut diff = Vec::new();
+
+    let wanted: BTreeSet<Address> = desired.signers.iter().copied().collect();
+    let actual: BTreeSet<Address> = current.signers.iter().copied().collect();
+    for added in wanted.difference(&actual) {
+        diff.push(format!("signers: + {:?}", added));
+    }
+    for removed in actual.difference(&wanted) {
+        diff.push(format!("signers: - {:?}", removed));
+    }
+
+    let (header, desired_header) = (&current.agent_header, &desired.agent_header);
+    let mut field = |name: &str, actual: String, desired: String| {
+        if actual != desired {
+            diff.push(format!("{}: {} -> {}", name, actual, desired));
+        }
+    };
+    field("threshold", current.threshold.to_string(), desired.threshold.to_string());
+    field("converter", format!("{:?}", current.converter_address), format!("{:?}", desired.converter_address));
+    field("header.version", format!("{:?}", header.version), format!("{:?}", desired_header.version));
+    field("header.message_id", format!("{:?}", header.message_id), format!("{:?}", desired_header.message_id));
+    field("header.source_agent_id", format!("{:?}", header.source_agent_id), format!("{:?}", desired_header.source_agent_id));
+    field("header.source_agent_name", format!("{:?}", header.source_agent_name), format!("{:?}", desired_header.source_agent_name));
+    field("header.target_agent_id", format!("{:?}", header.target_agent_id), format!("{:?}", desired_header.target_agent_id));
+    if compare_timestamp {
+        field("header.timestamp", header.timestamp.to_string(), desired_header.timestamp.to_string());
+    }
+    field("header.message_type", header.message_type.to_string(), desired_header.message_type.to_string());
+    field("header.priority", header.priority.to_string(), desired_header.priority.to_string());
+    field("header.ttl", header.ttl.to_string(), desired_header.ttl.to_string());
+    diff
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanAction {
+    /// The latest config is active and matches the manifest.
+    NoChange,
+    /// The agent has no config yet: `register_agent`.
+    Register,
+    /// The latest config matches but is not active yet: `accept_agent_setting_proposal`.
+    AcceptProposal,
+    /// The agent is registering with matching settings: `accept_agent`.
+    AcceptAgent,
+    /// The agent is registering with other settings: `remove_agent`, then `register_agent`.
+    Reregister,
+    /// `change_agent_setting_proposal`, then `accept_agent_setting_proposal`.
+    ChangeSettings,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedChange {
+    pub agent: Address,
+    pub action: PlanAction,
+    /// Digest of the latest config at plan time; `apply` refuses to act if it moved.
+    pub current_digest: Option<H256>,
+    pub diff: Vec<String>,
+    pub desired: ManifestAgent,
+}
+
+/// The result of comparing a manifest with the chain. It can be saved as JSON,
+/// reviewed, and applied later.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Plan {
+    pub changes: Vec<PlannedChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppliedChange {
+    pub agent: Address,
+    pub action: PlanAction,
+    /// Transaction receipts, in order.
+    pub receipts: Vec<String>,
+}
+
+impl Plan {
+    pub fn has_changes(&self) -> bool {
+        self.changes.iter().any(|change| change.action != PlanAction::NoChange)
+    }
+
+    /// Sends the planned transactions, agent by agent. Stops at the first failure;
+    /// changes applied before it stay applied, and a new plan picks up from there.
+    pub async fn apply(&self, manager: &AgentManagerNode) -> ApiResult<Vec<AppliedChange>> {
+        let mut applied = Vec::new();
+        for change in self.changes.iter().filter(|change| change.action != PlanAction::NoChange) {
+            let agent = format!("{:?}", change.agent);
+            let latest = latest_config(&manager.agent_configs(&agent).await?).map(|config| H256::from(config.config_digest));
+            if latest != change.current_digest {
+                return Err(format!("Agent {} changed on chain since the plan was made; plan again", agent));
+            }
+
+            let receipts = apply_change(manager, change).await.map_err(|e| {
+                format!("Failed to apply {:?} for agent {}: {} ({} changes applied before)", change.action, agent, e, applied.len())
+            })?;
+            applied.push(AppliedChange { agent: change.agent, action: change.action, receipts });
+        }
+        Ok(applied)
+    }
+}
+
+async fn apply_change(manager: &AgentManagerNode, change: &PlannedChange) -> ApiResult<Vec<String>> {
+    let agent = format!("{:?}", change.agent);
+    let settings = change.desired.settings();
+    let signers: Vec<String> = settings.signers.iter().map(|signer| format!("{:?}", signer)).collect();
+    let signers: Vec<&str> = signers.iter().map(String::as_str).collect();
+    let converter = format!("{:?}", settings.converter_address);
+    let header = &settings.agent_header;
+
+    let register = || {
+        manager.register_agent(
+            &agent,
+            signers.clone(),
+            settings.threshold,
+            &converter,
+            &header.version,
+            &header.message_id,
+            &header.source_agent_id,
+            &header.source_agent_name,
+            &header.target_agent_id,
+            header.timestamp.as_u64(),
+            header.message_type,
+            header.priority,
+            header.ttl.as_u64(),
+        )
+    };
+
+    match change.action {
+        PlanAction::NoChange => Ok(Vec::new()),
+        PlanAction::Register => Ok(vec![register().await?]),
+        PlanAction::AcceptProposal => Ok(vec![manager.accept_agent_setting_proposal(&agent).await?]),
+        PlanAction::AcceptAgent => Ok(vec![manager.accept_agent(&agent).await?]),
+        PlanAction::Reregister => {
+            let removed = manager.remove_agent(&agent).await?;
+            let registered = register().await?;
+            Ok(vec![removed, registered])
+        }
+        PlanAction::ChangeSettings => {
+            let proposed = manager
+                .change_agent_setting_proposal(
+                    &agent,
+                    signers,
+                    settings.threshold,
+                    &converter,
+                    &header.version,
+                    &header.message_id,
+                    &header.source_agent_id,
+                    &header.source_agent_name,
+                    &header.target_agent_id,
+                    header.timestamp.as_u64(),
+                    header.message_type,
+                    header.priority,
+                    header.ttl.as_u64(),
+                )
+                .await?;
+            let accepted = manager.accept_agent_setting_proposal(&agent).await?;
+            Ok(vec![proposed, accepted])
+        }
+    }
+}
+
+fn latest_config(configs: &[AgentConfig]) -> Option<&AgentConfig> {
+    // max_by_key returns the last of equal keys, so later entries win within a block
+    configs.iter().max_by_key(|config| config.config_block_number)
+}
+
+/// `registering` is whether the agent was registered but never accepted; its configs
+/// are then inactive, and only `accept_agent` can activate them.
+fn plan_agent(desired: &ManifestAgent, configs: &[AgentConfig], registering: bool) -> PlannedChange {
+    let latest = latest_config(configs);
+    let (action, diff) = match latest {
+        None => (PlanAction::Register, Vec::new()),
+        Some(config) => {
+            let diff = desired.diff(&config.settings);
+            let action = match (diff.is_empty(), registering, config.is_active) {
+                (true, true, _) => PlanAction::AcceptAgent,
+                (false, true, _) => PlanAction::Reregister,
+                (true, false, true) => PlanAction::NoChange,
+                (true, false, false) => PlanAction::AcceptProposal,
+                (false, false, _) => PlanAction::ChangeSettings,
+            };
+            (action, diff)
+        }
+    };
+    PlannedChange {
+        agent: desired.agent,
+        action,
+        current_digest: latest.map(|config| H256::from(config.config_digest)),
+        diff,
+        desired: desired.clone(),
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            let (symbol, action) = match change.action {
+                PlanAction::NoChange => ("=", "unchanged"),
+                PlanAction::Register => ("+", "register"),
+                PlanAction::AcceptProposal => ("~", "accept pending proposal"),
+                PlanAction::AcceptAgent => ("+", "accept registration"),
+                PlanAction::Reregister => ("~", "remove registration and register again"),
+                PlanAction::ChangeSettings => ("~", "change settings"),
+            };
+            writeln!(f, "{} {:?}: {}", symbol, change.agent, action)?;
+            for line in &change.diff {
+                writeln!(f, "    {}", line)?;
+            }
+        }
+        let pending = self.changes.iter().filter(|change| change.action != PlanAction::NoChange).count();
+        write!(f, "{} of {} agents to change", pending, self.changes.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+agents:
+  - agent: "0x0000000000000000000000000000000000000001"
+    signers:
+      - "0x00000000000000000000000000000000000000a1"
+      - "0x00000000000000000000000000000000000000a2"
+    threshold: 2
+    header:
+      version: "1.0"
+      message_id: m
+      source_agent_id: s
+      source_agent_name: SourceAgent
+      target_agent_id: t
+      priority: 1
+      ttl: 3600
+"#;
+
+    #[test]
+    fn test_plan_against_configs() {
+        let manifest = AgentManifest::from_yaml_str(MANIFEST).unwrap();
+        let desired = &manifest.agents[0];
+        assert_eq!(plan_agent(desired, &[], false).action, PlanAction::Register);
+
+        let mut config = AgentConfig {
+            config_digest: [7; 32],
+            config_block_number: 10,
+            is_active: true,
+            settings: desired.settings(),
+        };
+        // a different timestamp does not count when the manifest leaves it out
+        config.settings.agent_header.timestamp = U256::from(1);
+        config.settings.signers.reverse();
+        assert_eq!(plan_agent(desired, std::slice::from_ref(&config), false).action, PlanAction::NoChange);
+
+        let mut pending = config.clone();
+        pending.is_active = false;
+        pending.config_block_number = 11;
+        assert_eq!(plan_agent(desired, &[config.clone(), pending.clone()], false).action, PlanAction::AcceptProposal);
+        // an agent that was never accepted has only inactive configs
+        assert_eq!(plan_agent(desired, std::slice::from_ref(&pending), true).action, PlanAction::AcceptAgent);
+        pending.settings.threshold = 1;
+        assert_eq!(plan_agent(desired, &[pending], true).action, PlanAction::Reregister);
+
+        config.settings.threshold = 1;
+        config.settings.signers.pop();
+        let change = plan_agent(desired, &[config], false);
+        assert_eq!(change.action, PlanAction::ChangeSettings);
+        assert_eq!(change.current_digest, Some(H256::from([7; 32])));
+        assert_eq!(change.diff, vec![
+            "signers: + 0x00000000000000000000000000000000000000a1".to_string(),
+            "threshold: 1 -> 2".to_string(),
+        ]);
+
+        let err = AgentManifest::from_yaml_str(&MANIFEST.replace("threshold: 2", "threshold: 3")).unwrap_err();
+        assert!(err.contains("threshold 3 needs between 1 and 2 signers"), "{}", err);
+    }
+}
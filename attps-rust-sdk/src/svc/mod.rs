@@ -1,3 +1,15 @@
 pub mod agent_proxy_node;
 pub mod agent_manager_node;
-pub mod agent_factory_node;
\ No newline at end of file
+pub mod agent_factory_node;
+pub mod client;
+pub mod verify_batch;
+pub mod manifest;
+pub mod onboard;
+pub mod ownership;
+pub mod proposals;
+pub mod review;
+pub mod webhooks;
+#[cfg(feature = "gateway")]
+pub mod gateway;
+#[cfg(feature = "indexer")]
+pub mod indexer;
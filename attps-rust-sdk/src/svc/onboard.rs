@@ -0,0 +1,608 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use ethers::abi::{Detokenize, Tokenizable};
+use ethers::contract::{ContractCall, EthEvent, EthLogDecode};
+use ethers::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::core::events::get_logs_in_chunks;
+use crate::core::metrics;
+use crate::core::{ApiResult, SignerClient};
+use crate::svc::agent_factory_node::AgentCreatedFilter;
+use crate::svc::agent_manager_node::{settings_json, AgentAcceptedFilter, AgentSettings};
+use crate::svc::client::AttpsClient;
+use crate::utils::common::open_journal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardStep {
+    /// AgentProxy `create_and_register_agent`, for a new agent.
+    Create,
+    /// AgentManager `register_agent`, for an agent created beforehand.
+    Register,
+    /// AgentManager `accept_agent`.
+    Accept,
+    /// The AgentAccepted event is on chain.
+    Confirm,
+}
+
+impl OnboardStep {
+    fn contract_method(&self) -> (&'static str, &'static str) {
+        match self {
+            OnboardStep::Create => ("AgentProxy", "create_and_register_agent"),
+            OnboardStep::Register => ("AgentManager", "register_agent"),
+            // Confirm sends nothing
+            OnboardStep::Accept | OnboardStep::Confirm => ("AgentManager", "accept_agent"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OnboardReport {
+    pub agent: Address,
+    /// Digest of the accepted config.
+    pub settings_digest: H256,
+    pub accepted_block: u64,
+    /// Transactions mined for this onboarding, including ones sent before a restart.
+    pub transactions: Vec<(OnboardStep, H256)>,
+    /// Steps found already done in the journal or on chain.
+    pub skipped: Vec<OnboardStep>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum JournalEntry {
+    Started { settings: Value, agent: Option<Address> },
+    Sent { step: OnboardStep, tx_hash: H256 },
+    Mined { step: OnboardStep, tx_hash: H256 },
+    /// The sent transaction reverted or vanished; the step is checked and sent again.
+    Cleared { step: OnboardStep, tx_hash: H256 },
+    Created { agent: Address },
+    Confirmed { agent: Address, settings_digest: H256, block_number: u64 },
+}
+
+#[derive(Debug, Default)]
+struct JournalState {
+    started: Option<(Value, Option<Address>)>,
+    sent: HashMap<OnboardStep, H256>,
+    mined: Vec<(OnboardStep, H256)>,
+    created: Option<Address>,
+    confirmed: Option<(Address, H256, u64)>,
+}
+
+/// Takes an agent from settings to accepted: create (or register an agent created
+/// beforehand), accept, and confirm the AgentAccepted event.
+///
+/// Every transaction is journaled once signed and before it is broadcast, and every
+/// finished step after it, so calling `onboard_agent` again after a failure picks up where
+/// the last call stopped instead of creating a second agent. A journaled
+/// transaction that reverted or is no longer known to the node is cleared, and its step
+/// is checked on chain and sent again.
+pub struct Onboarding<'a> {
+    client: &'a AttpsClient,
+    journal_path: Option<PathBuf>,
+}
+
+impl<'a> Onboarding<'a> {
+    pub fn new(client: &'a AttpsClient, journal_path: Option<&str>) -> Self {
+        Self { client, journal_path: journal_path.map(PathBuf::from) }
+    }
+
+    /// Onboards a new agent with `settings`, or registers `agent` when it was already
+    /// created through the AgentFactory.
+    #[tracing::instrument(name = "onboard_agent", skip_all, fields(agent = tracing::field::Empty))]
+    pub async fn onboard_agent(&self, settings: &AgentSettings, agent: Option<Address>) -> ApiResult<OnboardReport> {
+        let mut state = match &self.journal_path {
+            Some(path) => read_journal(path)?,
+            None => JournalState::default(),
+        };
+        let mut journal = match &self.journal_path {
+            Some(path) => Some(open_journal(path).map_err(|e| format!("Failed to open onboarding journal: {}", e))?),
+            None => None,
+        };
+
+        let started = (settings_json(settings), agent);
+        match &state.started {
+            Some(journaled) if *journaled != started => {
+                return Err("The onboarding journal is for different settings or a different agent".to_string());
+            }
+            Some(_) => {}
+            None => write_journal(&mut journal, &JournalEntry::Started { settings: started.0.clone(), agent })?,
+        }
+
+        let mut skipped = Vec::new();
+        if let Some((agent, settings_digest, accepted_block)) = state.confirmed {
+            return Ok(OnboardReport {
+                agent,
+                settings_digest,
+                accepted_block,
+                transactions: state.mined,
+                skipped: vec![OnboardStep::Confirm],
+            });
+        }
+
+        let manager = self.client.manager();
+        let agent = match (state.created, agent) {
+            (Some(created), _) => {
+                skipped.push(OnboardStep::Create);
+                created
+            }
+            (None, None) => {
+                let receipt = match self.resume(OnboardStep::Create, &mut state, &mut journal).await? {
+                    Some(receipt) => receipt,
+                    None => {
+                        // the proxy bindings have their own copy of the settings struct
+                        let proxy_settings = Tokenizable::from_token(settings.clone().into_token())
+                            .map_err(|e| format!("Invalid agent settings: {}", e))?;
+                        let call = self.client.proxy().contract().create_and_register_agent(proxy_settings);
+                        self.send(OnboardStep::Create, call, &mut state, &mut journal).await?
+                    }
+                };
+                let factory = self.client.deployment().agent_factory;
+                let created = receipt
+                    .logs
+                    .iter()
+                    .filter(|log| Some(log.address) == factory)
+                    .find_map(|log| <AgentCreatedFilter as EthLogDecode>::decode_log(&log.clone().into()).ok())
+                    .ok_or_else(|| format!("No AgentCreated event in transaction {:?}", receipt.transaction_hash))?
+                    .agent;
+                write_journal(&mut journal, &JournalEntry::Created { agent: created })?;
+                created
+            }
+            (None, Some(agent)) => {
+                let address = format!("{:?}", agent);
+                if self.resume(OnboardStep::Register, &mut state, &mut journal).await?.is_some() {
+                    // mined before a restart
+                } else if manager.allowed_agent(&address).await? || manager.registering_agents().await?.contains(&agent) {
+                    skipped.push(OnboardStep::Register);
+                } else {
+                    let call = manager.contract().register_agent(agent, settings.clone());
+                    self.send(OnboardStep::Register, call, &mut state, &mut journal).await?;
+                }
+                agent
+            }
+        };
+        tracing::Span::current().record("agent", format!("{:?}", agent));
+
+        if self.resume(OnboardStep::Accept, &mut state, &mut journal).await?.is_some() {
+            // mined before a restart
+        } else if manager.allowed_agent(&format!("{:?}", agent)).await? {
+            skipped.push(OnboardStep::Accept);
+        } else {
+            let call = manager.contract().accept_agent(agent);
+            self.send(OnboardStep::Accept, call, &mut state, &mut journal).await?;
+        }
+
+        let (settings_digest, accepted_block) = self.find_accepted(agent).await?;
+        write_journal(&mut journal, &JournalEntry::Confirmed { agent, settings_digest, block_number: accepted_block })?;
+        tracing::info!(digest = ?settings_digest, block = accepted_block, "Agent onboarded");
+
+        Ok(OnboardReport { agent, settings_digest, accepted_block, transactions: state.mined, skipped })
+    }
+
+    /// The receipt of the transaction journaled for `step` before a restart, once it is
+    /// mined. `None` if nothing was journaled, or if the transaction reverted or is
+    /// unknown to the node; those are cleared so the caller checks the chain and sends
+    /// the step again.
+    async fn resume(
+        &self,
+        step: OnboardStep,
+        state: &mut JournalState,
+        journal: &mut Option<File>,
+    ) -> ApiResult<Option<TransactionReceipt>> {
+        let Some(tx_hash) = state.sent.get(&step).copied() else {
+            return Ok(None);
+        };
+        let client = self.client.proxy().contract().client();
+        let mut receipt = client.get_transaction_receipt(tx_hash).await
+            .map_err(|e| format!("Failed to get receipt of {:?}: {}", tx_hash, e))?;
+        if receipt.is_none() {
+            let known = client.get_transaction(tx_hash).await
+                .map_err(|e| format!("Failed to get transaction {:?}: {}", tx_hash, e))?;
+            if known.is_some() {
+                tracing::info!(?step, ?tx_hash, "Waiting for journaled transaction");
+                receipt = PendingTransaction::new(tx_hash, client.provider()).await
+                    .map_err(|e| format!("Transaction failed: {:?}", e))?;
+            }
+        }
+
+        match receipt {
+            Some(receipt) if receipt.status == Some(U64::one()) => {
+                if !state.mined.contains(&(step, tx_hash)) {
+                    self.mined(step, &receipt, state, journal)?;
+                }
+                Ok(Some(receipt))
+            }
+            receipt => {
+                let outcome = if receipt.is_some() { "reverted" } else { "is unknown to the node" };
+                tracing::warn!(?step, ?tx_hash, "Journaled transaction {}; checking the step again", outcome);
+                write_journal(journal, &JournalEntry::Cleared { step, tx_hash })?;
+                state.sent.remove(&step);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Sends `call` for `step` and waits for it to be mined. The signed transaction's hash
+    /// is journaled before it is broadcast, so a crash in between cannot lose a sent step.
+    async fn send<D: Detokenize>(
+        &self,
+        step: OnboardStep,
+        call: ContractCall<SignerClient, D>,
+        state: &mut JournalState,
+        journal: &mut Option<File>,
+    ) -> ApiResult<TransactionReceipt> {
+        let method = call.function.name.clone();
+        let gas_estimate = call.estimate_gas().await
+            .map_err(|e| format!("Failed to estimate gas for {}: {:?}", method, e))?;
+        let mut tx = call.gas(gas_estimate).tx;
+
+        let client = self.client.proxy().contract().client();
+        client.fill_transaction(&mut tx, None).await
+            .map_err(|e| format!("Failed to fill {} transaction: {:?}", method, e))?;
+        let signature = client.sign_transaction(&tx, client.address()).await
+            .map_err(|e| format!("Failed to sign {} transaction: {:?}", method, e))?;
+        let raw_tx = tx.rlp_signed(&signature);
+        let tx_hash = H256::from(ethers::utils::keccak256(&raw_tx));
+        write_journal(journal, &JournalEntry::Sent { step, tx_hash })?;
+        state.sent.insert(step, tx_hash);
+
+        let pending_tx = client.send_raw_transaction(raw_tx).await
+            .map_err(|e| format!("Failed to send {} transaction: {:?}", method, e))?;
+        tracing::info!(?step, ?tx_hash, "Sent transaction");
+
+        let receipt = pending_tx.await
+            .map_err(|e| format!("Transaction failed: {:?}", e))?
+            .ok_or_else(|| format!("Transaction {:?} was dropped", tx_hash))?;
+        if receipt.status != Some(U64::one()) {
+            let (contract, method) = step.contract_method();
+            metrics::observe_receipt(contract, method, &receipt);
+            return Err(format!("{} transaction {:?} reverted", method, receipt.transaction_hash));
+        }
+        self.mined(step, &receipt, state, journal)?;
+        Ok(receipt)
+    }
+
+    fn mined(&self, step: OnboardStep, receipt: &TransactionReceipt, state: &mut JournalState, journal: &mut Option<File>) -> ApiResult<()> {
+        let (contract, method) = step.contract_method();
+        metrics::observe_receipt(contract, method, receipt);
+        write_journal(journal, &JournalEntry::Mined { step, tx_hash: receipt.transaction_hash })?;
+        state.mined.push((step, receipt.transaction_hash));
+        Ok(())
+    }
+
+    /// The digest and block of the agent's latest AgentAccepted event.
+    async fn find_accepted(&self, agent: Address) -> ApiResult<(H256, u64)> {
+        let deployment = self.client.deployment();
+        let filter = Filter::new()
+            .address(deployment.agent_manager.ok_or("The deployment has no AgentManager address")?)
+            .topic0(AgentAcceptedFilter::signature())
+            .topic1(H256::from(agent));
+        let client = self.client.proxy().contract().client();
+        let logs = get_logs_in_chunks(client.as_ref(), &filter, deployment.deployment_block.unwrap_or_default()).await
+            .map_err(|e| format!("Failed to get AgentAccepted events: {}", e))?;
+
+        logs.iter()
+            .rev()
+            .find_map(|log| {
+                let accepted = <AgentAcceptedFilter as EthLogDecode>::decode_log(&log.clone().into()).ok()?;
+                Some((H256::from(accepted.digest), log.block_number?.as_u64()))
+            })
+            .ok_or_else(|| format!("No AgentAccepted event found for agent {:?}", agent))
+    }
+}
+
+fn read_journal(path: &Path) -> ApiResult<JournalState> {
+    let mut state = JournalState::default();
+    if !path.exists() {
+        return Ok(state);
+    }
+
+    let file = File::open(path).map_err(|e| format!("Failed to open onboarding journal: {}", e))?;
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("Failed to read onboarding journal: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        // a torn last line from a crash is skipped; open_journal cuts it off before writing
+        match serde_json::from_str::<JournalEntry>(&line) {
+            Ok(JournalEntry::Started { settings, agent }) => state.started = Some((settings, agent)),
+            Ok(JournalEntry::Sent { step, tx_hash }) => {
+                state.sent.insert(step, tx_hash);
+            }
+            Ok(JournalEntry::Mined { step, tx_hash }) => state.mined.push((step, tx_hash)),
+            Ok(JournalEntry::Cleared { step, tx_hash }) => {
+                if state.sent.get(&step) == Some(&tx_hash) {
+                    state.sent.remove(&step);
+                }
+            }
+            Ok(JournalEntry::Created { agent }) => state.created = Some(agent),
+            Ok(JournalEntry::Confirmed { agent, settings_digest, block_number }) => {
+                state.confirmed = Some((agent, settings_digest, block_number));
+            }
+            Err(_) => continue,
+        }
+    }
+    Ok(state)
+}
+
+fn write_journal(journal: &mut Option<File>, entry: &JournalEntry) -> ApiResult<()> {
+    let Some(file) = journal else {
+        return Ok(());
+    };
+
+    let line = serde_json::to_string(entry).map_err(|e| format!("Failed to encode onboarding journal entry: {}", e))?;
+    writeln!(file, "{}", line)
+        .and_then(|_| file.sync_data())
+        .map_err(|e| format!("Failed to write onboarding journal: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_resume_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("onboard.jsonl");
+        let agent = Address::repeat_byte(0xaa);
+        let (create_tx, accept_tx) = (H256::repeat_byte(1), H256::repeat_byte(2));
+
+        let mut journal = Some(File::create(&path).unwrap());
+        for entry in [
+            JournalEntry::Started { settings: serde_json::json!({ "threshold": 1 }), agent: None },
+            JournalEntry::Sent { step: OnboardStep::Create, tx_hash: create_tx },
+            JournalEntry::Mined { step: OnboardStep::Create, tx_hash: create_tx },
+            JournalEntry::Created { agent },
+            JournalEntry::Sent { step: OnboardStep::Accept, tx_hash: accept_tx },
+        ] {
+            write_journal(&mut journal, &entry).unwrap();
+        }
+        // torn by a crash mid-write, then reopened and written after the restart
+        write!(journal.as_mut().unwrap(), "{{\"event\":\"mined\",\"st").unwrap();
+        let mut journal = Some(open_journal(&path).unwrap());
+        write_journal(&mut journal, &JournalEntry::Mined { step: OnboardStep::Accept, tx_hash: accept_tx }).unwrap();
+
+        let state = read_journal(&path).unwrap();
+        assert_eq!(state.started, Some((serde_json::json!({ "threshold": 1 }), None)));
+        assert_eq!(state.created, Some(agent));
+        assert_eq!(state.mined, vec![(OnboardStep::Create, create_tx), (OnboardStep::Accept, accept_tx)]);
+        assert_eq!(state.sent.get(&OnboardStep::Accept), Some(&accept_tx));
+        assert!(state.confirmed.is_none());
+        assert!(read_journal(&dir.path().join("missing.jsonl")).unwrap().started.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_resume_skips_steps_done_on_chain() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        use serde_json::json;
+
+        use crate::core::deployments::Deployment;
+        use crate::core::versions::VersionPolicy;
+        use crate::svc::agent_manager_node::AgentHeader;
+        use crate::svc::client::ConnectOptions;
+        use crate::utils::mock_server::{MockRequest, MockResponse, MockServer};
+        use crate::utils::secret::SecretKey;
+
+        let (proxy, manager, factory) = (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+        let agent = Address::repeat_byte(0xaa);
+        let settings = AgentSettings {
+            signers: vec![Address::repeat_byte(0x11)],
+            threshold: 1,
+            converter_address: Address::zero(),
+            agent_header: AgentHeader { version: "1.0".to_string(), ..Default::default() },
+        };
+        let log = |address: Address, topics: Vec<H256>, data: Vec<u8>| Log {
+            address,
+            topics,
+            data: data.into(),
+            block_number: Some(7.into()),
+            log_index: Some(0.into()),
+            ..Default::default()
+        };
+
+        // before the restart the create was mined but not journaled, and the accept
+        // reverted because someone else accepted the agent first
+        let (create_tx, accept_tx) = (H256::repeat_byte(1), H256::repeat_byte(2));
+        let created = log(
+            factory,
+            vec![AgentCreatedFilter::signature(), H256::from(Address::repeat_byte(0xee)), H256::from(agent)],
+            ethers::abi::encode(&[manager.into_token(), U256::one().into_token()]),
+        );
+        let create_receipt = TransactionReceipt {
+            transaction_hash: create_tx,
+            status: Some(U64::one()),
+            logs: vec![created],
+            ..Default::default()
+        };
+        let accept_receipt = TransactionReceipt { transaction_hash: accept_tx, status: Some(U64::zero()), ..Default::default() };
+        let accepted = log(
+            manager,
+            vec![AgentAcceptedFilter::signature(), H256::from(agent), H256::repeat_byte(0xdd)],
+            ethers::abi::encode(&[settings.clone().into_token()]),
+        );
+
+        let sent = Arc::new(AtomicUsize::new(0));
+        let counter = sent.clone();
+        let server = MockServer::start(Arc::new(move |request: MockRequest| {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let result = match body["method"].as_str() {
+                Some("eth_chainId") => json!("0x7a69"),
+                Some("eth_blockNumber") => json!("0x10"),
+                // allowed_agent
+                Some("eth_call") => json!(format!("0x{:064x}", 1)),
+                Some("eth_getTransactionReceipt") => match body["params"][0].as_str() {
+                    Some(hash) if hash == format!("{:?}", create_tx) => json!(create_receipt),
+                    Some(hash) if hash == format!("{:?}", accept_tx) => json!(accept_receipt),
+                    _ => serde_json::Value::Null,
+                },
+                Some("eth_getLogs") => json!([accepted]),
+                Some("eth_sendRawTransaction") | Some("eth_estimateGas") => {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    serde_json::Value::Null
+                }
+                _ => serde_json::Value::Null,
+            };
+            let response = json!({ "jsonrpc": "2.0", "id": body["id"], "result": result });
+            MockResponse::new(200, "application/json", response.to_string())
+        }))
+        .await
+        .unwrap();
+
+        let mut options = ConnectOptions { version_policy: VersionPolicy::Skip, ..Default::default() };
+        options.deployments.insert(Deployment {
+            name: "local".to_string(),
+            chain_id: 31337,
+            agent_proxy: proxy,
+            agent_manager: Some(manager),
+            agent_factory: Some(factory),
+            deployment_block: Some(0),
+        });
+        let wallet = SecretKey::from("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80").to_wallet().unwrap();
+        let client = AttpsClient::connect_with_options("local", &server.url(), wallet, &options).await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("onboard.jsonl");
+        let mut journal = Some(File::create(&path).unwrap());
+        for entry in [
+            JournalEntry::Started { settings: settings_json(&settings), agent: None },
+            JournalEntry::Sent { step: OnboardStep::Create, tx_hash: create_tx },
+            JournalEntry::Sent { step: OnboardStep::Accept, tx_hash: accept_tx },
+        ] {
+            write_journal(&mut journal, &entry).unwrap();
+        }
+
+        let onboarding = Onboarding::new(&client, path.to_str());
+        let report = onboarding.onboard_agent(&settings, None).await.unwrap();
+        assert_eq!(report.agent, agent);
+        assert_eq!(report.settings_digest, H256::repeat_byte(0xdd));
+        assert_eq!(report.transactions, vec![(OnboardStep::Create, create_tx)]);
+        assert_eq!(report.skipped, vec![OnboardStep::Accept]);
+        assert_eq!(sent.load(Ordering::SeqCst), 0);
+
+        let state = read_journal(&path).unwrap();
+        assert!(!state.sent.contains_key(&OnboardStep::Accept));
+        assert_eq!(state.created, Some(agent));
+        assert!(state.confirmed.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_create_is_journaled_before_broadcast() {
+        use std::sync::{Arc, Mutex};
+
+        use ethers::types::transaction::eip2718::TypedTransaction;
+        use ethers::utils::{keccak256, rlp::Rlp};
+        use serde_json::json;
+
+        use crate::core::deployments::Deployment;
+        use crate::core::versions::VersionPolicy;
+        use crate::svc::agent_manager_node::AgentHeader;
+        use crate::svc::client::ConnectOptions;
+        use crate::utils::mock_server::{MockRequest, MockResponse, MockServer};
+        use crate::utils::secret::SecretKey;
+
+        let (proxy, manager, factory) = (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+        let agent = Address::repeat_byte(0xaa);
+        let settings = AgentSettings {
+            signers: vec![Address::repeat_byte(0x11)],
+            threshold: 1,
+            converter_address: Address::zero(),
+            agent_header: AgentHeader { version: "1.0".to_string(), ..Default::default() },
+        };
+        let created = Log {
+            address: factory,
+            topics: vec![AgentCreatedFilter::signature(), H256::from(Address::repeat_byte(0xee)), H256::from(agent)],
+            data: ethers::abi::encode(&[manager.into_token(), U256::one().into_token()]).into(),
+            ..Default::default()
+        };
+        let accepted = Log {
+            address: manager,
+            topics: vec![AgentAcceptedFilter::signature(), H256::from(agent), H256::repeat_byte(0xdd)],
+            data: ethers::abi::encode(&[settings.clone().into_token()]).into(),
+            block_number: Some(7.into()),
+            ..Default::default()
+        };
+
+        // the node mines the create, but the process dies before it hears back
+        let sent: Arc<Mutex<Vec<H256>>> = Arc::default();
+        let node = sent.clone();
+        let server = MockServer::start(Arc::new(move |request: MockRequest| {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let result = match body["method"].as_str() {
+                Some("eth_chainId") => json!("0x7a69"),
+                Some("eth_blockNumber") => json!("0x10"),
+                Some("eth_getTransactionCount") => json!("0x0"),
+                Some("eth_estimateGas") => json!("0x30000"),
+                Some("eth_gasPrice") | Some("eth_maxPriorityFeePerGas") => json!("0x3b9aca00"),
+                Some("eth_getBlockByNumber") => json!(Block::<H256> {
+                    number: Some(16.into()),
+                    base_fee_per_gas: Some(1_000_000_000u64.into()),
+                    ..Default::default()
+                }),
+                Some("eth_feeHistory") => json!({
+                    "oldestBlock": "0x1",
+                    "baseFeePerGas": ["0x3b9aca00", "0x3b9aca00"],
+                    "gasUsedRatio": [0.5],
+                    "reward": [["0x3b9aca00"]],
+                }),
+                // allowed_agent
+                Some("eth_call") => json!(format!("0x{:064x}", 1)),
+                Some("eth_sendRawTransaction") => {
+                    let raw = hex::decode(body["params"][0].as_str().unwrap().trim_start_matches("0x")).unwrap();
+                    TypedTransaction::decode_signed(&Rlp::new(&raw)).unwrap();
+                    node.lock().unwrap().push(H256::from(keccak256(&raw)));
+                    let response = json!({ "jsonrpc": "2.0", "id": body["id"], "error": { "code": -32000, "message": "connection reset" } });
+                    return MockResponse::new(200, "application/json", response.to_string());
+                }
+                Some("eth_getTransactionReceipt") => {
+                    let hash: H256 = serde_json::from_value(body["params"][0].clone()).unwrap();
+                    match node.lock().unwrap().contains(&hash) {
+                        true => json!(TransactionReceipt {
+                            transaction_hash: hash,
+                            status: Some(U64::one()),
+                            logs: vec![created.clone()],
+                            ..Default::default()
+                        }),
+                        false => serde_json::Value::Null,
+                    }
+                }
+                Some("eth_getLogs") => json!([accepted]),
+                _ => serde_json::Value::Null,
+            };
+            let response = json!({ "jsonrpc": "2.0", "id": body["id"], "result": result });
+            MockResponse::new(200, "application/json", response.to_string())
+        }))
+        .await
+        .unwrap();
+
+        let mut options = ConnectOptions { version_policy: VersionPolicy::Skip, ..Default::default() };
+        options.deployments.insert(Deployment {
+            name: "local".to_string(),
+            chain_id: 31337,
+            agent_proxy: proxy,
+            agent_manager: Some(manager),
+            agent_factory: Some(factory),
+            deployment_block: Some(0),
+        });
+        let wallet = SecretKey::from("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80").to_wallet().unwrap();
+        let client = AttpsClient::connect_with_options("local", &server.url(), wallet, &options).await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("onboard.jsonl");
+        let onboarding = Onboarding::new(&client, path.to_str());
+        assert!(onboarding.onboard_agent(&settings, None).await.is_err());
+        let create_tx = sent.lock().unwrap()[0];
+        assert_eq!(read_journal(&path).unwrap().sent.get(&OnboardStep::Create), Some(&create_tx));
+
+        let report = onboarding.onboard_agent(&settings, None).await.unwrap();
+        assert_eq!(report.agent, agent);
+        assert_eq!(report.transactions, vec![(OnboardStep::Create, create_tx)]);
+        assert_eq!(sent.lock().unwrap().len(), 1);
+    }
+}
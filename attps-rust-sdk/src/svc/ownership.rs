@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use ethers::contract::{EthEvent, EthLogDecode};
+use ethers::prelude::*;
+use serde::Serialize;
+
+use crate::core::versions::ContractKind;
+use crate::core::events::get_logs_in_chunks;
+use crate::core::ApiResult;
+use crate::svc::agent_manager_node::{OwnershipTransferRequestedFilter, OwnershipTransferredFilter};
+use crate::svc::client::AttpsClient;
+use crate::utils::common::parse_address;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The owner of one contract, and the address it is being transferred to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OwnershipStatus {
+    pub contract: ContractKind,
+    pub address: Address,
+    pub owner: Address,
+    /// Set by `transfer_ownership` until the new owner accepts.
+    pub pending_owner: Option<Address>,
+}
+
+/// Two-step ownership transfer of every owned contract in a deployment: the AgentProxy
+/// and the AgentManager. The AgentFactory has no owner.
+///
+/// The current owner calls `transfer_all`, the new owner's client calls `accept_all`,
+/// and either side can `wait_for_transfer` until `owner()` returns the new owner. The
+/// contracts have no getter for the pending owner, so it is read from the events since
+/// the deployment block.
+pub struct OwnershipTransfer<'a> {
+    client: &'a AttpsClient,
+}
+
+impl<'a> OwnershipTransfer<'a> {
+    pub fn new(client: &'a AttpsClient) -> Self {
+        Self { client }
+    }
+
+    pub async fn status(&self) -> ApiResult<Vec<OwnershipStatus>> {
+        let pending = pending_owners(&self.ownership_logs().await?);
+        let mut statuses = Vec::new();
+        for (contract, address) in self.contracts()? {
+            statuses.push(OwnershipStatus {
+                contract,
+                address,
+                owner: self.owner(contract).await?,
+                pending_owner: pending.get(&address).copied().flatten(),
+            });
+        }
+        Ok(statuses)
+    }
+
+    /// Starts the transfer of every contract to `new_owner`. Contracts already owned by
+    /// or pending to `new_owner` are skipped, so a partly failed call can be repeated.
+    /// Nothing is sent unless this client's signer owns all the other contracts.
+    pub async fn transfer_all(&self, new_owner: &str) -> ApiResult<Vec<(ContractKind, String)>> {
+        let new_owner = parse_address(new_owner)?;
+        let signer = self.signer_address();
+        let statuses = self.status().await?;
+
+        let not_owned: Vec<&str> = statuses
+            .iter()
+            .filter(|status| status.owner != signer && status.owner != new_owner)
+            .map(|status| status.contract.name())
+            .collect();
+        if !not_owned.is_empty() {
+            return Err(format!("{:?} does not own {}", signer, not_owned.join(", ")));
+        }
+
+        let mut sent = Vec::new();
+        for status in statuses {
+            if status.owner == new_owner || status.pending_owner == Some(new_owner) {
+                tracing::info!(contract = status.contract.name(), "Ownership transfer already started");
+                continue;
+            }
+            let new_owner = format!("{:?}", new_owner);
+            let receipt = match status.contract {
+                ContractKind::AgentManager => self.client.manager().transfer_ownership(&new_owner).await?,
+                _ => self.client.proxy().transfer_ownership(&new_owner).await?,
+            };
+            sent.push((status.contract, receipt));
+        }
+        Ok(sent)
+    }
+
+    /// Accepts every transfer pending to this client's signer.
+    pub async fn accept_all(&self) -> ApiResult<Vec<(ContractKind, String)>> {
+        let signer = self.signer_address();
+        let mut accepted = Vec::new();
+        for status in self.status().await? {
+            if status.pending_owner != Some(signer) {
+                continue;
+            }
+            let receipt = match status.contract {
+                ContractKind::AgentManager => self.client.manager().accept_ownership().await?,
+                _ => self.client.proxy().accept_ownership().await?,
+            };
+            accepted.push((status.contract, receipt));
+        }
+        if accepted.is_empty() {
+            return Err(format!("No ownership transfers are pending for {:?}", signer));
+        }
+        Ok(accepted)
+    }
+
+    /// Waits until `new_owner` owns every contract.
+    pub async fn wait_for_transfer(&self, new_owner: &str, timeout: Duration) -> ApiResult<Vec<OwnershipStatus>> {
+        let new_owner = parse_address(new_owner)?;
+        let started = Instant::now();
+        loop {
+            let mut waiting = Vec::new();
+            for (contract, _) in self.contracts()? {
+                if self.owner(contract).await? != new_owner {
+                    waiting.push(contract.name());
+                }
+            }
+            if waiting.is_empty() {
+                return self.status().await;
+            }
+            if started.elapsed() >= timeout {
+                return Err(format!("{} not transferred to {:?} after {:?}", waiting.join(", "), new_owner, timeout));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    fn contracts(&self) -> ApiResult<Vec<(ContractKind, Address)>> {
+        let deployment = self.client.deployment();
+        Ok(vec![
+            (ContractKind::AgentProxy, deployment.agent_proxy),
+            (
+                ContractKind::AgentManager,
+                deployment.agent_manager.ok_or("The deployment has no AgentManager address")?,
+            ),
+        ])
+    }
+
+    async fn owner(&self, contract: ContractKind) -> ApiResult<Address> {
+        let owner = match contract {
+            ContractKind::AgentManager => self.client.manager().get_owner().await?,
+            _ => self.client.proxy().get_owner().await?,
+        };
+        parse_address(&owner)
+    }
+
+    fn signer_address(&self) -> Address {
+        self.client.proxy().contract().client().address()
+    }
+
+    async fn ownership_logs(&self) -> ApiResult<Vec<Log>> {
+        let addresses: Vec<Address> = self.contracts()?.into_iter().map(|(_, address)| address).collect();
+        let filter = Filter::new()
+            .address(addresses)
+            .topic0(vec![OwnershipTransferRequestedFilter::signature(), OwnershipTransferredFilter::signature()]);
+        let from_block = self.client.deployment().deployment_block.unwrap_or_default();
+        get_logs_in_chunks(self.client.proxy().contract().client().as_ref(), &filter, from_block).await
+            .map_err(|e| format!("Failed to get ownership events: {}", e))
+    }
+}
+
+/// The pending owner of each contract after `logs`: a request sets it and a completed
+/// transfer clears it.
+fn pending_owners(logs: &[Log]) -> HashMap<Address, Option<Address>> {
+    let mut logs: Vec<&Log> = logs.iter().filter(|log| log.removed != Some(true)).collect();
+    logs.sort_by_key(|log| (log.block_number, log.log_index));
+
+    let mut pending = HashMap::new();
+    for log in logs {
+        let raw = log.clone().into();
+        if let Ok(request) = <OwnershipTransferRequestedFilter as EthLogDecode>::decode_log(&raw) {
+            pending.insert(log.address, Some(request.to));
+        } else if <OwnershipTransferredFilter as EthLogDecode>::decode_log(&raw).is_ok() {
+            pending.insert(log.address, None);
+        }
+    }
+    pending
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ownership_log(contract: Address, signature: H256, from: Address, to: Address, block: u64) -> Log {
+        Log {
+            address: contract,
+            topics: vec![signature, H256::from(from), H256::from(to)],
+            block_number: Some(block.into()),
+            log_index: Some(0.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pending_owners_from_events() {
+        let (proxy, manager) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let (old, new, other) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb), Address::repeat_byte(0xcc));
+        let requested = OwnershipTransferRequestedFilter::signature();
+        let transferred = OwnershipTransferredFilter::signature();
+
+        // the proxy's second request replaces the first; the manager's transfer is done
+        let logs = vec![
+            ownership_log(proxy, requested, old, new, 7),
+            ownership_log(manager, transferred, old, new, 6),
+            ownership_log(proxy, requested, old, other, 4),
+            ownership_log(manager, requested, old, new, 5),
+        ];
+        let pending = pending_owners(&logs);
+        assert_eq!(pending[&proxy], Some(new));
+        assert_eq!(pending[&manager], None);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_transfer_reads_current_owner() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        use serde_json::{json, Value};
+
+        use crate::core::deployments::Deployment;
+        use crate::core::versions::VersionPolicy;
+        use crate::svc::client::ConnectOptions;
+        use crate::utils::mock_server::{MockRequest, MockResponse, MockServer};
+        use crate::utils::secret::SecretKey;
+
+        // owner() returns the old owner for the first three calls, then the new one
+        let (old, new) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        let owner_calls = Arc::new(AtomicUsize::new(0));
+        let counter = owner_calls.clone();
+        let server = MockServer::start(Arc::new(move |request: MockRequest| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let call = &body["params"][0];
+            let input = call["input"].as_str().or(call["data"].as_str()).unwrap_or_default();
+            let result = match body["method"].as_str() {
+                Some("eth_chainId") => json!("0x7a69"),
+                Some("eth_blockNumber") => json!("0x10"),
+                Some("eth_getLogs") => json!([]),
+                Some("eth_call") if input.starts_with("0x8da5cb5b") => {
+                    let owner = if counter.fetch_add(1, Ordering::SeqCst) < 3 { old } else { new };
+                    json!(format!("{:?}", H256::from(owner)))
+                }
+                Some("eth_call") => json!(format!("0x{:064x}", 2)),
+                _ => Value::Null,
+            };
+            let response = json!({ "jsonrpc": "2.0", "id": body["id"], "result": result });
+            MockResponse::new(200, "application/json", response.to_string())
+        }))
+        .await
+        .unwrap();
+
+        let mut options = ConnectOptions { version_policy: VersionPolicy::Skip, ..Default::default() };
+        options.deployments.insert(Deployment {
+            name: "local".to_string(),
+            chain_id: 31337,
+            agent_proxy: Address::repeat_byte(1),
+            agent_manager: None,
+            agent_factory: None,
+            deployment_block: Some(0),
+        });
+        let wallet = SecretKey::from("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80").to_wallet().unwrap();
+        let client = AttpsClient::connect_with_options("local", &server.url(), wallet, &options).await.unwrap();
+        let transfer = OwnershipTransfer::new(&client);
+
+        let error = transfer.wait_for_transfer(&format!("{:?}", new), Duration::ZERO).await.unwrap_err();
+        assert!(error.starts_with("AgentProxy, AgentManager not transferred"), "{}", error);
+
+        let statuses = transfer.wait_for_transfer(&format!("{:?}", new), Duration::from_secs(30)).await.unwrap();
+        assert!(owner_calls.load(Ordering::SeqCst) > 4);
+        assert!(statuses.iter().all(|status| status.owner == new && status.pending_owner.is_none()));
+    }
+}
@@ -0,0 +1,317 @@
+use ethers::abi::RawLog;
+use ethers::contract::{EthEvent, EthLogDecode};
+use ethers::prelude::*;
+use serde::{Serialize, Serializer};
+
+use crate::core::events::get_logs_in_chunks;
+use crate::core::ApiResult;
+use crate::svc::agent_manager_node::{
+    settings_json, AgentManagerContractEvents, AgentRemovedFilter, AgentSettings, AgentSettingsProposedFilter,
+    AgentSettingsUpdatedFilter,
+};
+use crate::svc::client::AttpsClient;
+use crate::svc::manifest::diff_settings;
+use crate::utils::common::parse_address;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProposalState {
+    /// Waiting for `accept_agent_setting_proposal`.
+    Pending,
+    /// Accepted; the settings are the agent's active config.
+    Applied,
+    /// Replaced by a newer proposal before it was accepted.
+    Superseded,
+    /// The agent was removed before the proposal was accepted.
+    Removed,
+}
+
+/// One `change_agent_setting_proposal` and what became of it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Proposal {
+    pub agent: Address,
+    #[serde(serialize_with = "serialize_settings")]
+    pub settings: AgentSettings,
+    /// Sender of the proposing transaction.
+    pub proposer: Address,
+    pub block_number: u64,
+    pub transaction_hash: H256,
+    pub state: ProposalState,
+    /// Block of the event that ended the proposal, unless it is pending.
+    pub closed_block: Option<u64>,
+    /// Config digest, once applied.
+    pub digest: Option<H256>,
+}
+
+/// Setting proposals rebuilt from the AgentManager's AgentSettingsProposed,
+/// AgentSettingsUpdated and AgentRemoved events since the deployment block.
+pub struct ProposalTracker<'a> {
+    client: &'a AttpsClient,
+}
+
+impl<'a> ProposalTracker<'a> {
+    pub fn new(client: &'a AttpsClient) -> Self {
+        Self { client }
+    }
+
+    /// Every proposal, oldest first; only `agent`'s when given.
+    pub async fn proposals(&self, agent: Option<&str>) -> ApiResult<Vec<Proposal>> {
+        let proposals = self.load(agent).await?;
+        self.with_proposers(proposals).await
+    }
+
+    /// Pending proposals of all agents.
+    pub async fn pending(&self) -> ApiResult<Vec<Proposal>> {
+        let mut proposals = self.load(None).await?;
+        proposals.retain(|proposal| proposal.state == ProposalState::Pending);
+        self.with_proposers(proposals).await
+    }
+
+    pub async fn pending_for(&self, agent: &str) -> ApiResult<Option<Proposal>> {
+        let proposals = self.load(Some(agent)).await?;
+        let pending = proposals.into_iter().filter(|proposal| proposal.state == ProposalState::Pending).collect();
+        Ok(self.with_proposers(pending).await?.pop())
+    }
+
+    /// Applied proposals of `agent`, oldest first.
+    pub async fn history(&self, agent: &str) -> ApiResult<Vec<Proposal>> {
+        let mut proposals = self.load(Some(agent)).await?;
+        proposals.retain(|proposal| proposal.state == ProposalState::Applied);
+        self.with_proposers(proposals).await
+    }
+
+    /// Differences between the agent's active config and its pending proposal, in the
+    /// format of manifest plans.
+    pub async fn diff(&self, agent: &str) -> ApiResult<Vec<String>> {
+        let proposal = self.pending_for(agent).await?
+            .ok_or_else(|| format!("Agent {} has no pending proposal", agent))?;
+        let configs = self.client.manager().agent_configs(agent).await?;
+        let active = configs
+            .iter()
+            .filter(|config| config.is_active)
+            .max_by_key(|config| config.config_block_number)
+            .ok_or_else(|| format!("Agent {} has no active config", agent))?;
+        Ok(diff_settings(&active.settings, &proposal.settings, true))
+    }
+
+    /// Accepts the pending proposal if its settings are still `reviewed`. A proposal
+    /// replaced between that check and the transaction is still accepted; the
+    /// AgentSettingsUpdated event in the receipt is then checked and the mismatch
+    /// returned as an error.
+    pub async fn accept(&self, agent: &str, reviewed: &AgentSettings) -> ApiResult<String> {
+        let proposal = self.pending_for(agent).await?
+            .ok_or_else(|| format!("Agent {} has no pending proposal", agent))?;
+        if proposal.settings != *reviewed {
+            return Err(format!(
+                "The pending proposal for agent {} changed since it was reviewed (proposed by {:?} in {:?})",
+                agent, proposal.proposer, proposal.transaction_hash
+            ));
+        }
+
+        let receipt = self.client.manager().send_accept_agent_setting_proposal(proposal.agent).await?;
+        let manager = self.client.deployment().agent_manager.ok_or("The deployment has no AgentManager address")?;
+        let (applied, digest) = applied_settings(&receipt, manager, proposal.agent)?;
+        if applied != *reviewed {
+            return Err(format!(
+                "Agent {} was updated to settings that differ from the reviewed ones (digest {:?} in {:?}): {}",
+                agent,
+                digest,
+                receipt.transaction_hash,
+                diff_settings(reviewed, &applied, true).join(", ")
+            ));
+        }
+        Ok(format!("{:?}", receipt))
+    }
+
+    /// Proposals from the manager's events, without proposers.
+    async fn load(&self, agent: Option<&str>) -> ApiResult<Vec<Proposal>> {
+        let deployment = self.client.deployment();
+        let mut filter = Filter::new()
+            .address(deployment.agent_manager.ok_or("The deployment has no AgentManager address")?)
+            .topic0(vec![
+                AgentSettingsProposedFilter::signature(),
+                AgentSettingsUpdatedFilter::signature(),
+                AgentRemovedFilter::signature(),
+            ]);
+        if let Some(agent) = agent {
+            filter = filter.topic1(H256::from(parse_address(agent)?));
+        }
+
+        let provider = self.client.proxy().contract().client();
+        let logs = get_logs_in_chunks(provider.as_ref(), &filter, deployment.deployment_block.unwrap_or_default()).await
+            .map_err(|e| format!("Failed to get proposal events: {}", e))?;
+        Ok(build_proposals(&logs))
+    }
+
+    /// Fills in each proposal's proposer from its transaction.
+    async fn with_proposers(&self, mut proposals: Vec<Proposal>) -> ApiResult<Vec<Proposal>> {
+        let provider = self.client.proxy().contract().client();
+        for proposal in &mut proposals {
+            let transaction = provider.get_transaction(proposal.transaction_hash).await
+                .map_err(|e| format!("Failed to get transaction {:?}: {}", proposal.transaction_hash, e))?
+                .ok_or_else(|| format!("Transaction {:?} not found", proposal.transaction_hash))?;
+            proposal.proposer = transaction.from;
+        }
+        Ok(proposals)
+    }
+}
+
+/// The settings and digest that the AgentSettingsUpdated event in `receipt` applied
+/// to `agent`.
+fn applied_settings(receipt: &TransactionReceipt, manager: Address, agent: Address) -> ApiResult<(AgentSettings, H256)> {
+    if receipt.status != Some(U64::one()) {
+        return Err(format!("accept_agent_setting_proposal transaction {:?} reverted", receipt.transaction_hash));
+    }
+    receipt
+        .logs
+        .iter()
+        .filter(|log| log.address == manager)
+        .find_map(|log| match AgentManagerContractEvents::decode_log(&RawLog::from(log.clone())) {
+            Ok(AgentManagerContractEvents::AgentSettingsUpdatedFilter(e)) if e.agent == agent => {
+                Some((e.agent_settings, H256::from(e.digest)))
+            }
+            _ => None,
+        })
+        .ok_or_else(|| format!("Transaction {:?} has no AgentSettingsUpdated event for {:?}", receipt.transaction_hash, agent))
+}
+
+/// Runs the proposal state machine over `logs`. Proposers are left as zero.
+fn build_proposals(logs: &[Log]) -> Vec<Proposal> {
+    let mut logs: Vec<&Log> = logs.iter().filter(|log| log.removed != Some(true)).collect();
+    logs.sort_by_key(|log| (log.block_number, log.log_index));
+
+    let mut proposals: Vec<Proposal> = Vec::new();
+    for log in logs {
+        let (Some(block_number), Some(transaction_hash)) = (log.block_number, log.transaction_hash) else {
+            continue;
+        };
+        let block_number = block_number.as_u64();
+        let Ok(event) = AgentManagerContractEvents::decode_log(&RawLog::from(log.clone())) else {
+            continue;
+        };
+        let (agent, closed_state, digest) = match event {
+            AgentManagerContractEvents::AgentSettingsProposedFilter(e) => {
+                close_pending(&mut proposals, e.agent, ProposalState::Superseded, block_number, None);
+                proposals.push(Proposal {
+                    agent: e.agent,
+                    settings: e.agent_settings,
+                    proposer: Address::zero(),
+                    block_number,
+                    transaction_hash,
+                    state: ProposalState::Pending,
+                    closed_block: None,
+                    digest: None,
+                });
+                continue;
+            }
+            AgentManagerContractEvents::AgentSettingsUpdatedFilter(e) => (e.agent, ProposalState::Applied, Some(H256::from(e.digest))),
+            AgentManagerContractEvents::AgentRemovedFilter(e) => (e.agent, ProposalState::Removed, None),
+            _ => continue,
+        };
+        close_pending(&mut proposals, agent, closed_state, block_number, digest);
+    }
+    proposals
+}
+
+fn close_pending(proposals: &mut [Proposal], agent: Address, state: ProposalState, block: u64, digest: Option<H256>) {
+    if let Some(pending) = proposals
+        .iter_mut()
+        .rev()
+        .find(|proposal| proposal.agent == agent && proposal.state == ProposalState::Pending)
+    {
+        pending.state = state;
+        pending.closed_block = Some(block);
+        pending.digest = digest;
+    }
+}
+
+fn serialize_settings<S: Serializer>(settings: &AgentSettings, serializer: S) -> Result<S::Ok, S::Error> {
+    settings_json(settings).serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::Tokenizable;
+
+    use crate::svc::agent_manager_node::AgentHeader;
+
+    fn settings(threshold: u8) -> AgentSettings {
+        AgentSettings {
+            signers: vec![Address::repeat_byte(0x11), Address::repeat_byte(0x22)],
+            threshold,
+            converter_address: Address::zero(),
+            agent_header: AgentHeader { version: "1.0".to_string(), ttl: U256::from(3600), ..Default::default() },
+        }
+    }
+
+    fn log(topics: Vec<H256>, data: Vec<u8>, block: u64) -> Log {
+        Log {
+            address: Address::repeat_byte(2),
+            topics,
+            data: data.into(),
+            block_number: Some(block.into()),
+            transaction_hash: Some(H256::from_low_u64_be(block)),
+            log_index: Some(0.into()),
+            ..Default::default()
+        }
+    }
+
+    fn proposed(agent: Address, settings: AgentSettings, block: u64) -> Log {
+        log(vec![AgentSettingsProposedFilter::signature(), H256::from(agent)], ethers::abi::encode(&[settings.into_token()]), block)
+    }
+
+    #[test]
+    fn test_proposal_lifecycle() {
+        let (a, b) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        let updated = log(
+            vec![AgentSettingsUpdatedFilter::signature(), H256::from(a), H256::repeat_byte(0xdd)],
+            ethers::abi::encode(&[settings(2).into_token()]),
+            5,
+        );
+        let logs = vec![
+            proposed(b, settings(1), 6),
+            updated,
+            proposed(a, settings(1), 3),
+            proposed(a, settings(2), 4),
+            proposed(a, settings(1), 8),
+            log(vec![AgentRemovedFilter::signature(), H256::from(b)], Vec::new(), 7),
+        ];
+
+        let proposals = build_proposals(&logs);
+        let summary: Vec<(Address, u64, ProposalState, Option<u64>)> = proposals
+            .iter()
+            .map(|proposal| (proposal.agent, proposal.block_number, proposal.state, proposal.closed_block))
+            .collect();
+        assert_eq!(summary, vec![
+            (a, 3, ProposalState::Superseded, Some(4)),
+            (a, 4, ProposalState::Applied, Some(5)),
+            (b, 6, ProposalState::Removed, Some(7)),
+            (a, 8, ProposalState::Pending, None),
+        ]);
+        assert_eq!(proposals[1].digest, Some(H256::repeat_byte(0xdd)));
+        assert_eq!(diff_settings(&proposals[1].settings, &proposals[3].settings, true), vec!["threshold: 2 -> 1".to_string()]);
+        assert_eq!(serde_json::to_value(&proposals[3]).unwrap()["settings"]["threshold"], 1);
+    }
+
+    #[test]
+    fn test_applied_settings_from_receipt() {
+        let (agent, other) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        let updated = |agent: Address, settings: AgentSettings| log(
+            vec![AgentSettingsUpdatedFilter::signature(), H256::from(agent), H256::repeat_byte(0xdd)],
+            ethers::abi::encode(&[settings.into_token()]),
+            5,
+        );
+        let mut receipt = TransactionReceipt {
+            status: Some(U64::one()),
+            logs: vec![updated(other, settings(1)), updated(agent, settings(2))],
+            ..Default::default()
+        };
+        let manager = Address::repeat_byte(2);
+
+        assert_eq!(applied_settings(&receipt, manager, agent).unwrap(), (settings(2), H256::repeat_byte(0xdd)));
+        assert!(applied_settings(&receipt, Address::repeat_byte(3), agent).unwrap_err().contains("no AgentSettingsUpdated event"));
+        receipt.status = Some(U64::zero());
+        assert!(applied_settings(&receipt, manager, agent).unwrap_err().contains("reverted"));
+    }
+}
@@ -0,0 +1,215 @@
+use ethers::types::Address;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::core::ApiResult;
+use crate::svc::agent_manager_node::{settings_json, AgentManagerNode, AgentSettings};
+
+/// Acceptance rules for registering agents. Unset rules accept anything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewPolicy {
+    #[serde(default)]
+    pub min_threshold: Option<u8>,
+    /// Every signer must be in this list.
+    #[serde(default)]
+    pub allowed_signers: Option<Vec<Address>>,
+    /// Accepted `agent_header.version` values.
+    #[serde(default)]
+    pub versions: Option<Vec<String>>,
+}
+
+impl ReviewPolicy {
+    /// Why `settings` break the policy; empty when they pass.
+    pub fn check(&self, settings: &AgentSettings) -> Vec<String> {
+        let mut violations = Vec::new();
+        if let Some(min_threshold) = self.min_threshold {
+            if settings.threshold < min_threshold {
+                violations.push(format!("threshold {} is below {}", settings.threshold, min_threshold));
+            }
+        }
+        if let Some(allowed) = &self.allowed_signers {
+            for signer in settings.signers.iter().filter(|signer| !allowed.contains(signer)) {
+                violations.push(format!("signer {:?} is not allowed", signer));
+            }
+        }
+        if let Some(versions) = &self.versions {
+            if !versions.contains(&settings.agent_header.version) {
+                violations.push(format!("version {:?} is not accepted", settings.agent_header.version));
+            }
+        }
+        violations
+    }
+}
+
+/// A registering agent with the settings it submitted.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Applicant {
+    pub agent: Address,
+    /// The latest submitted config; `None` if the agent has none.
+    #[serde(serialize_with = "serialize_settings")]
+    pub settings: Option<AgentSettings>,
+    pub violations: Vec<String>,
+}
+
+impl Applicant {
+    pub fn passes(&self) -> bool {
+        self.settings.is_some() && self.violations.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewAction {
+    Accept,
+    Reject,
+    /// Not sent: the agent is not registering or breaks the policy.
+    Skip,
+}
+
+/// What happened to one agent in a batch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewReport {
+    pub agent: Address,
+    pub action: ReviewAction,
+    #[serde(default)]
+    pub reasons: Vec<String>,
+    /// Transaction receipt of the accept or reject.
+    pub receipt: Option<String>,
+    pub error: Option<String>,
+}
+
+/// The manager owner's queue of registering agents: list them with their submitted
+/// settings, check them against a policy, and accept (`accept_agent`) or reject
+/// (`remove_agent`) them in batches. A failure for one agent is reported and the batch
+/// goes on.
+pub struct ReviewQueue<'a> {
+    manager: &'a AgentManagerNode,
+    policy: ReviewPolicy,
+}
+
+impl<'a> ReviewQueue<'a> {
+    pub fn new(manager: &'a AgentManagerNode, policy: ReviewPolicy) -> Self {
+        Self { manager, policy }
+    }
+
+    pub async fn applicants(&self) -> ApiResult<Vec<Applicant>> {
+        let mut applicants = Vec::new();
+        for agent in self.manager.registering_agents().await? {
+            applicants.push(self.applicant(agent).await?);
+        }
+        Ok(applicants)
+    }
+
+    /// Accepts `agents` that are registering and pass the policy; the rest are skipped.
+    pub async fn accept(&self, agents: &[Address]) -> ApiResult<Vec<ReviewReport>> {
+        let registering = self.manager.registering_agents().await?;
+        let mut reports = Vec::new();
+        for agent in agents {
+            let report = if !registering.contains(agent) {
+                skipped(*agent, vec!["not registering".to_string()])
+            } else {
+                match self.applicant(*agent).await {
+                    Ok(applicant) if !applicant.passes() => skipped(*agent, applicant.violations),
+                    Ok(_) => sent(*agent, ReviewAction::Accept, self.manager.accept_agent(&format!("{:?}", agent)).await),
+                    Err(error) => sent(*agent, ReviewAction::Accept, Err(error)),
+                }
+            };
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+
+    /// Rejects `agents` that are registering. The policy is not consulted.
+    pub async fn reject(&self, agents: &[Address]) -> ApiResult<Vec<ReviewReport>> {
+        let registering = self.manager.registering_agents().await?;
+        let mut reports = Vec::new();
+        for agent in agents {
+            let report = if registering.contains(agent) {
+                sent(*agent, ReviewAction::Reject, self.manager.remove_agent(&format!("{:?}", agent)).await)
+            } else {
+                skipped(*agent, vec!["not registering".to_string()])
+            };
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+
+    /// Accepts every applicant that passes the policy, and rejects the others when
+    /// `reject_failing` is set.
+    pub async fn process(&self, reject_failing: bool) -> ApiResult<Vec<ReviewReport>> {
+        let mut reports = Vec::new();
+        for applicant in self.applicants().await? {
+            let agent = format!("{:?}", applicant.agent);
+            let report = if applicant.passes() {
+                sent(applicant.agent, ReviewAction::Accept, self.manager.accept_agent(&agent).await)
+            } else if reject_failing {
+                let mut report = sent(applicant.agent, ReviewAction::Reject, self.manager.remove_agent(&agent).await);
+                report.reasons = applicant.violations;
+                report
+            } else {
+                skipped(applicant.agent, applicant.violations)
+            };
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+
+    async fn applicant(&self, agent: Address) -> ApiResult<Applicant> {
+        let configs = self.manager.agent_configs(&format!("{:?}", agent)).await?;
+        // max_by_key returns the last of equal keys, so later entries win within a block
+        let settings = configs.into_iter().max_by_key(|config| config.config_block_number).map(|config| config.settings);
+        let violations = match &settings {
+            Some(settings) => self.policy.check(settings),
+            None => vec!["no submitted config".to_string()],
+        };
+        Ok(Applicant { agent, settings, violations })
+    }
+}
+
+fn skipped(agent: Address, reasons: Vec<String>) -> ReviewReport {
+    ReviewReport { agent, action: ReviewAction::Skip, reasons, receipt: None, error: None }
+}
+
+fn sent(agent: Address, action: ReviewAction, result: ApiResult<String>) -> ReviewReport {
+    let (receipt, error) = match result {
+        Ok(receipt) => (Some(receipt), None),
+        Err(error) => (None, Some(error)),
+    };
+    ReviewReport { agent, action, reasons: Vec::new(), receipt, error }
+}
+
+fn serialize_settings<S: Serializer>(settings: &Option<AgentSettings>, serializer: S) -> Result<S::Ok, S::Error> {
+    settings.as_ref().map(settings_json).serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::svc::agent_manager_node::AgentHeader;
+
+    #[test]
+    fn test_policy_violations() {
+        let (a, b, c) = (Address::repeat_byte(0xa1), Address::repeat_byte(0xa2), Address::repeat_byte(0xa3));
+        let settings = AgentSettings {
+            signers: vec![a, c],
+            threshold: 1,
+            converter_address: Address::zero(),
+            agent_header: AgentHeader { version: "0.9".to_string(), ..Default::default() },
+        };
+        assert!(ReviewPolicy::default().check(&settings).is_empty());
+
+        let policy = ReviewPolicy {
+            min_threshold: Some(2),
+            allowed_signers: Some(vec![a, b]),
+            versions: Some(vec!["1.0".to_string()]),
+        };
+        assert_eq!(policy.check(&settings), vec![
+            "threshold 1 is below 2".to_string(),
+            format!("signer {:?} is not allowed", c),
+            "version \"0.9\" is not accepted".to_string(),
+        ]);
+
+        let applicant = Applicant { agent: a, settings: Some(settings), violations: Vec::new() };
+        assert!(applicant.passes());
+        assert!(!Applicant { settings: None, ..applicant }.passes());
+    }
+}
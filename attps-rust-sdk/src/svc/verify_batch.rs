@@ -0,0 +1,556 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ethers::prelude::*;
+use ethers::types::{Address, H256, U256};
+use futures::future::{BoxFuture, FutureExt};
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{ApiResult, SignerClient};
+use crate::svc::agent_proxy_node::{parse_verify_args, AgentProxyNode, MessagePayload};
+use crate::utils::common::{decode_revert_data, open_journal};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyRequest {
+    pub id: String,
+    pub agent: String,
+    pub settings_digest: String,
+    pub data: String,
+    pub data_hash: String,
+    pub signature_proof: String,
+    pub zk_proof: String,
+    pub merkle_proof: String,
+    pub content_type: String,
+    pub encoding: String,
+    pub compression: String,
+}
+
+impl VerifyRequest {
+    fn parse(&self) -> ApiResult<(Address, [u8; 32], MessagePayload)> {
+        parse_verify_args(
+            &self.agent,
+            &self.settings_digest,
+            &self.data,
+            &self.data_hash,
+            &self.signature_proof,
+            &self.zk_proof,
+            &self.merkle_proof,
+            &self.content_type,
+            &self.encoding,
+            &self.compression,
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum VerifyOutcome {
+    Confirmed { tx_hash: H256, block_number: u64, gas_used: Option<U256> },
+    Reverted { tx_hash: Option<H256>, reason: String },
+    Dropped { tx_hash: H256 },
+    /// No receipt within the receipt timeout. It is not journaled as finished, so a
+    /// resumed `submit` waits on the transaction again.
+    Pending { tx_hash: H256 },
+    Failed { error: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyReport {
+    pub id: String,
+    pub outcome: VerifyOutcome,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum JournalEntry {
+    Sent { id: String, tx_hash: H256, nonce: U256 },
+    Finished { id: String, outcome: VerifyOutcome },
+}
+
+const DEFAULT_RECEIPT_TIMEOUT: Duration = Duration::from_secs(600);
+const MAX_RESENDS: u32 = 3;
+
+type InFlight<'b> = FuturesUnordered<BoxFuture<'b, (&'b VerifyRequest, U256, VerifyOutcome)>>;
+
+struct Progress<'b> {
+    journal: Option<File>,
+    outcomes: HashMap<String, VerifyOutcome>,
+    resends: HashMap<&'b str, u32>,
+    in_flight: InFlight<'b>,
+}
+
+impl Progress<'_> {
+    fn finish(&mut self, id: &str, outcome: VerifyOutcome) -> ApiResult<()> {
+        write_journal(&mut self.journal, &JournalEntry::Finished { id: id.to_string(), outcome: outcome.clone() })?;
+        self.outcomes.insert(id.to_string(), outcome);
+        Ok(())
+    }
+}
+
+/// Pipelines `verify` transactions from the node's wallet with locally assigned nonces.
+///
+/// Every send and every final outcome is appended to the journal, so calling `submit`
+/// again with the same requests after a restart waits on the transactions already in
+/// flight and skips the finished ones instead of sending them twice.
+///
+/// A dropped transaction leaves a nonce gap that stalls every later one, so it is sent
+/// again with the same nonce, up to three times. Transactions still unmined after the
+/// receipt timeout are reported as pending.
+pub struct BatchVerifier<'a> {
+    node: &'a AgentProxyNode,
+    max_in_flight: usize,
+    journal_path: Option<PathBuf>,
+    receipt_timeout: Duration,
+    poll_interval: Option<Duration>,
+}
+
+impl<'a> BatchVerifier<'a> {
+    pub fn new(node: &'a AgentProxyNode, max_in_flight: usize, journal_path: Option<&str>) -> Self {
+        Self {
+            node,
+            max_in_flight: max_in_flight.max(1),
+            journal_path: journal_path.map(PathBuf::from),
+            receipt_timeout: DEFAULT_RECEIPT_TIMEOUT,
+            poll_interval: None,
+        }
+    }
+
+    /// How long to wait for each receipt; ten minutes by default.
+    pub fn with_receipt_timeout(mut self, receipt_timeout: Duration) -> Self {
+        self.receipt_timeout = receipt_timeout;
+        self
+    }
+
+    /// How often to poll for receipts; the provider's interval by default.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = Some(poll_interval);
+        self
+    }
+
+    /// Fails without sending anything when two requests share an id.
+    pub async fn submit<'b>(&'b self, requests: &'b [VerifyRequest]) -> ApiResult<Vec<VerifyReport>> {
+        let mut ids = HashSet::new();
+        if let Some(request) = requests.iter().find(|request| !ids.insert(request.id.as_str())) {
+            return Err(format!("Duplicate request id in batch: {}", request.id));
+        }
+
+        let (sent, outcomes) = match &self.journal_path {
+            Some(path) => read_journal(path)?,
+            None => (HashMap::new(), HashMap::new()),
+        };
+        let journal = match &self.journal_path {
+            Some(path) => Some(open_journal(path).map_err(|e| format!("Failed to open verify journal: {}", e))?),
+            None => None,
+        };
+        let mut progress = Progress { journal, outcomes, resends: HashMap::new(), in_flight: FuturesUnordered::new() };
+
+        // transactions sent before a restart are awaited again instead of being resent
+        for request in requests {
+            if progress.outcomes.contains_key(&request.id) {
+                continue;
+            }
+            if let Some((tx_hash, nonce)) = sent.get(&request.id) {
+                progress.in_flight.push(self.await_outcome(request, *tx_hash, *nonce).boxed());
+            }
+        }
+
+        let mut nonce = self.pending_nonce().await?;
+
+        for request in requests {
+            if progress.outcomes.contains_key(&request.id) || sent.contains_key(&request.id) {
+                continue;
+            }
+
+            while progress.in_flight.len() >= self.max_in_flight {
+                if let Some(landed) = progress.in_flight.next().await {
+                    self.settle(landed, &mut progress).await?;
+                }
+            }
+
+            // a payload that fails estimation never gets a nonce, so it cannot stall the ones behind it
+            let contract_call = match self.prepare(request).await {
+                Ok(contract_call) => contract_call,
+                Err(outcome) => {
+                    progress.finish(&request.id, outcome)?;
+                    continue;
+                }
+            };
+
+            if self.send(request, contract_call, nonce, &mut progress).await? {
+                nonce += U256::one();
+            } else {
+                // the node may or may not have accepted the nonce, so ask it again
+                nonce = self.pending_nonce().await?;
+            }
+        }
+
+        while let Some(landed) = progress.in_flight.next().await {
+            self.settle(landed, &mut progress).await?;
+        }
+
+        Ok(requests
+            .iter()
+            .map(|request| {
+                let outcome = progress.outcomes.remove(&request.id).unwrap_or(VerifyOutcome::Failed {
+                    error: "No outcome recorded".to_string(),
+                });
+                VerifyReport { id: request.id.clone(), outcome }
+            })
+            .collect())
+    }
+
+    /// Records the outcome of a transaction, or sends it again in place when it was dropped.
+    async fn settle<'b>(
+        &'b self,
+        (request, nonce, outcome): (&'b VerifyRequest, U256, VerifyOutcome),
+        progress: &mut Progress<'b>,
+    ) -> ApiResult<()> {
+        match outcome {
+            VerifyOutcome::Dropped { tx_hash } if progress.resends.get(request.id.as_str()).copied().unwrap_or(0) < MAX_RESENDS => {
+                *progress.resends.entry(request.id.as_str()).or_default() += 1;
+                tracing::warn!(id = %request.id, ?tx_hash, %nonce, "Verify transaction dropped, resending with the same nonce");
+                match self.prepare(request).await {
+                    Ok(contract_call) => {
+                        self.send(request, contract_call, nonce, progress).await?;
+                    }
+                    Err(outcome) => progress.finish(&request.id, outcome)?,
+                }
+            }
+            VerifyOutcome::Pending { .. } => {
+                progress.outcomes.insert(request.id.clone(), outcome);
+            }
+            outcome => progress.finish(&request.id, outcome)?,
+        }
+        Ok(())
+    }
+
+    async fn prepare(&self, request: &VerifyRequest) -> Result<ContractCall<SignerClient, ()>, VerifyOutcome> {
+        let (agent, settings_digest, message_payload) = request.parse().map_err(|error| VerifyOutcome::Failed { error })?;
+
+        let contract_call = self.node.contract().verify(agent, settings_digest, message_payload);
+        match contract_call.estimate_gas().await {
+            Ok(gas_estimate) => Ok(contract_call.gas(gas_estimate)),
+            Err(e) => Err(match e.as_revert() {
+                Some(revert_data) => VerifyOutcome::Reverted {
+                    tx_hash: None,
+                    reason: decode_revert_data(revert_data),
+                },
+                None => VerifyOutcome::Failed { error: format!("Failed to estimate gas: {:?}", e) },
+            }),
+        }
+    }
+
+    /// Sends `contract_call` with `nonce`; false when the send failed and the failure was recorded.
+    async fn send<'b>(
+        &'b self,
+        request: &'b VerifyRequest,
+        contract_call: ContractCall<SignerClient, ()>,
+        nonce: U256,
+        progress: &mut Progress<'b>,
+    ) -> ApiResult<bool> {
+        let contract_call = contract_call.nonce(nonce);
+        match contract_call.send().await.map(|pending_tx| pending_tx.tx_hash()) {
+            Ok(tx_hash) => {
+                write_journal(&mut progress.journal, &JournalEntry::Sent { id: request.id.clone(), tx_hash, nonce })?;
+                progress.in_flight.push(self.await_outcome(request, tx_hash, nonce).boxed());
+                Ok(true)
+            }
+            Err(e) => {
+                progress.finish(&request.id, VerifyOutcome::Failed { error: format!("Failed to send transaction: {:?}", e) })?;
+                Ok(false)
+            }
+        }
+    }
+
+    async fn pending_nonce(&self) -> ApiResult<U256> {
+        let client = self.node.contract().client();
+        client
+            .get_transaction_count(client.address(), Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(|e| format!("Failed to get nonce: {:?}", e))
+    }
+
+    async fn await_outcome<'b>(
+        &'b self,
+        request: &'b VerifyRequest,
+        tx_hash: H256,
+        nonce: U256,
+    ) -> (&'b VerifyRequest, U256, VerifyOutcome) {
+        let client = self.node.contract().client();
+        let mut pending_tx = PendingTransaction::new(tx_hash, client.provider());
+        if let Some(poll_interval) = self.poll_interval {
+            pending_tx = pending_tx.interval(poll_interval);
+        }
+
+        let outcome = match tokio::time::timeout(self.receipt_timeout, pending_tx).await {
+            Ok(Ok(Some(receipt))) if receipt.status == Some(U64::one()) => VerifyOutcome::Confirmed {
+                tx_hash,
+                block_number: receipt.block_number.unwrap_or_default().as_u64(),
+                gas_used: receipt.gas_used,
+            },
+            Ok(Ok(Some(receipt))) => {
+                let block_number = receipt.block_number.unwrap_or_default().as_u64();
+                VerifyOutcome::Reverted {
+                    tx_hash: Some(tx_hash),
+                    reason: self.replay_revert(request, block_number).await,
+                }
+            }
+            Ok(Ok(None)) => VerifyOutcome::Dropped { tx_hash },
+            Ok(Err(e)) => VerifyOutcome::Failed { error: format!("Transaction failed: {:?}", e) },
+            Err(_) => VerifyOutcome::Pending { tx_hash },
+        };
+
+        (request, nonce, outcome)
+    }
+
+    // receipts carry no revert data, so the call is replayed against the parent block
+    async fn replay_revert(&self, request: &VerifyRequest, block_number: u64) -> String {
+        let (agent, settings_digest, message_payload) = match request.parse() {
+            Ok(args) => args,
+            Err(e) => return e,
+        };
+
+        let contract_call = self.node.contract()
+            .verify(agent, settings_digest, message_payload)
+            .block(block_number.saturating_sub(1));
+
+        match contract_call.call().await {
+            Ok(()) => "execution reverted (not reproducible by replay)".to_string(),
+            Err(e) => match e.as_revert() {
+                Some(revert_data) => decode_revert_data(revert_data),
+                None => format!("execution reverted (replay failed: {:?})", e),
+            },
+        }
+    }
+}
+
+type JournalState = (HashMap<String, (H256, U256)>, HashMap<String, VerifyOutcome>);
+
+fn read_journal(path: &Path) -> ApiResult<JournalState> {
+    let mut sent = HashMap::new();
+    let mut finished = HashMap::new();
+
+    if !path.exists() {
+        return Ok((sent, finished));
+    }
+
+    let file = File::open(path).map_err(|e| format!("Failed to open verify journal: {}", e))?;
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("Failed to read verify journal: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        // a torn last line from a crash is skipped; open_journal cuts it off before writing
+        match serde_json::from_str::<JournalEntry>(&line) {
+            Ok(JournalEntry::Sent { id, tx_hash, nonce }) => {
+                sent.insert(id, (tx_hash, nonce));
+            }
+            Ok(JournalEntry::Finished { id, outcome }) => {
+                finished.insert(id, outcome);
+            }
+            Err(_) => continue,
+        }
+    }
+
+    Ok((sent, finished))
+}
+
+fn write_journal(journal: &mut Option<File>, entry: &JournalEntry) -> ApiResult<()> {
+    let Some(file) = journal else {
+        return Ok(());
+    };
+
+    let line = serde_json::to_string(entry).map_err(|e| format!("Failed to encode verify journal entry: {}", e))?;
+    writeln!(file, "{}", line)
+        .and_then(|_| file.sync_data())
+        .map_err(|e| format!("Failed to write verify journal: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    use ethers::types::transaction::eip2718::TypedTransaction;
+    use ethers::utils::{keccak256, rlp::Rlp};
+    use serde_json::{json, Value};
+
+    use crate::core::transport::AttpsTransport;
+    use crate::utils::mock_server::{MockRequest, MockResponse, MockServer};
+    use crate::utils::secret::SecretKey;
+
+    /// Mines pooled transactions in nonce order and drops the first `drops` sends of nonce 5.
+    #[derive(Default)]
+    struct Node {
+        drops: usize,
+        sends: usize,
+        pool: BTreeMap<u64, H256>,
+        mined: HashMap<H256, (u64, u64)>,
+    }
+
+    impl Node {
+        fn respond(&mut self, method: &str, params: &Value) -> Value {
+            match method {
+                "eth_chainId" => json!("0x7a69"),
+                "eth_getTransactionCount" => json!("0x5"),
+                "eth_estimateGas" => json!("0x30000"),
+                "eth_gasPrice" | "eth_maxPriorityFeePerGas" => json!("0x3b9aca00"),
+                "eth_blockNumber" => json!("0x10"),
+                "eth_getBlockByNumber" => json!(Block::<H256> {
+                    number: Some(16.into()),
+                    base_fee_per_gas: Some(1_000_000_000u64.into()),
+                    ..Default::default()
+                }),
+                "eth_feeHistory" => json!({
+                    "oldestBlock": "0x1",
+                    "baseFeePerGas": ["0x3b9aca00", "0x3b9aca00"],
+                    "gasUsedRatio": [0.5],
+                    "reward": [["0x3b9aca00"]],
+                }),
+                "eth_sendRawTransaction" => {
+                    let raw = hex::decode(params[0].as_str().unwrap().trim_start_matches("0x")).unwrap();
+                    let (tx, _) = TypedTransaction::decode_signed(&Rlp::new(&raw)).unwrap();
+                    let (hash, nonce) = (H256::from(keccak256(&raw)), tx.nonce().unwrap().as_u64());
+                    self.sends += 1;
+                    if nonce == 5 && self.drops > 0 {
+                        self.drops -= 1;
+                    } else {
+                        self.pool.insert(nonce, hash);
+                    }
+                    let mut next = 5 + self.mined.len() as u64;
+                    while let Some(hash) = self.pool.remove(&next) {
+                        self.mined.insert(hash, (next, 20 + next));
+                        next += 1;
+                    }
+                    json!(hash)
+                }
+                "eth_getTransactionByHash" => {
+                    let hash: H256 = serde_json::from_value(params[0].clone()).unwrap();
+                    let block_number = match self.mined.get(&hash) {
+                        Some((_, block)) => Some(U64::from(*block)),
+                        None if self.pool.values().any(|pooled| *pooled == hash) => None,
+                        None => return Value::Null,
+                    };
+                    json!(Transaction { hash, block_number, ..Default::default() })
+                }
+                "eth_getTransactionReceipt" => {
+                    let hash: H256 = serde_json::from_value(params[0].clone()).unwrap();
+                    match self.mined.get(&hash) {
+                        Some((_, block)) => json!(TransactionReceipt {
+                            transaction_hash: hash,
+                            block_number: Some((*block).into()),
+                            status: Some(1.into()),
+                            gas_used: Some(50_000.into()),
+                            ..Default::default()
+                        }),
+                        None => Value::Null,
+                    }
+                }
+                _ => Value::Null,
+            }
+        }
+    }
+
+    fn request(id: &str) -> VerifyRequest {
+        VerifyRequest {
+            id: id.to_string(),
+            agent: format!("{:?}", Address::repeat_byte(0xaa)),
+            settings_digest: format!("{:?}", H256::repeat_byte(1)),
+            data: "1234".to_string(),
+            data_hash: format!("{:?}", H256::repeat_byte(2)),
+            signature_proof: "0x".to_string(),
+            zk_proof: "0x".to_string(),
+            merkle_proof: "0x".to_string(),
+            content_type: "json".to_string(),
+            encoding: "utf-8".to_string(),
+            compression: "none".to_string(),
+        }
+    }
+
+    async fn mock_node(drops: usize) -> (MockServer, Arc<Mutex<Node>>, AgentProxyNode) {
+        let node = Arc::new(Mutex::new(Node { drops, ..Default::default() }));
+        let state = node.clone();
+        let server = MockServer::start(Arc::new(move |request: MockRequest| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let result = state.lock().unwrap().respond(body["method"].as_str().unwrap_or_default(), &body["params"]);
+            let response = json!({ "jsonrpc": "2.0", "id": body["id"], "result": result });
+            MockResponse::new(200, "application/json", response.to_string())
+        }))
+        .await
+        .unwrap();
+        let proxy = AgentProxyNode::try_new_with_transport(
+            AttpsTransport::from_urls(&server.url()).unwrap(),
+            "0x0000000000000000000000000000000000000001",
+            SecretKey::from("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80").to_wallet().unwrap(),
+        )
+        .await
+        .unwrap();
+        (server, node, proxy)
+    }
+
+    #[tokio::test]
+    async fn test_submit_fills_dropped_nonce() {
+        let (_server, node, proxy) = mock_node(1).await;
+        let requests = vec![request("a"), request("b"), request("c")];
+
+        let duplicate = vec![request("a"), request("b"), request("a")];
+        let verifier = BatchVerifier::new(&proxy, 3, None).with_poll_interval(Duration::from_millis(10));
+        assert_eq!(verifier.submit(&duplicate).await.unwrap_err(), "Duplicate request id in batch: a");
+        assert_eq!(node.lock().unwrap().sends, 0);
+
+        // a's first send is dropped, which holds b and c back until a is sent again
+        let reports = verifier.submit(&requests).await.unwrap();
+        let blocks: Vec<Option<u64>> = reports
+            .iter()
+            .map(|report| match report.outcome {
+                VerifyOutcome::Confirmed { block_number, .. } => Some(block_number),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(blocks, vec![Some(25), Some(26), Some(27)]);
+        assert_eq!(node.lock().unwrap().sends, 4);
+
+        // when a never lands, b and c are reported as pending instead of waiting forever
+        let (_server, node, proxy) = mock_node(usize::MAX).await;
+        let reports = BatchVerifier::new(&proxy, 3, None)
+            .with_poll_interval(Duration::from_millis(10))
+            .with_receipt_timeout(Duration::from_millis(500))
+            .submit(&requests)
+            .await
+            .unwrap();
+        assert!(matches!(reports[0].outcome, VerifyOutcome::Dropped { .. }));
+        assert!(matches!(reports[1].outcome, VerifyOutcome::Pending { .. }));
+        assert!(matches!(reports[2].outcome, VerifyOutcome::Pending { .. }));
+        assert_eq!(node.lock().unwrap().sends, 3 + MAX_RESENDS as usize);
+    }
+
+    #[test]
+    fn test_journal_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("verify.jsonl");
+        let mut journal = Some(open_journal(&path).unwrap());
+
+        let tx_hash = H256::repeat_byte(0x11);
+        let confirmed = VerifyOutcome::Confirmed { tx_hash, block_number: 7, gas_used: Some(U256::from(151844)) };
+        write_journal(&mut journal, &JournalEntry::Sent { id: "a".to_string(), tx_hash, nonce: U256::from(3) }).unwrap();
+        write_journal(&mut journal, &JournalEntry::Sent { id: "b".to_string(), tx_hash: H256::repeat_byte(0x22), nonce: U256::from(4) }).unwrap();
+        write_journal(&mut journal, &JournalEntry::Finished { id: "a".to_string(), outcome: confirmed.clone() }).unwrap();
+        std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"event\":\"sen").unwrap();
+
+        // reopened after the crash: the next entry must not land on the torn line
+        let mut journal = Some(open_journal(&path).unwrap());
+        write_journal(&mut journal, &JournalEntry::Sent { id: "c".to_string(), tx_hash: H256::repeat_byte(0x33), nonce: U256::from(5) }).unwrap();
+
+        let (sent, finished) = read_journal(&path).unwrap();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(sent["c"], (H256::repeat_byte(0x33), U256::from(5)));
+        assert_eq!(sent["b"], (H256::repeat_byte(0x22), U256::from(4)));
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished["a"], confirmed);
+    }
+}
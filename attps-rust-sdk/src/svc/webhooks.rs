@@ -0,0 +1,591 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ethers::abi::RawLog;
+use ethers::contract::EthLogDecode;
+use ethers::prelude::*;
+use futures::StreamExt;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use crate::core::events::LogStream;
+use crate::core::transport::AttpsTransport;
+use crate::core::ApiResult;
+use crate::svc::agent_manager_node::{settings_json, AgentManagerContractEvents};
+use crate::svc::agent_proxy_node::AgentProxyContractEvents;
+use crate::svc::client::AttpsClient;
+use crate::utils::secret::SecretKey;
+
+pub const EVENT_KINDS: [&str; 10] = [
+    "agent_registered",
+    "agent_accepted",
+    "agent_removed",
+    "agent_settings_proposed",
+    "agent_settings_updated",
+    "agent_proxy_set",
+    "agent_manager_set",
+    "agent_factory_set",
+    "ownership_transfer_requested",
+    "ownership_transferred",
+];
+
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// The `WebhookEvent` as JSON.
+    #[default]
+    Json,
+    /// `{"text": ...}` for Slack incoming webhooks.
+    Slack,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    /// Key for the `X-Attps-Signature` HMAC.
+    pub secret: SecretKey,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Event kinds to send, from `EVENT_KINDS`; all when empty.
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+/// A registry event as delivered to webhooks. `id` is unique per log, so receivers can
+/// drop the duplicates that at-least-once delivery allows.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    pub id: String,
+    pub kind: String,
+    pub contract: Address,
+    pub block_number: u64,
+    pub transaction_hash: H256,
+    pub log_index: U256,
+    pub data: Value,
+}
+
+impl WebhookEvent {
+    /// Decodes a log of the given AgentManager or AgentProxy. Other logs give `None`.
+    pub fn from_log(manager: Address, proxy: Address, log: &Log) -> Option<Self> {
+        let raw = RawLog::from(log.clone());
+        let (kind, data) = if log.address == manager {
+            match AgentManagerContractEvents::decode_log(&raw).ok()? {
+                AgentManagerContractEvents::AgentRegisteredFilter(e) => {
+                    ("agent_registered", json!({ "agent": e.agent, "settings": settings_json(&e.agent_settings) }))
+                }
+                AgentManagerContractEvents::AgentAcceptedFilter(e) => (
+                    "agent_accepted",
+                    json!({ "agent": e.agent, "digest": H256::from(e.digest), "settings": settings_json(&e.agent_settings) }),
+                ),
+                AgentManagerContractEvents::AgentRemovedFilter(e) => ("agent_removed", json!({ "agent": e.agent })),
+                AgentManagerContractEvents::AgentSettingsProposedFilter(e) => {
+                    ("agent_settings_proposed", json!({ "agent": e.agent, "settings": settings_json(&e.agent_settings) }))
+                }
+                AgentManagerContractEvents::AgentSettingsUpdatedFilter(e) => (
+                    "agent_settings_updated",
+                    json!({ "agent": e.agent, "digest": H256::from(e.digest), "settings": settings_json(&e.agent_settings) }),
+                ),
+                AgentManagerContractEvents::AgentProxySetFilter(e) => {
+                    ("agent_proxy_set", json!({ "old": e.old_proxy, "new": e.new_proxy }))
+                }
+                AgentManagerContractEvents::OwnershipTransferRequestedFilter(e) => {
+                    ("ownership_transfer_requested", json!({ "from": e.from, "to": e.to }))
+                }
+                AgentManagerContractEvents::OwnershipTransferredFilter(e) => {
+                    ("ownership_transferred", json!({ "from": e.from, "to": e.to }))
+                }
+            }
+        } else if log.address == proxy {
+            match AgentProxyContractEvents::decode_log(&raw).ok()? {
+                AgentProxyContractEvents::AgentFactorySetFilter(e) => {
+                    ("agent_factory_set", json!({ "old": e.old_factory, "new": e.new_factory }))
+                }
+                AgentProxyContractEvents::AgentManagerSetFilter(e) => {
+                    ("agent_manager_set", json!({ "old": e.old_manager, "new": e.new_manager }))
+                }
+                AgentProxyContractEvents::OwnershipTransferRequestedFilter(e) => {
+                    ("ownership_transfer_requested", json!({ "from": e.from, "to": e.to }))
+                }
+                AgentProxyContractEvents::OwnershipTransferredFilter(e) => {
+                    ("ownership_transferred", json!({ "from": e.from, "to": e.to }))
+                }
+            }
+        } else {
+            return None;
+        };
+
+        let (block_number, transaction_hash, log_index) = (log.block_number?, log.transaction_hash?, log.log_index?);
+        Some(Self {
+            id: format!("{:?}-{}", transaction_hash, log_index),
+            kind: kind.to_string(),
+            contract: log.address,
+            block_number: block_number.as_u64(),
+            transaction_hash,
+            log_index,
+            data,
+        })
+    }
+
+    /// One line for chat notifications.
+    pub fn summary(&self) -> String {
+        let subject = match (&self.data["agent"], &self.data["new"], &self.data["to"]) {
+            (Value::String(agent), _, _) => format!("agent {}", agent),
+            (_, Value::String(new), _) => format!("now {}", new),
+            (_, _, Value::String(to)) => format!("to {}", to),
+            _ => String::new(),
+        };
+        format!(
+            "{} on {:?}: {} (block {}, tx {:?})",
+            self.kind, self.contract, subject, self.block_number, self.transaction_hash
+        )
+    }
+}
+
+/// `X-Attps-Signature` for a body: hex HMAC-SHA256 of `"{timestamp}.{body}"`, prefixed
+/// with `sha256=`.
+pub fn webhook_signature(secret: &SecretKey, timestamp: u64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Checks a received webhook against `X-Attps-Timestamp` and `X-Attps-Signature`.
+pub fn verify_webhook_signature(secret: &SecretKey, timestamp: u64, body: &[u8], signature: &str) -> bool {
+    let Some(signature) = signature.strip_prefix("sha256=").and_then(|hex| hex::decode(hex).ok()) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Delivery {
+    url: String,
+    event: WebhookEvent,
+    attempts: u32,
+    /// Unix time in milliseconds.
+    not_before: u64,
+}
+
+/// A delivery that failed `max_attempts` times.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub url: String,
+    pub event: WebhookEvent,
+    pub attempts: u32,
+    /// The last failure.
+    pub error: String,
+    /// Unix time in milliseconds.
+    pub failed_at: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DispatchState {
+    /// Block to resume the event stream from.
+    next_block: Option<u64>,
+    pending: VecDeque<Delivery>,
+    #[serde(default)]
+    dead_letters: Vec<DeadLetter>,
+}
+
+/// POSTs registry events to webhook endpoints.
+///
+/// Every event is queued for each endpoint that wants it, and failed deliveries are
+/// retried with exponential backoff; deliveries that run out of attempts are kept as
+/// dead letters. With a state file the queue, the dead letters and the stream position
+/// survive restarts: events are queued before the position moves past their block, so
+/// each one is delivered at least once.
+///
+/// Events are sent as soon as they are seen, without waiting for confirmations. When a
+/// reorg removes a log, deliveries of its event that have not been sent yet are
+/// cancelled, but one that was already sent cannot be recalled.
+pub struct WebhookDispatcher {
+    endpoints: Vec<WebhookEndpoint>,
+    http: reqwest::Client,
+    state_path: Option<PathBuf>,
+    max_attempts: u32,
+    retry_delay: Duration,
+    state: Mutex<DispatchState>,
+}
+
+impl WebhookDispatcher {
+    pub fn new(endpoints: Vec<WebhookEndpoint>) -> ApiResult<Self> {
+        for endpoint in &endpoints {
+            reqwest::Url::parse(&endpoint.url).map_err(|e| format!("Invalid webhook URL {}: {}", endpoint.url, e))?;
+            if endpoint.secret.expose_secret().is_empty() {
+                return Err(format!("Webhook {} needs a secret", endpoint.url));
+            }
+            if let Some(kind) = endpoint.events.iter().find(|kind| !EVENT_KINDS.contains(&kind.as_str())) {
+                return Err(format!("Unknown webhook event \"{}\"", kind));
+            }
+        }
+        let http = reqwest::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+        Ok(Self {
+            endpoints,
+            http,
+            state_path: None,
+            max_attempts: 12,
+            retry_delay: Duration::from_secs(1),
+            state: Mutex::new(DispatchState::default()),
+        })
+    }
+
+    /// Keeps the queue and stream position in `path`, loading them if the file exists.
+    pub fn with_state_file(mut self, path: impl AsRef<Path>) -> ApiResult<Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read webhook state {}: {}", path.display(), e))?;
+            let state = serde_json::from_str(&contents)
+                .map_err(|e| format!("Invalid webhook state {}: {}", path.display(), e))?;
+            self.state = Mutex::new(state);
+        }
+        self.state_path = Some(path.to_path_buf());
+        Ok(self)
+    }
+
+    /// A delivery becomes a dead letter after `max_attempts`; retries wait `delay`,
+    /// doubling each time up to five minutes.
+    pub fn with_retries(mut self, max_attempts: u32, delay: Duration) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Deliveries waiting to be sent or retried.
+    pub fn pending(&self) -> usize {
+        self.state.lock().unwrap().pending.len()
+    }
+
+    /// Deliveries that ran out of attempts, oldest first.
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.state.lock().unwrap().dead_letters.clone()
+    }
+
+    /// Queues every dead letter again with a fresh attempt count and returns how many.
+    pub fn retry_dead_letters(&self) -> ApiResult<usize> {
+        let mut state = self.state.lock().unwrap();
+        let dead_letters = std::mem::take(&mut state.dead_letters);
+        let count = dead_letters.len();
+        state.pending.extend(dead_letters.into_iter().map(|dead| Delivery {
+            url: dead.url,
+            event: dead.event,
+            attempts: 0,
+            not_before: 0,
+        }));
+        self.save(&state)?;
+        Ok(count)
+    }
+
+    /// Forgets the dead letters and returns how many there were.
+    pub fn clear_dead_letters(&self) -> ApiResult<usize> {
+        let mut state = self.state.lock().unwrap();
+        let count = std::mem::take(&mut state.dead_letters).len();
+        self.save(&state)?;
+        Ok(count)
+    }
+
+    /// Queues `event` for every endpoint that wants it.
+    pub fn enqueue(&self, event: &WebhookEvent) -> ApiResult<()> {
+        let mut state = self.state.lock().unwrap();
+        for endpoint in &self.endpoints {
+            if endpoint.events.is_empty() || endpoint.events.contains(&event.kind) {
+                state.pending.push_back(Delivery {
+                    url: endpoint.url.clone(),
+                    event: event.clone(),
+                    attempts: 0,
+                    not_before: 0,
+                });
+            }
+        }
+        state.next_block = Some(state.next_block.unwrap_or_default().max(event.block_number));
+        self.save(&state)
+    }
+
+    /// Sends every delivery that is due and returns how long until the next retry.
+    pub async fn deliver_due(&self) -> ApiResult<Option<Duration>> {
+        let now = unix_millis();
+        let due: VecDeque<Delivery> = {
+            let mut state = self.state.lock().unwrap();
+            let (due, waiting) = std::mem::take(&mut state.pending).into_iter().partition(|d| d.not_before <= now);
+            state.pending = waiting;
+            due
+        };
+
+        let (mut retries, mut dead_letters) = (Vec::new(), Vec::new());
+        for mut delivery in due {
+            let Some(endpoint) = self.endpoints.iter().find(|endpoint| endpoint.url == delivery.url) else {
+                tracing::warn!(url = %delivery.url, "Dropping delivery for a removed webhook");
+                continue;
+            };
+            match self.send(endpoint, &delivery.event).await {
+                Ok(()) => tracing::debug!(url = %endpoint.url, id = %delivery.event.id, "Webhook delivered"),
+                Err(e) => {
+                    delivery.attempts += 1;
+                    if delivery.attempts >= self.max_attempts {
+                        tracing::error!(url = %endpoint.url, id = %delivery.event.id, error = %e, "Webhook failed {} times, keeping it as a dead letter", delivery.attempts);
+                        dead_letters.push(DeadLetter {
+                            url: delivery.url,
+                            event: delivery.event,
+                            attempts: delivery.attempts,
+                            error: e,
+                            failed_at: unix_millis(),
+                        });
+                        continue;
+                    }
+                    let delay = self.retry_delay.saturating_mul(1 << (delivery.attempts - 1).min(16)).min(MAX_RETRY_DELAY);
+                    tracing::warn!(url = %endpoint.url, id = %delivery.event.id, error = %e, "Webhook failed, retrying in {:?}", delay);
+                    delivery.not_before = unix_millis() + delay.as_millis() as u64;
+                    retries.push(delivery);
+                }
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.pending.extend(retries);
+        state.dead_letters.extend(dead_letters);
+        self.save(&state)?;
+        let now = unix_millis();
+        Ok(state.pending.iter().map(|d| Duration::from_millis(d.not_before.saturating_sub(now))).min())
+    }
+
+    /// Follows the client's AgentManager and AgentProxy events and delivers them until
+    /// the stream fails. Starts where the state file left off, else at `from_block`,
+    /// else at the current head.
+    pub async fn run(&self, client: &AttpsClient, from_block: Option<u64>) -> ApiResult<()> {
+        let deployment = client.deployment();
+        let manager = deployment.agent_manager.ok_or("The deployment has no AgentManager address")?;
+        let proxy = deployment.agent_proxy;
+        let start = self.state.lock().unwrap().next_block.or(from_block);
+
+        let signer_client = client.proxy().contract().client();
+        let transport: &AttpsTransport = signer_client.provider().as_ref();
+        let mut logs = LogStream::spawn(transport.ws_urls(), Filter::new().address(vec![manager, proxy]), start)?;
+
+        let mut wait = self.deliver_due().await?;
+        loop {
+            let sleep = tokio::time::sleep(wait.unwrap_or(Duration::from_secs(3600)));
+            tokio::select! {
+                log = logs.next() => {
+                    let log = log.ok_or("The event stream ended")?;
+                    if let Some(event) = WebhookEvent::from_log(manager, proxy, &log) {
+                        if log.removed == Some(true) {
+                            self.cancel(&event)?;
+                        } else {
+                            self.enqueue(&event)?;
+                        }
+                    }
+                }
+                _ = sleep => {}
+            }
+            wait = self.deliver_due().await?;
+        }
+    }
+
+    /// Drops the unsent deliveries of an event whose log was removed by a reorg.
+    fn cancel(&self, event: &WebhookEvent) -> ApiResult<()> {
+        let mut state = self.state.lock().unwrap();
+        let before = state.pending.len();
+        state.pending.retain(|delivery| delivery.event.id != event.id);
+        let cancelled = before - state.pending.len();
+        if cancelled > 0 {
+            tracing::warn!(id = %event.id, cancelled, "Cancelled deliveries of a reorged event");
+        }
+        self.save(&state)
+    }
+
+    async fn send(&self, endpoint: &WebhookEndpoint, event: &WebhookEvent) -> ApiResult<()> {
+        let body = match endpoint.format {
+            WebhookFormat::Json => serde_json::to_vec(event),
+            WebhookFormat::Slack => serde_json::to_vec(&json!({ "text": event.summary() })),
+        }
+        .map_err(|e| format!("Failed to encode webhook: {}", e))?;
+        let timestamp = unix_millis() / 1000;
+
+        let response = self
+            .http
+            .post(&endpoint.url)
+            .header("content-type", "application/json")
+            .header("x-attps-event", &event.kind)
+            .header("x-attps-delivery", &event.id)
+            .header("x-attps-timestamp", timestamp.to_string())
+            .header("x-attps-signature", webhook_signature(&endpoint.secret, timestamp, &body))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status()));
+        }
+        Ok(())
+    }
+
+    fn save(&self, state: &DispatchState) -> ApiResult<()> {
+        let Some(path) = &self.state_path else {
+            return Ok(());
+        };
+        let contents = serde_json::to_string(state).map_err(|e| format!("Failed to encode webhook state: {}", e))?;
+        // write then rename, so a crash never leaves a half-written file
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, contents)
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .map_err(|e| format!("Failed to write webhook state {}: {}", path.display(), e))
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_millis() as u64).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use ethers::contract::EthEvent;
+
+    use crate::svc::agent_manager_node::AgentRemovedFilter;
+    use crate::utils::mock_server::{MockRequest, MockResponse, MockServer};
+
+    #[tokio::test]
+    async fn test_signed_delivery_with_retries() {
+        let (manager, proxy, agent) = (Address::repeat_byte(2), Address::repeat_byte(1), Address::repeat_byte(0xaa));
+        let log = Log {
+            address: manager,
+            topics: vec![AgentRemovedFilter::signature(), H256::from(agent)],
+            block_number: Some(42.into()),
+            transaction_hash: Some(H256::repeat_byte(0x77)),
+            log_index: Some(3.into()),
+            ..Default::default()
+        };
+        let event = WebhookEvent::from_log(manager, proxy, &log).unwrap();
+        assert_eq!(event.kind, "agent_removed");
+        assert_eq!(event.data["agent"], json!(agent));
+        assert!(WebhookEvent::from_log(Address::repeat_byte(9), proxy, &log).is_none());
+
+        // the receiver fails the first request
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let requests = Arc::new(AtomicUsize::new(0));
+        let (store, counter) = (received.clone(), requests.clone());
+        let receiver = MockServer::start(Arc::new(move |request: MockRequest| {
+            if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                return MockResponse::new(500, "text/plain", "boom");
+            }
+            store.lock().unwrap().push(request);
+            MockResponse::new(200, "text/plain", "ok")
+        }))
+        .await
+        .unwrap();
+
+        let secret = SecretKey::from("webhook-secret");
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join("webhooks.json");
+        let dispatcher = WebhookDispatcher::new(vec![
+            WebhookEndpoint { url: receiver.url(), secret: secret.clone(), format: WebhookFormat::Json, events: vec![] },
+            WebhookEndpoint {
+                url: "http://127.0.0.1:1/unused".to_string(),
+                secret: secret.clone(),
+                format: WebhookFormat::Slack,
+                events: vec!["agent_registered".to_string()],
+            },
+        ])
+        .unwrap()
+        .with_retries(3, Duration::from_millis(20))
+        .with_state_file(&state_path)
+        .unwrap();
+
+        dispatcher.enqueue(&event).unwrap();
+        assert_eq!(dispatcher.pending(), 1);
+        let wait = dispatcher.deliver_due().await.unwrap().unwrap();
+        assert!(wait <= Duration::from_millis(20));
+
+        // a restart keeps the retry
+        let dispatcher = WebhookDispatcher::new(dispatcher.endpoints.clone())
+            .unwrap()
+            .with_retries(3, Duration::from_millis(20))
+            .with_state_file(&state_path)
+            .unwrap();
+        assert_eq!(dispatcher.pending(), 1);
+        tokio::time::sleep(wait).await;
+        assert_eq!(dispatcher.deliver_due().await.unwrap(), None);
+        assert_eq!(dispatcher.pending(), 0);
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let header = |name: &str| received[0].headers.iter().find(|(key, _)| key == name).unwrap().1.clone();
+        assert_eq!(header("x-attps-event"), "agent_removed");
+        let timestamp = header("x-attps-timestamp").parse().unwrap();
+        assert!(verify_webhook_signature(&secret, timestamp, &received[0].body, &header("x-attps-signature")));
+        assert!(!verify_webhook_signature(&SecretKey::from("other"), timestamp, &received[0].body, &header("x-attps-signature")));
+        assert_eq!(serde_json::from_slice::<WebhookEvent>(&received[0].body).unwrap(), event);
+
+        let state: Value = serde_json::from_str(&std::fs::read_to_string(&state_path).unwrap()).unwrap();
+        assert_eq!(state["next_block"], json!(42));
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_and_reorged_events() {
+        let removed = |block: u64, index: u64| {
+            let log = Log {
+                address: Address::repeat_byte(2),
+                topics: vec![AgentRemovedFilter::signature(), H256::from(Address::repeat_byte(0xaa))],
+                block_number: Some(block.into()),
+                transaction_hash: Some(H256::from_low_u64_be(block)),
+                log_index: Some(index.into()),
+                ..Default::default()
+            };
+            WebhookEvent::from_log(Address::repeat_byte(2), Address::repeat_byte(1), &log).unwrap()
+        };
+        let (event, reorged) = (removed(42, 0), removed(43, 0));
+
+        // nothing listens on port 1
+        let endpoints = vec![WebhookEndpoint {
+            url: "http://127.0.0.1:1/hook".to_string(),
+            secret: SecretKey::from("webhook-secret"),
+            format: WebhookFormat::Json,
+            events: vec![],
+        }];
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join("webhooks.json");
+        let dispatcher = WebhookDispatcher::new(endpoints.clone())
+            .unwrap()
+            .with_retries(1, Duration::ZERO)
+            .with_state_file(&state_path)
+            .unwrap();
+
+        dispatcher.enqueue(&event).unwrap();
+        dispatcher.enqueue(&reorged).unwrap();
+        dispatcher.cancel(&reorged).unwrap();
+        assert_eq!(dispatcher.pending(), 1);
+        assert_eq!(dispatcher.deliver_due().await.unwrap(), None);
+        assert_eq!(dispatcher.pending(), 0);
+
+        // dead letters survive a restart and can be queued again
+        let dispatcher = WebhookDispatcher::new(endpoints).unwrap().with_state_file(&state_path).unwrap();
+        let dead_letters = dispatcher.dead_letters();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!((dead_letters[0].event.id.as_str(), dead_letters[0].attempts), (event.id.as_str(), 1));
+        assert_eq!(dispatcher.retry_dead_letters().unwrap(), 1);
+        assert_eq!(dispatcher.pending(), 1);
+        assert!(dispatcher.dead_letters().is_empty());
+        assert_eq!(dispatcher.clear_dead_letters().unwrap(), 0);
+    }
+}
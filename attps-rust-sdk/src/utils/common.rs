@@ -1,56 +1,178 @@
 use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+use std::time::Instant;
 
 use ethers::prelude::*;
-use ethers::signers::LocalWallet;
-use ethers::utils::keccak256;
 use hex;
 
+use crate::core::metrics;
+use crate::core::ApiResult;
+use crate::svc::agent_factory_node::AGENTFACTORYCONTRACT_ABI;
+use crate::svc::agent_manager_node::AGENTMANAGERCONTRACT_ABI;
+use crate::svc::agent_proxy_node::AGENTPROXYCONTRACT_ABI;
+use crate::utils::secret::SecretKey;
+use crate::utils::signer::ProofSigner;
+
 pub async fn generate_signature_proof(
     message: &str,
-    private_keys: Vec<&str>,
+    private_keys: Vec<&SecretKey>,
 ) -> Result<String, Box<dyn Error>> {
-    let mut rs_array = Vec::new();
-    let mut ss_array = Vec::new();
-    let mut v_array = Vec::new();
-
-    let message_bytes = message.as_bytes();
+    let mut wallets = Vec::new();
+    for private_key in private_keys {
+        wallets.push(private_key.to_wallet()?);
+    }
 
-    let message_hash = keccak256(message_bytes);
+    generate_signature_proof_with_signers(message, &wallets).await
+}
 
-    for private_key in private_keys {
-        let wallet: LocalWallet = private_key.parse()?;
+#[tracing::instrument(skip_all, fields(signers = signers.len()))]
+pub async fn generate_signature_proof_with_signers<S: ProofSigner>(
+    message: &str,
+    signers: &[S],
+) -> Result<String, Box<dyn Error>> {
+    let started = Instant::now();
+    let mut signatures = Vec::new();
 
+    for signer in signers {
         // sign message hash
-        let signature = wallet.sign_hash(H256::from(message_hash))?;
+        match signer.sign_proof(message.as_bytes()).await {
+            Ok(signature) => signatures.push(signature),
+            Err(e) => {
+                metrics::observe_proof(started.elapsed(), false);
+                return Err(e.into());
+            }
+        }
+    }
+
+    let proof = encode_signature_proof(&signatures);
+    metrics::observe_proof(started.elapsed(), true);
+    Ok(proof)
+}
+
+pub(crate) fn parse_address(address: &str) -> ApiResult<Address> {
+    address.parse::<Address>().map_err(|e| format!("Invalid address format: {}", e))
+}
+
+/// Opens an append-only journal. A torn last line left by a crash is cut off first, so
+/// the next entry starts on a line of its own.
+pub(crate) fn open_journal(path: &Path) -> std::io::Result<File> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let contents = std::fs::read(path)?;
+    let end = contents.iter().rposition(|byte| *byte == b'\n').map_or(0, |index| index + 1);
+    if end < contents.len() {
+        file.set_len(end as u64)?;
+        file.sync_data()?;
+    }
+    Ok(file)
+}
 
+pub fn encode_signature_proof(signatures: &[Signature]) -> String {
+    let mut rs_array = Vec::new();
+    let mut ss_array = Vec::new();
+    let mut v_array = Vec::new();
+
+    for signature in signatures {
         // extract r, s, v
         let mut r_bytes = [0u8; 32];
         let mut s_bytes = [0u8; 32];
         signature.r.to_big_endian(&mut r_bytes);
         signature.s.to_big_endian(&mut s_bytes);
-        let r = hex::encode(r_bytes);
-        let s = hex::encode(s_bytes);
-        let v = if signature.v == 27 { 0u8 } else { 1u8 };
+        // signers may report v either as a recovery id or in the 27/28 form
+        let v = if signature.v >= 27 { signature.v - 27 } else { signature.v };
 
-        rs_array.push(r);
-        ss_array.push(s);
-        v_array.push(v);
+        rs_array.push(ethers::abi::Token::FixedBytes(r_bytes.to_vec()));
+        ss_array.push(ethers::abi::Token::FixedBytes(s_bytes.to_vec()));
+        v_array.push(ethers::abi::Token::Uint(ethers::types::U256::from(v)));
     }
 
     // encode to signatureProof format
     let signature_proof = ethers::abi::encode(&[
-        ethers::abi::Token::Array(
-            rs_array.iter().map(|r| ethers::abi::Token::FixedBytes(hex::decode(r).unwrap())).collect()
-        ),
-        ethers::abi::Token::Array(
-            ss_array.iter().map(|s| ethers::abi::Token::FixedBytes(hex::decode(s).unwrap())).collect()
-        ),
-        ethers::abi::Token::Array(
-            v_array.iter().map(|&v| ethers::abi::Token::Uint(ethers::types::U256::from(v))).collect()
-        ),
+        ethers::abi::Token::Array(rs_array),
+        ethers::abi::Token::Array(ss_array),
+        ethers::abi::Token::Array(v_array),
     ]);
 
-    let signature_proof_hex = format!("0x{}", hex::encode(signature_proof));
+    format!("0x{}", hex::encode(signature_proof))
+}
+
+pub fn decode_revert_data(data: &[u8]) -> String {
+    if data.is_empty() {
+        return "execution reverted".to_string();
+    }
+    if let Some(reason) = <String as EthError>::decode_with_selector(data) {
+        return reason;
+    }
+
+    // the proxy forwards reverts from the manager and factory, so all three ABIs are searched
+    let abis = [&*AGENTPROXYCONTRACT_ABI, &*AGENTMANAGERCONTRACT_ABI, &*AGENTFACTORYCONTRACT_ABI];
+    for abi in abis {
+        for error in abi.errors() {
+            if data.len() >= 4 && error.signature()[..4] == data[..4] {
+                if let Ok(tokens) = error.decode(&data[4..]) {
+                    let args: Vec<String> = tokens.iter().map(|t| t.to_string()).collect();
+                    return format!("{}({})", error.name, args.join(", "));
+                }
+            }
+        }
+    }
+
+    format!("unknown revert 0x{}", hex::encode(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use ethers::utils::keccak256;
+
+    use crate::core::ApiResult;
+
+    const TEST_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    struct RecoveryIdSigner {
+        wallet: LocalWallet,
+    }
 
-    Ok(signature_proof_hex)
-}
\ No newline at end of file
+    #[async_trait]
+    impl ProofSigner for RecoveryIdSigner {
+        fn address(&self) -> Address {
+            Signer::address(&self.wallet)
+        }
+
+        async fn sign_proof(&self, message: &[u8]) -> ApiResult<Signature> {
+            let mut signature = self.wallet.sign_proof(message).await?;
+            // report v as a bare recovery id, like most remote signers do
+            signature.v -= 27;
+            Ok(signature)
+        }
+    }
+
+    #[test]
+    fn test_decode_revert_data() {
+        let invalid_agent = &ethers::utils::id("InvalidAgent()")[..];
+        assert_eq!(decode_revert_data(invalid_agent), "InvalidAgent()");
+
+        let reason = ethers::abi::AbiEncode::encode("not allowed".to_string());
+        let mut data = ethers::utils::id("Error(string)").to_vec();
+        data.extend(reason);
+        assert_eq!(decode_revert_data(&data), "not allowed");
+
+        assert_eq!(decode_revert_data(&[]), "execution reverted");
+        assert_eq!(decode_revert_data(&[0xde, 0xad, 0xbe, 0xef]), "unknown revert 0xdeadbeef");
+    }
+
+    #[tokio::test]
+    async fn test_custom_signer_matches_private_key_proof() {
+        let wallet: LocalWallet = TEST_KEY.parse().unwrap();
+        let from_keys = generate_signature_proof("hello world", vec![&SecretKey::from(TEST_KEY)]).await.unwrap();
+        let custom: Vec<Box<dyn ProofSigner>> = vec![Box::new(RecoveryIdSigner { wallet: wallet.clone() })];
+        let from_signers = generate_signature_proof_with_signers("hello world", &custom).await.unwrap();
+
+        assert_eq!(from_keys, from_signers);
+
+        let signature = wallet.sign_proof(b"hello world").await.unwrap();
+        let recovered = signature.recover(RecoveryMessage::Hash(H256::from(keccak256("hello world")))).unwrap();
+        assert_eq!(recovered, Signer::address(&wallet));
+    }
+}
@@ -0,0 +1,116 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::core::ApiResult;
+
+pub(crate) struct MockRequest {
+    pub method: String,
+    pub path: String,
+    /// Header names are lowercase.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+pub(crate) struct MockResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockResponse {
+    pub fn new(status: u16, content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self { status, content_type, headers: Vec::new(), body: body.into() }
+    }
+}
+
+pub(crate) type MockHandler = Arc<dyn Fn(MockRequest) -> MockResponse + Send + Sync>;
+
+/// A minimal HTTP/1.1 server on a random local port for offline tests of the SDK's
+/// HTTP clients. It handles one request per connection.
+pub(crate) struct MockServer {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub async fn start(handler: MockHandler) -> ApiResult<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(|e| format!("Failed to bind mock server: {}", e))?;
+        let addr = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to bind mock server: {}", e))?;
+
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                tokio::spawn(async move {
+                    let _ = handle_connection(stream, handler).await;
+                });
+            }
+        });
+
+        Ok(Self { addr, task })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn handle_connection(stream: TcpStream, handler: MockHandler) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = 0;
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.trim_end().split_once(':') {
+            if key.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+            headers.push((key.to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).await?;
+
+    let response = handler(MockRequest { method, path, headers, body });
+
+    let mut stream = reader.into_inner();
+    let mut head = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&response.body).await?;
+    stream.shutdown().await
+}
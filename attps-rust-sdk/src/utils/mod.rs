@@ -1 +1,6 @@
-pub mod common;
\ No newline at end of file
+pub mod common;
+pub(crate) mod mock_server;
+pub mod remote_signer;
+pub mod secret;
+pub mod signer;
+pub mod wallet;
\ No newline at end of file
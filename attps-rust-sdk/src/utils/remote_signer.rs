@@ -0,0 +1,250 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use ethers::prelude::*;
+use ethers::signers::LocalWallet;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::transaction::eip712::Eip712;
+use ethers::signers::to_eip155_v;
+use ethers::utils::keccak256;
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::core::ApiResult;
+use crate::utils::mock_server::{MockHandler, MockRequest, MockResponse, MockServer};
+use crate::utils::signer::{ProofSigner, SignerError};
+
+/// A signer backed by a Web3Signer-compatible HTTP service.
+///
+/// Keys are listed with `GET /api/v1/eth1/publicKeys` and used through
+/// `POST /api/v1/eth1/sign/{publicKey}`, which signs `keccak256(data)`. Transactions,
+/// EIP-191 messages and proofs are all reduced to that one call, so the key never
+/// leaves the service.
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    client: reqwest::Client,
+    base_url: Url,
+    public_key: String,
+    address: Address,
+    chain_id: u64,
+}
+
+#[derive(Deserialize)]
+struct SignRequest {
+    data: String,
+}
+
+impl RemoteSigner {
+    pub async fn connect(base_url: &str, address: &str) -> ApiResult<Self> {
+        let base_url = Url::parse(base_url).map_err(|e| format!("Invalid remote signer URL: {}", e))?;
+        let address = address.parse::<Address>().map_err(|e| {
+            format!("Invalid remote signer address format: {}", e)
+        })?;
+        let client = reqwest::Client::new();
+
+        let url = base_url
+            .join("api/v1/eth1/publicKeys")
+            .map_err(|e| format!("Invalid remote signer URL: {}", e))?;
+        let public_keys: Vec<String> = client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Failed to list remote signer keys: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid remote signer key list: {}", e))?;
+
+        let public_key = public_keys
+            .into_iter()
+            .find(|public_key| public_key_to_address(public_key) == Some(address))
+            .ok_or_else(|| format!("Remote signer has no key for {:?}", address))?;
+
+        Ok(Self { client, base_url, public_key, address, chain_id: 1 })
+    }
+
+    pub async fn sign_data(&self, data: &[u8]) -> Result<Signature, SignerError> {
+        let url = self
+            .base_url
+            .join(&format!("api/v1/eth1/sign/{}", self.public_key))
+            .map_err(|e| SignerError(format!("Invalid remote signer URL: {}", e)))?;
+
+        let response = self
+            .client
+            .post(url)
+            .json(&serde_json::json!({ "data": format!("0x{}", hex::encode(data)) }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| SignerError(format!("Remote signer request failed: {}", e)))?;
+        let body = response
+            .text()
+            .await
+            .map_err(|e| SignerError(format!("Remote signer request failed: {}", e)))?;
+
+        let signature_hex = body.trim().trim_matches('"');
+        let signature = hex::decode(signature_hex.strip_prefix("0x").unwrap_or(signature_hex))
+            .ok()
+            .and_then(|bytes| Signature::try_from(bytes.as_slice()).ok())
+            .ok_or_else(|| SignerError("Remote signer returned a malformed signature".to_string()))?;
+
+        // never hand out a signature the service made with some other key
+        let hash = H256::from(keccak256(data));
+        match signature.recover(RecoveryMessage::Hash(hash)) {
+            Ok(recovered) if recovered == self.address => Ok(signature),
+            _ => Err(SignerError(format!(
+                "Remote signer returned a signature that does not recover to {:?}",
+                self.address
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl Signer for RemoteSigner {
+    type Error = SignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(&self, message: S) -> Result<Signature, Self::Error> {
+        let message = message.as_ref();
+        let mut data = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+        data.extend_from_slice(message);
+        self.sign_data(&data).await
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        let mut tx = tx.clone();
+        let chain_id = tx.chain_id().map(|id| id.as_u64()).unwrap_or(self.chain_id);
+        tx.set_chain_id(chain_id);
+
+        let mut signature = self.sign_data(&tx.rlp()).await?;
+        let recovery_id = if signature.v >= 27 { signature.v - 27 } else { signature.v };
+        signature.v = to_eip155_v(recovery_id as u8, chain_id);
+        Ok(signature)
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(&self, _payload: &T) -> Result<Signature, Self::Error> {
+        Err(SignerError("Remote signer does not support typed data".to_string()))
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    fn with_chain_id<T: Into<u64>>(mut self, chain_id: T) -> Self {
+        self.chain_id = chain_id.into();
+        self
+    }
+}
+
+#[async_trait]
+impl ProofSigner for RemoteSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_proof(&self, message: &[u8]) -> ApiResult<Signature> {
+        self.sign_data(message).await.map_err(|e| e.to_string())
+    }
+}
+
+fn public_key_to_address(public_key: &str) -> Option<Address> {
+    let bytes = hex::decode(public_key.strip_prefix("0x").unwrap_or(public_key)).ok()?;
+    // accept both the bare 64-byte form and the SEC1 form with its 0x04 tag
+    let bytes = match bytes.len() {
+        64 => &bytes[..],
+        65 if bytes[0] == 4 => &bytes[1..],
+        _ => return None,
+    };
+    Some(Address::from_slice(&keccak256(bytes)[12..]))
+}
+
+/// An in-process Web3Signer stand-in holding local wallets, for testing remote signing offline.
+pub struct MockRemoteSigner {
+    server: MockServer,
+}
+
+impl MockRemoteSigner {
+    pub async fn start(wallets: Vec<LocalWallet>) -> ApiResult<Self> {
+        let keys: Vec<(String, LocalWallet)> = wallets
+            .into_iter()
+            .map(|wallet| {
+                let point = wallet.signer().verifying_key().to_encoded_point(false);
+                (format!("0x{}", hex::encode(&point.as_bytes()[1..])), wallet)
+            })
+            .collect();
+
+        let handler: MockHandler = Arc::new(move |request: MockRequest| {
+            if request.method == "GET" && request.path == "/upcheck" {
+                return MockResponse::new(200, "text/plain", "OK");
+            }
+            if request.method == "GET" && request.path == "/api/v1/eth1/publicKeys" {
+                let public_keys: Vec<&String> = keys.iter().map(|(public_key, _)| public_key).collect();
+                return MockResponse::new(200, "application/json", serde_json::to_vec(&public_keys).unwrap_or_default());
+            }
+
+            let Some(public_key) = request.path.strip_prefix("/api/v1/eth1/sign/") else {
+                return MockResponse::new(404, "text/plain", "Not Found");
+            };
+            let Some((_, wallet)) = keys.iter().find(|(key, _)| key.eq_ignore_ascii_case(public_key)) else {
+                return MockResponse::new(404, "text/plain", "Public Key not found");
+            };
+            let data = serde_json::from_slice::<SignRequest>(&request.body)
+                .ok()
+                .and_then(|body| hex::decode(body.data.trim_start_matches("0x")).ok());
+            let Some(data) = data else {
+                return MockResponse::new(400, "text/plain", "Bad Request");
+            };
+
+            match wallet.sign_hash(H256::from(keccak256(&data))) {
+                Ok(signature) => MockResponse::new(200, "text/plain", format!("0x{}", signature)),
+                Err(_) => MockResponse::new(500, "text/plain", "Internal Server Error"),
+            }
+        });
+
+        Ok(Self { server: MockServer::start(handler).await? })
+    }
+
+    pub fn url(&self) -> String {
+        self.server.url()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::common::{generate_signature_proof, generate_signature_proof_with_signers};
+    use crate::utils::secret::SecretKey;
+
+    const TEST_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    #[tokio::test]
+    async fn test_remote_signer_against_mock() {
+        let wallet: LocalWallet = TEST_KEY.parse().unwrap();
+        let mock = MockRemoteSigner::start(vec![wallet.clone()]).await.unwrap();
+        let address = format!("{:?}", Signer::address(&wallet));
+
+        let signer = RemoteSigner::connect(&mock.url(), &address).await.unwrap().with_chain_id(11155111u64);
+
+        let local_proof = generate_signature_proof("hello world", vec![&SecretKey::from(TEST_KEY)]).await.unwrap();
+        let remote_proof = generate_signature_proof_with_signers("hello world", std::slice::from_ref(&signer)).await.unwrap();
+        assert_eq!(local_proof, remote_proof);
+
+        let message_signature = signer.sign_message("hello").await.unwrap();
+        assert_eq!(message_signature, wallet.sign_message("hello").await.unwrap());
+
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(Signer::address(&wallet))
+            .nonce(3)
+            .chain_id(11155111u64)
+            .into();
+        let tx_signature = signer.sign_transaction(&tx).await.unwrap();
+        assert_eq!(tx_signature, wallet.with_chain_id(11155111u64).sign_transaction(&tx).await.unwrap());
+
+        let err = RemoteSigner::connect(&mock.url(), "0x0000000000000000000000000000000000000001").await.unwrap_err();
+        assert!(err.starts_with("Remote signer has no key for"));
+    }
+}
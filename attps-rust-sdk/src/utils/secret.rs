@@ -0,0 +1,179 @@
+use std::cell::Cell;
+use std::fmt;
+use std::str::FromStr;
+
+use ethers::core::k256::ecdsa::SigningKey;
+use ethers::signers::LocalWallet;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::Zeroizing;
+
+use crate::core::ApiResult;
+
+/// A hex-encoded private key that is wiped from memory on drop and never printed. Also
+/// holds other key material, such as a wallet mnemonic.
+///
+/// Debug and Display both show `<redacted>`, and parse errors never echo the input,
+/// so a key can be logged or put in an error by accident without leaking it.
+#[derive(Clone)]
+pub struct SecretKey(Zeroizing<String>);
+
+impl SecretKey {
+    pub fn new(private_key: impl Into<String>) -> Self {
+        Self(Zeroizing::new(private_key.into()))
+    }
+
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+
+    pub fn to_wallet(&self) -> ApiResult<LocalWallet> {
+        let hex_key = self.0.trim();
+        let hex_key = hex_key.strip_prefix("0x").unwrap_or(hex_key);
+        let bytes = Zeroizing::new(hex::decode(hex_key).map_err(|_| "Invalid private key".to_string())?);
+        SigningKey::from_slice(&bytes)
+            .map(LocalWallet::from)
+            .map_err(|_| "Invalid private key".to_string())
+    }
+}
+
+impl From<String> for SecretKey {
+    fn from(private_key: String) -> Self {
+        Self::new(private_key)
+    }
+}
+
+impl From<&str> for SecretKey {
+    fn from(private_key: &str) -> Self {
+        Self::new(private_key)
+    }
+}
+
+impl FromStr for SecretKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = Self::new(s);
+        key.to_wallet()?;
+        Ok(key)
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretKey(<redacted>)")
+    }
+}
+
+impl fmt::Display for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+/// What a `SecretKey` serializes as outside of [`expose_secrets`].
+pub const REDACTED: &str = "[REDACTED]";
+
+thread_local! {
+    static EXPOSE_SECRETS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `f` with every `SecretKey` serialized in full on this thread. Only for writing
+/// config files; everywhere else a key serializes as [`REDACTED`].
+pub fn expose_secrets<T>(f: impl FnOnce() -> T) -> T {
+    struct Reset(bool);
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            EXPOSE_SECRETS.with(|expose| expose.set(self.0));
+        }
+    }
+
+    let _reset = Reset(EXPOSE_SECRETS.with(|expose| expose.replace(true)));
+    f()
+}
+
+impl Serialize for SecretKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if EXPOSE_SECRETS.with(Cell::get) {
+            serializer.serialize_str(&self.0)
+        } else {
+            serializer.serialize_str(REDACTED)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        if value == REDACTED {
+            return Err(serde::de::Error::custom("secret was redacted when this file was written"));
+        }
+        Ok(Self::new(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::AttpsConfig;
+    use crate::svc::agent_proxy_node::AgentProxyNode;
+    use crate::utils::common::generate_signature_proof;
+    use ethers::signers::Signer;
+
+    const TEST_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    // a valid-looking key one character short, so it fails to parse
+    const BAD_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff8";
+
+    #[tokio::test]
+    async fn test_keys_never_appear_in_output() {
+        let key = SecretKey::from(TEST_KEY);
+        assert_eq!(
+            key.to_wallet().unwrap().address(),
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap()
+        );
+        assert!(!format!("{:?} {}", key, key).contains(TEST_KEY));
+        assert!(!format!("{:?}", AttpsConfig::new("http://127.0.0.1:8545", "0x590CDE19Ee0FbA69A72D37d1B8C3474f327C8Eec", Some(&key)).unwrap())
+            .contains(TEST_KEY));
+
+        let bad = SecretKey::from(BAD_KEY);
+        let errors = vec![
+            bad.expose_secret().parse::<SecretKey>().unwrap_err(),
+            format!("0x{}zz", TEST_KEY).parse::<SecretKey>().unwrap_err(),
+            AttpsConfig::new("http://127.0.0.1:8545", "0x590CDE19Ee0FbA69A72D37d1B8C3474f327C8Eec", Some(&bad)).unwrap_err(),
+            AgentProxyNode::try_new("http://127.0.0.1:8545", "0x590CDE19Ee0FbA69A72D37d1B8C3474f327C8Eec", &bad)
+                .await
+                .err()
+                .unwrap(),
+            generate_signature_proof("hello world", vec![&bad]).await.unwrap_err().to_string(),
+        ];
+        for error in errors {
+            assert!(error.contains("Invalid private key"), "{}", error);
+            assert!(!error.contains(BAD_KEY) && !error.contains(TEST_KEY), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_serialize_redacts_outside_config_save() {
+        use crate::core::config::{ConfigFile, ProfileConfig};
+        use crate::utils::wallet::WalletSource;
+
+        let mut file = ConfigFile::default();
+        file.profiles.insert("local".to_string(), ProfileConfig {
+            agent_proxy_contract: Some("0x590CDE19Ee0FbA69A72D37d1B8C3474f327C8Eec".to_string()),
+            wallet_private_key: Some(SecretKey::from(TEST_KEY)),
+            ..Default::default()
+        });
+
+        let dump = serde_json::to_string(&file).unwrap();
+        assert!(!dump.contains(TEST_KEY) && dump.contains(REDACTED), "{}", dump);
+        assert!(serde_json::from_str::<ConfigFile>(&dump).unwrap_err().to_string().contains("redacted"));
+
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["config.toml", "config.json"] {
+            let path = dir.path().join(name);
+            file.save(&path).unwrap();
+            let loaded = AttpsConfig::from_file(&path, Some("local")).unwrap();
+            assert!(matches!(loaded.wallet, Some(WalletSource::PrivateKey(ref key)) if key.expose_secret() == TEST_KEY));
+        }
+        assert!(serde_json::to_string(&file).unwrap().contains(REDACTED));
+    }
+}
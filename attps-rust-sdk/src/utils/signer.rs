@@ -0,0 +1,171 @@
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use ethers::core::k256::ecdsa::SigningKey;
+use ethers::prelude::*;
+use ethers::signers::Wallet;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::transaction::eip712::Eip712;
+use ethers::utils::keccak256;
+
+use crate::core::ApiResult;
+use crate::utils::remote_signer::RemoteSigner;
+
+/// Signs proof messages for `AgentProxy.verify`.
+///
+/// The proxy recovers signers from the bare `keccak256(message)` hash, without the
+/// EIP-191 prefix that `ethers::signers::Signer::sign_message` applies, so any key
+/// backend used for proofs has to implement this trait instead.
+#[async_trait]
+pub trait ProofSigner: Send + Sync {
+    fn address(&self) -> Address;
+
+    async fn sign_proof(&self, message: &[u8]) -> ApiResult<Signature>;
+}
+
+#[async_trait]
+impl ProofSigner for Wallet<SigningKey> {
+    fn address(&self) -> Address {
+        Signer::address(self)
+    }
+
+    async fn sign_proof(&self, message: &[u8]) -> ApiResult<Signature> {
+        self.sign_hash(H256::from(keccak256(message)))
+            .map_err(|e| format!("Failed to sign proof: {}", e))
+    }
+}
+
+#[async_trait]
+impl<T: ProofSigner + ?Sized> ProofSigner for &T {
+    fn address(&self) -> Address {
+        (**self).address()
+    }
+
+    async fn sign_proof(&self, message: &[u8]) -> ApiResult<Signature> {
+        (**self).sign_proof(message).await
+    }
+}
+
+#[async_trait]
+impl<T: ProofSigner + ?Sized> ProofSigner for Box<T> {
+    fn address(&self) -> Address {
+        (**self).address()
+    }
+
+    async fn sign_proof(&self, message: &[u8]) -> ApiResult<Signature> {
+        (**self).sign_proof(message).await
+    }
+}
+
+#[async_trait]
+impl<T: ProofSigner + ?Sized> ProofSigner for Arc<T> {
+    fn address(&self) -> Address {
+        (**self).address()
+    }
+
+    async fn sign_proof(&self, message: &[u8]) -> ApiResult<Signature> {
+        (**self).sign_proof(message).await
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignerError(pub String);
+
+impl fmt::Display for SignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SignerError {}
+
+/// The transaction and proof signer used by the nodes, holding either an in-process
+/// wallet or a handle to a remote signing service.
+#[derive(Debug, Clone)]
+pub enum AttpsSigner {
+    Local(Wallet<SigningKey>),
+    Remote(RemoteSigner),
+}
+
+impl From<Wallet<SigningKey>> for AttpsSigner {
+    fn from(wallet: Wallet<SigningKey>) -> Self {
+        AttpsSigner::Local(wallet)
+    }
+}
+
+impl From<RemoteSigner> for AttpsSigner {
+    fn from(signer: RemoteSigner) -> Self {
+        AttpsSigner::Remote(signer)
+    }
+}
+
+#[async_trait]
+impl Signer for AttpsSigner {
+    type Error = SignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(&self, message: S) -> Result<Signature, Self::Error> {
+        match self {
+            AttpsSigner::Local(wallet) => wallet
+                .sign_message(message)
+                .await
+                .map_err(|e| SignerError(format!("Failed to sign message: {}", e))),
+            AttpsSigner::Remote(signer) => signer.sign_message(message).await,
+        }
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        match self {
+            AttpsSigner::Local(wallet) => wallet
+                .sign_transaction(tx)
+                .await
+                .map_err(|e| SignerError(format!("Failed to sign transaction: {}", e))),
+            AttpsSigner::Remote(signer) => signer.sign_transaction(tx).await,
+        }
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(&self, payload: &T) -> Result<Signature, Self::Error> {
+        match self {
+            AttpsSigner::Local(wallet) => wallet
+                .sign_typed_data(payload)
+                .await
+                .map_err(|e| SignerError(format!("Failed to sign typed data: {}", e))),
+            AttpsSigner::Remote(signer) => signer.sign_typed_data(payload).await,
+        }
+    }
+
+    fn address(&self) -> Address {
+        match self {
+            AttpsSigner::Local(wallet) => Signer::address(wallet),
+            AttpsSigner::Remote(signer) => Signer::address(signer),
+        }
+    }
+
+    fn chain_id(&self) -> u64 {
+        match self {
+            AttpsSigner::Local(wallet) => wallet.chain_id(),
+            AttpsSigner::Remote(signer) => signer.chain_id(),
+        }
+    }
+
+    fn with_chain_id<T: Into<u64>>(self, chain_id: T) -> Self {
+        match self {
+            AttpsSigner::Local(wallet) => AttpsSigner::Local(wallet.with_chain_id(chain_id)),
+            AttpsSigner::Remote(signer) => AttpsSigner::Remote(signer.with_chain_id(chain_id)),
+        }
+    }
+}
+
+#[async_trait]
+impl ProofSigner for AttpsSigner {
+    fn address(&self) -> Address {
+        Signer::address(self)
+    }
+
+    async fn sign_proof(&self, message: &[u8]) -> ApiResult<Signature> {
+        match self {
+            AttpsSigner::Local(wallet) => wallet.sign_proof(message).await,
+            AttpsSigner::Remote(signer) => signer.sign_proof(message).await,
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use ethers::signers::coins_bip39::English;
+use ethers::signers::{LocalWallet, MnemonicBuilder};
+use zeroize::Zeroizing;
+
+use crate::core::ApiResult;
+use crate::utils::remote_signer::RemoteSigner;
+use crate::utils::secret::SecretKey;
+use crate::utils::signer::AttpsSigner;
+
+#[derive(Clone)]
+pub enum PasswordSource {
+    Env(String),
+    File(PathBuf),
+    Prompt,
+    Value(Zeroizing<String>),
+}
+
+impl PasswordSource {
+    pub fn read(&self) -> ApiResult<Zeroizing<String>> {
+        match self {
+            PasswordSource::Env(name) => std::env::var(name)
+                .map(Zeroizing::new)
+                .map_err(|_| format!("Keystore password variable {} is not set", name)),
+            PasswordSource::File(path) => std::fs::read_to_string(path)
+                .map(|password| {
+                    let password = Zeroizing::new(password);
+                    Zeroizing::new(password.trim_end_matches(['\r', '\n']).to_string())
+                })
+                .map_err(|e| format!("Failed to read keystore password file {}: {}", path.display(), e)),
+            PasswordSource::Prompt => rpassword::prompt_password("Keystore password: ")
+                .map(Zeroizing::new)
+                .map_err(|e| format!("Failed to read keystore password: {}", e)),
+            PasswordSource::Value(password) => Ok(password.clone()),
+        }
+    }
+}
+
+impl fmt::Debug for PasswordSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PasswordSource::Env(name) => f.debug_tuple("Env").field(name).finish(),
+            PasswordSource::File(path) => f.debug_tuple("File").field(path).finish(),
+            PasswordSource::Prompt => f.write_str("Prompt"),
+            PasswordSource::Value(_) => f.write_str("Value(<redacted>)"),
+        }
+    }
+}
+
+/// Where the sending wallet's key comes from.
+#[derive(Clone)]
+pub enum WalletSource {
+    PrivateKey(SecretKey),
+    Keystore { path: PathBuf, password: PasswordSource },
+    Mnemonic { phrase: Zeroizing<String>, derivation_path: Option<String> },
+    Remote { url: String, address: String },
+}
+
+impl WalletSource {
+    pub fn load(&self) -> ApiResult<LocalWallet> {
+        match self {
+            WalletSource::PrivateKey(private_key) => private_key.to_wallet(),
+            WalletSource::Keystore { path, password } => {
+                let password = password.read()?;
+                LocalWallet::decrypt_keystore(path, password.as_bytes())
+                    .map_err(|e| format!("Failed to decrypt keystore {}: {}", path.display(), e))
+            }
+            WalletSource::Mnemonic { phrase, derivation_path } => {
+                let mut builder = MnemonicBuilder::<English>::default().phrase(phrase.as_str());
+                if let Some(derivation_path) = derivation_path {
+                    builder = builder.derivation_path(derivation_path).map_err(|_| {
+                        format!("Invalid derivation path \"{}\"", derivation_path)
+                    })?;
+                }
+                builder.build().map_err(|_| "Invalid mnemonic".to_string())
+            }
+            WalletSource::Remote { .. } => Err("A remote signer key cannot be loaded locally".to_string()),
+        }
+    }
+
+    pub async fn load_signer(&self) -> ApiResult<AttpsSigner> {
+        match self {
+            WalletSource::Remote { url, address } => RemoteSigner::connect(url, address).await.map(AttpsSigner::from),
+            _ => self.load().map(AttpsSigner::from),
+        }
+    }
+
+    // cheap checks only, so loading a config never decrypts a keystore or prompts
+    pub fn validate(&self) -> ApiResult<()> {
+        match self {
+            WalletSource::Keystore { path, .. } if !path.is_file() => {
+                Err(format!("Keystore file {} does not exist", path.display()))
+            }
+            WalletSource::Keystore { .. } => Ok(()),
+            WalletSource::Remote { url, address } => {
+                reqwest::Url::parse(url).map_err(|e| format!("Invalid remote signer URL \"{}\": {}", url, e))?;
+                address
+                    .parse::<ethers::types::Address>()
+                    .map(|_| ())
+                    .map_err(|e| format!("Invalid remote signer address \"{}\": {}", address, e))
+            }
+            _ => self.load().map(|_| ()),
+        }
+    }
+}
+
+impl fmt::Debug for WalletSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalletSource::PrivateKey(_) => f.write_str("PrivateKey(<redacted>)"),
+            WalletSource::Keystore { path, password } => f
+                .debug_struct("Keystore")
+                .field("path", path)
+                .field("password", password)
+                .finish(),
+            WalletSource::Mnemonic { derivation_path, .. } => f
+                .debug_struct("Mnemonic")
+                .field("phrase", &"<redacted>")
+                .field("derivation_path", derivation_path)
+                .finish(),
+            WalletSource::Remote { url, address } => f
+                .debug_struct("Remote")
+                .field("url", url)
+                .field("address", address)
+                .finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::signers::Signer;
+
+    const TEST_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+    #[test]
+    fn test_keystore_and_mnemonic_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let from_mnemonic = WalletSource::Mnemonic {
+            phrase: Zeroizing::new(TEST_MNEMONIC.to_string()),
+            derivation_path: Some("m/44'/60'/0'/0/1".to_string()),
+        }.load().unwrap();
+        assert_eq!(
+            from_mnemonic.address(),
+            "0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap()
+        );
+
+        let mut rng = ethers::core::rand::thread_rng();
+        let secret = from_mnemonic.signer().to_bytes();
+        LocalWallet::encrypt_keystore(dir.path(), &mut rng, secret, "hunter2", Some("key.json")).unwrap();
+
+        let keystore = WalletSource::Keystore {
+            path: dir.path().join("key.json"),
+            password: PasswordSource::Value(Zeroizing::new("hunter2".to_string())),
+        };
+        keystore.validate().unwrap();
+        assert_eq!(keystore.load().unwrap().address(), from_mnemonic.address());
+        assert!(!format!("{:?}", keystore).contains("hunter2"));
+
+        let wrong_password = WalletSource::Keystore {
+            path: dir.path().join("key.json"),
+            password: PasswordSource::Value(Zeroizing::new("wrong".to_string())),
+        };
+        assert!(wrong_password.load().is_err());
+
+        let missing = WalletSource::Keystore {
+            path: dir.path().join("missing.json"),
+            password: PasswordSource::Prompt,
+        };
+        assert!(missing.validate().unwrap_err().contains("does not exist"));
+    }
+}
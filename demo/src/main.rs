@@ -8,16 +8,13 @@ use tracing_subscriber::EnvFilter;
 
 
 use attps_rust_sdk::{
-    AgentFactoryNode,
-    AgentManagerNode,
-    AgentProxyNode,
-    AGENTPROXY_CONTRACT,
-    AGENTPROXY_PROVIDER,
-    WALLET_PRIVATE_KEY,
+    AttpsClient,
+    AttpsConfig,
     extract_setting_digests,
     generate_signature_proof,
     Metadata,
     Proofs,
+    SecretKey,
 };
 
 #[tokio::main]
@@ -30,33 +27,28 @@ async fn main() {
 
     /*==========read function==========*/
     
-    let agent_proxy_node = AgentProxyNode::new(
-        AGENTPROXY_PROVIDER.as_str(),
-        AGENTPROXY_CONTRACT.as_str(),
-        WALLET_PRIVATE_KEY.as_str(),
-    ).await;
-
-    let agent_factory_address = match agent_proxy_node.get_agent_factory().await {
-        Ok(agent_factory) => {
-            info!("AGENT_FACTORY: {}", agent_factory);
-            agent_factory
-        },
+    let config = match AttpsConfig::from_env() {
+        Ok(config) => config,
         Err(e) => {
             error!("Error: {}", e);
             return;
         }
     };
-
-    let agent_manager_address = match agent_proxy_node.get_agent_manager().await {
-        Ok(agent_manager) => {
-            info!("AGENT_MANAGER: {}", agent_manager);
-            agent_manager
-        },
+    let client = match AttpsClient::from_config(&config).await {
+        Ok(client) => client,
         Err(e) => {
             error!("Error: {}", e);
             return;
         }
     };
+    let agent_proxy_node = client.proxy();
+    let agent_manager_node = client.manager();
+    let agent_factory_node = client.factory();
+
+    let deployment = client.deployment();
+    info!("DEPLOYMENT: {} (chain ID {})", deployment.name, deployment.chain_id);
+    info!("AGENT_FACTORY: {:?}", deployment.agent_factory.unwrap_or_default());
+    info!("AGENT_MANAGER: {:?}", deployment.agent_manager.unwrap_or_default());
 
     match agent_proxy_node.get_owner().await {
         Ok(owner) => info!("OWNER: {}", owner),
@@ -68,12 +60,6 @@ async fn main() {
         Err(e) => error!("Error: {}", e),
     }
 
-    let agent_manager_node = AgentManagerNode::new(
-        AGENTPROXY_PROVIDER.as_str(),
-        agent_manager_address.as_str(),
-        WALLET_PRIVATE_KEY.as_str(),
-    ).await;
-
     match agent_manager_node.get_owner().await {
         Ok(owner) => info!("OWNER: {}", owner),
         Err(e) => error!("Error: {}", e),
@@ -194,12 +180,6 @@ async fn main() {
         }
     }
 
-    let agent_factory_node = AgentFactoryNode::new(
-        AGENTPROXY_PROVIDER.as_str(),
-        agent_factory_address.as_str(),
-        WALLET_PRIVATE_KEY.as_str(),
-    ).await;
-
     match agent_factory_node.get_agent_manager().await {
         Ok(agent_manager) => info!("AGENT_MANAGER: {}", agent_manager),
         Err(e) => error!("Error: {}", e),
@@ -262,10 +242,10 @@ async fn main() {
     /*==========verify==========*/
 
     let message = "hello world";
-    let signer_private_key_1 = env::var("SIGNER_PRIVATE_KEY_1").expect("SIGNER_PRIVATE_KEY_1 not set");
-    let signer_private_key_2 = env::var("SIGNER_PRIVATE_KEY_2").expect("SIGNER_PRIVATE_KEY_2 not set");
+    let signer_private_key_1 = SecretKey::from(env::var("SIGNER_PRIVATE_KEY_1").expect("SIGNER_PRIVATE_KEY_1 not set"));
+    let signer_private_key_2 = SecretKey::from(env::var("SIGNER_PRIVATE_KEY_2").expect("SIGNER_PRIVATE_KEY_2 not set"));
 
-    let private_keys: Vec<&str> = vec![
+    let private_keys: Vec<&SecretKey> = vec![
         &signer_private_key_1,
         &signer_private_key_2,
     ];